@@ -0,0 +1,133 @@
+use std::{marker::PhantomData, path::PathBuf, time::Duration};
+
+use async_std::{net::TcpStream, task::sleep};
+use proto::{
+    discovery,
+    prelude::{host, server, ReadConn, WriteConn, APIVERSION, PROTO_IDENT, SERVER_PORT},
+};
+
+use crate::{
+    platform::Platform,
+    responder::{Power, Responder},
+};
+
+// the server may be restarting, or the network not up yet
+const RECONNECT: Duration = Duration::from_secs(5);
+// mdns answers come within a second on a local network
+const DISCOVER: Duration = Duration::from_secs(10);
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("no network interface with a mac address")]
+    NoMac,
+    #[error("fail to read mac address")]
+    Mac(#[from] mac_address::MacAddressError),
+    #[error("fail to connect")]
+    Io(#[from] std::io::Error),
+    #[error("conn error")]
+    Conn(#[from] proto::prelude::Error),
+    #[error("fail to discover server")]
+    Discovery(#[from] discovery::Error),
+}
+
+pub struct Config {
+    // "host[:port]" of the server, discovered by mdns if none
+    pub server: Option<String>,
+    // file keeping the os id issued by the server
+    pub state: PathBuf,
+    // run with the mac address of a hung machine this agent relays for, to reset it
+    pub reset_hook: Option<PathBuf>,
+    // report firmware applications by bcdedit
+    #[cfg(windows)]
+    pub bcdedit: bool,
+}
+
+impl Config {
+    /// GRUBWOL_SERVER_ADDR, GRUBWOL_STATE, GRUBWOL_RESET_HOOK and GRUBWOL_BCDEDIT,
+    /// services get them from system environment
+    pub fn from_env() -> Self {
+        let default_state = match cfg!(windows) {
+            true => r"C:\ProgramData\grub-wol\uid",
+            false => "/var/lib/grub-wol/uid",
+        };
+        Self {
+            server: std::env::var("GRUBWOL_SERVER_ADDR")
+                .ok()
+                .filter(|server| !server.is_empty()),
+            state: std::env::var("GRUBWOL_STATE")
+                .unwrap_or_else(|_| default_state.to_owned())
+                .into(),
+            reset_hook: std::env::var("GRUBWOL_RESET_HOOK")
+                .ok()
+                .filter(|hook| !hook.is_empty())
+                .map(PathBuf::from),
+            #[cfg(windows)]
+            bcdedit: matches!(
+                std::env::var("GRUBWOL_BCDEDIT").as_deref(),
+                Ok("1" | "true")
+            ),
+        }
+    }
+    async fn server_addr(&self) -> Result<String, Error> {
+        Ok(match &self.server {
+            Some(server) if server.contains(':') => server.clone(),
+            Some(server) => format!("{}:{}", server, SERVER_PORT),
+            None => discovery::discover(DISCOVER).await?.to_string(),
+        })
+    }
+}
+
+/// answer the server until the host goes down, reconnecting whenever the connection is lost
+pub async fn run<P: Platform>(config: Config, platform: P) {
+    let mut responder = Responder::new(platform, config.state.clone(), config.reset_hook.clone()).await;
+    loop {
+        if let Err(err) = serve(&config, &mut responder).await {
+            log::warn!("connection to server lost: {}", err);
+        }
+        sleep(RECONNECT).await;
+    }
+}
+
+async fn serve<P: Platform>(config: &Config, responder: &mut Responder<P>) -> Result<(), Error> {
+    let mac_address = mac_address::get_mac_address()?.ok_or(Error::NoMac)?.bytes();
+    let server = config.server_addr().await?;
+    let stream = TcpStream::connect(&server).await?;
+    let mut reader: ReadConn<_, server::Packet> = ReadConn {
+        data_type: PhantomData,
+        stream: stream.clone(),
+    };
+    let mut writer: WriteConn<_, host::Packet> = WriteConn {
+        data_type: PhantomData,
+        stream,
+    };
+    let handshake = host::Handshake {
+        ident: PROTO_IDENT,
+        mac_address,
+        uid: responder.uid(),
+        version: APIVERSION,
+    };
+    writer.write(host::Packet::Handshake(handshake)).await?;
+    writer.flush().await?;
+    log::info!("connected to {}", server);
+
+    loop {
+        let req = reader.read().await?;
+        log::debug!("received {:?}", req);
+        let (res, power) = match responder.respond(req).await {
+            Some(res) => res,
+            None => continue,
+        };
+        writer.write(res).await?;
+        writer.flush().await?;
+        if let Some(power) = power {
+            let res = match power {
+                Power::Shutdown => responder.platform.shutdown().await,
+                Power::Reboot => responder.platform.reboot().await,
+            };
+            if let Err(err) = res {
+                log::error!("fail to {:?}: {}", power, err);
+            }
+            return Ok(());
+        }
+    }
+}
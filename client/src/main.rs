@@ -1,6 +1,37 @@
+mod agent;
+mod platform;
 mod responder;
-mod state;
+#[cfg(windows)]
+mod service;
+mod uefi;
 
+#[cfg(windows)]
 fn main() {
-    println!("Hello, world!");
+    env_logger::init();
+    // started by the service control manager
+    if std::env::args().any(|arg| arg == "--service") {
+        if let Err(err) = service::run() {
+            log::error!("fail to start service {}: {}", service::SERVICE_NAME, err);
+        }
+        return;
+    }
+    let config = agent::Config::from_env();
+    let platform = platform::windows::Windows {
+        bcdedit: config.bcdedit,
+    };
+    async_std::task::block_on(agent::run(config, platform));
+}
+
+#[cfg(target_os = "linux")]
+fn main() {
+    env_logger::init();
+    let config = agent::Config::from_env();
+    async_std::task::block_on(agent::run(config, platform::linux::Linux::system()));
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+fn main() {
+    env_logger::init();
+    log::error!("the agent only runs on windows and linux");
+    std::process::exit(1);
 }
@@ -0,0 +1,107 @@
+use super::BootEntry;
+
+// the windows boot manager itself, booting it is a plain reboot
+const BOOTMGR: &str = "{bootmgr}";
+
+/// firmware applications loading from disk in `bcdedit /enum firmware`
+///
+/// field names are only english, bcdedit translates them with the display language
+pub fn parse_firmware(output: &str) -> Vec<BootEntry> {
+    output
+        .replace("\r\n", "\n")
+        .split("\n\n")
+        .filter_map(|block| {
+            let (mut id, mut description, mut path) = (None, None, false);
+            // the first line is the title, then the underline
+            for line in block.trim().lines().skip(2) {
+                // lines of a list value are indented
+                if line.starts_with(char::is_whitespace) {
+                    continue;
+                }
+                let (field, value) = match line.split_once(char::is_whitespace) {
+                    Some((field, value)) => (field, value.trim()),
+                    None => continue,
+                };
+                match field {
+                    "identifier" => id = Some(value.to_owned()),
+                    "description" => description = Some(value.to_owned()),
+                    // network and removable entries have no path
+                    "path" => path = true,
+                    _ => {}
+                }
+            }
+            match (id, description) {
+                (Some(id), Some(description)) if path && id != BOOTMGR => {
+                    Some(BootEntry { id, description })
+                }
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// arguments of bcdedit to boot the firmware application once
+pub fn bootsequence_args(entry: &BootEntry) -> [&str; 4] {
+    ["/set", "{fwbootmgr}", "bootsequence", &entry.id]
+}
+
+#[cfg(test)]
+mod test {
+    use super::{bootsequence_args, parse_firmware, BootEntry};
+
+    const OUTPUT: &str = "\r
+Firmware Boot Manager\r
+---------------------\r
+identifier              {fwbootmgr}\r
+displayorder            {bootmgr}\r
+                        {7c3a4b52-7a23-11ee-b962-0242ac120002}\r
+                        {2d5b1c0e-7a23-11ee-b962-0242ac120002}\r
+timeout                 2\r
+\r
+Windows Boot Manager\r
+--------------------\r
+identifier              {bootmgr}\r
+device                  partition=\\Device\\HarddiskVolume1\r
+path                    \\EFI\\Microsoft\\Boot\\bootmgfw.efi\r
+description             Windows Boot Manager\r
+locale                  en-US\r
+\r
+Firmware Application (101fffff)\r
+-------------------------------\r
+identifier              {7c3a4b52-7a23-11ee-b962-0242ac120002}\r
+device                  partition=\\Device\\HarddiskVolume1\r
+path                    \\EFI\\ubuntu\\shimx64.efi\r
+description             ubuntu\r
+\r
+Firmware Application (101fffff)\r
+-------------------------------\r
+identifier              {2d5b1c0e-7a23-11ee-b962-0242ac120002}\r
+description             UEFI: PXE IPv4 Intel(R) Ethernet Connection\r
+";
+
+    #[test]
+    fn firmware() {
+        assert_eq!(
+            parse_firmware(OUTPUT),
+            vec![BootEntry {
+                id: "{7c3a4b52-7a23-11ee-b962-0242ac120002}".to_owned(),
+                description: "ubuntu".to_owned(),
+            }]
+        );
+        assert!(parse_firmware("").is_empty());
+    }
+
+    #[test]
+    fn bootsequence() {
+        let entry = &parse_firmware(OUTPUT)[0];
+        assert_eq!(
+            bootsequence_args(entry),
+            [
+                "/set",
+                "{fwbootmgr}",
+                "bootsequence",
+                "{7c3a4b52-7a23-11ee-b962-0242ac120002}"
+            ]
+        );
+    }
+}
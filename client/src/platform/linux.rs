@@ -0,0 +1,251 @@
+use std::{io, path::PathBuf};
+
+use async_std::{fs, process::Command};
+use async_trait::async_trait;
+use proto::prelude::{host, UefiId};
+
+use super::{BootEntry, Platform};
+use crate::uefi::Efivars;
+
+const OS_RELEASE: &str = "/etc/os-release";
+// grub.cfg and the tool booting one of its entries once, fedora ships grub as grub2
+const GRUB: [(&str, &str); 2] = [
+    ("/boot/grub/grub.cfg", "grub-reboot"),
+    ("/boot/grub2/grub.cfg", "grub2-reboot"),
+];
+// entry of grub entering the firmware setup, which never boots an os
+const FIRMWARE_SETUP: &str = "uefi-firmware";
+
+/// linux booted by grub, grub-reboot only works with GRUB_DEFAULT=saved
+pub struct Linux {
+    // none if grub isn't installed, then no entry is reported
+    grub: Option<(PathBuf, &'static str)>,
+    // none if the host didn't boot by uefi
+    efivars: Option<Efivars>,
+}
+
+impl Linux {
+    pub fn system() -> Self {
+        Self {
+            grub: GRUB
+                .iter()
+                .find(|(cfg, _)| std::path::Path::new(cfg).is_file())
+                .map(|(cfg, reboot)| (cfg.into(), *reboot)),
+            efivars: Efivars::system(),
+        }
+    }
+}
+
+async fn run(program: &str, args: &[&str]) -> io::Result<String> {
+    let output = Command::new(program).args(args).output().await?;
+    match output.status.success() {
+        true => Ok(String::from_utf8_lossy(&output.stdout).into_owned()),
+        false => Err(io::Error::other(format!(
+            "{} {} fail: {}",
+            program,
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))),
+    }
+}
+
+#[async_trait]
+impl Platform for Linux {
+    async fn os_query(&self) -> io::Result<host::OsQuery> {
+        let os_release = fs::read_to_string(OS_RELEASE).await.unwrap_or_default();
+        let field = |key| parse_os_release(&os_release, key);
+        Ok(host::OsQuery {
+            display_name: field("PRETTY_NAME")
+                .or_else(|| field("NAME"))
+                .unwrap_or_else(|| "Linux".to_owned()),
+            kernel_version: run("uname", &["-r"]).await?.trim().to_owned(),
+            distro_id: field("ID").unwrap_or_else(|| "linux".to_owned()),
+            hostname: run("uname", &["-n"]).await?.trim().to_owned(),
+        })
+    }
+    async fn shutdown(&self) -> io::Result<()> {
+        run("shutdown", &["-h", "now"]).await.map(|_| ())
+    }
+    async fn reboot(&self) -> io::Result<()> {
+        run("shutdown", &["-r", "now"]).await.map(|_| ())
+    }
+    async fn boot_entries(&self) -> io::Result<Vec<BootEntry>> {
+        match &self.grub {
+            Some((cfg, _)) => Ok(parse_grub_cfg(&fs::read_to_string(cfg).await?)),
+            None => Ok(Vec::new()),
+        }
+    }
+    async fn boot_once(&self, entry: &BootEntry) -> io::Result<()> {
+        match &self.grub {
+            Some((_, reboot)) => run(reboot, &[&entry.id]).await.map(|_| ()),
+            None => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "grub isn't installed",
+            )),
+        }
+    }
+    async fn uefi_entries(&self) -> io::Result<Vec<host::UefiInfo>> {
+        match &self.efivars {
+            Some(efivars) => efivars.entries().await,
+            None => Ok(Vec::new()),
+        }
+    }
+    async fn boot_next(&self, boot_num: UefiId) -> io::Result<()> {
+        match &self.efivars {
+            Some(efivars) => efivars.set_boot_next(boot_num).await,
+            None => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "host didn't boot by uefi",
+            )),
+        }
+    }
+}
+
+/// value of a KEY=value line of os-release, which may be quoted
+fn parse_os_release(os_release: &str, key: &str) -> Option<String> {
+    os_release.lines().find_map(|line| {
+        let (field, value) = line.split_once('=')?;
+        (field.trim() == key).then(|| value.trim().trim_matches(['"', '\'']).to_owned())
+    })
+}
+
+/// top level menu entries of grub.cfg, identified like grub-reboot takes them
+///
+/// entries of submenus are other kernels or recovery of an os already listed, they're skipped
+pub fn parse_grub_cfg(cfg: &str) -> Vec<BootEntry> {
+    let mut entries = Vec::new();
+    // blocks opened by menuentry, submenu or function
+    let mut depth = 0usize;
+    for line in cfg.lines().map(str::trim) {
+        if line == "}" {
+            depth = depth.saturating_sub(1);
+            continue;
+        }
+        if !line.ends_with('{') {
+            continue;
+        }
+        depth += 1;
+        let words = split_words(line);
+        if depth != 1 || words.first().map(String::as_str) != Some("menuentry") {
+            continue;
+        }
+        let Some(title) = words.get(1) else {
+            continue;
+        };
+        // "$menuentry_id_option" is expanded to "--id" by grub
+        let id = words
+            .iter()
+            .position(|word| word == "$menuentry_id_option" || word == "--id")
+            .and_then(|i| words.get(i + 1))
+            .unwrap_or(title);
+        if id == FIRMWARE_SETUP {
+            continue;
+        }
+        entries.push(BootEntry {
+            id: id.clone(),
+            description: title.clone(),
+        });
+    }
+    entries
+}
+
+/// words of a line of grub script, unquoted
+fn split_words(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            // no escape in single quotes, "'\''" embeds one by closing them
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                word.extend(chars.by_ref().take_while(|c| *c != '\''));
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => word.extend(chars.next()),
+                        c => word.push(c),
+                    }
+                }
+            }
+            '\\' => word.get_or_insert_with(String::new).extend(chars.next()),
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    words
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_grub_cfg, parse_os_release, BootEntry};
+
+    const GRUB_CFG: &str = r#"
+function load_video {
+  if [ x$feature_all_video_module = xy ]; then
+    insmod all_video
+  fi
+}
+if [ x"${feature_menuentry_id}" = xy ]; then
+  menuentry_id_option="--id"
+else
+  menuentry_id_option=""
+fi
+menuentry 'Ubuntu' --class ubuntu --class gnu-linux --class os $menuentry_id_option 'gnulinux-simple-8e2c' {
+	recordfail
+	linux	/boot/vmlinuz-6.5.0-14-generic root=UUID=8e2c ro quiet splash
+}
+submenu 'Advanced options for Ubuntu' $menuentry_id_option 'gnulinux-advanced-8e2c' {
+	menuentry 'Ubuntu, with Linux 6.5.0-14-generic' --class ubuntu $menuentry_id_option 'gnulinux-6.5.0-14-generic-advanced-8e2c' {
+		linux	/boot/vmlinuz-6.5.0-14-generic root=UUID=8e2c ro
+	}
+}
+menuentry 'Windows Boot Manager (on /dev/nvme0n1p1)' --class windows --class os $menuentry_id_option 'osprober-efi-4A1B' {
+	chainloader /efi/Microsoft/Boot/bootmgfw.efi
+}
+menuentry 'Arch'\''s "rolling"' {
+	linux /vmlinuz-linux
+}
+menuentry 'UEFI Firmware Settings' $menuentry_id_option 'uefi-firmware' {
+	fwsetup
+}
+"#;
+
+    #[test]
+    fn grub_cfg() {
+        let entry = |id: &str, description: &str| BootEntry {
+            id: id.to_owned(),
+            description: description.to_owned(),
+        };
+        assert_eq!(
+            parse_grub_cfg(GRUB_CFG),
+            vec![
+                entry("gnulinux-simple-8e2c", "Ubuntu"),
+                entry(
+                    "osprober-efi-4A1B",
+                    "Windows Boot Manager (on /dev/nvme0n1p1)"
+                ),
+                entry("Arch's \"rolling\"", "Arch's \"rolling\""),
+            ]
+        );
+        assert!(parse_grub_cfg("").is_empty());
+    }
+
+    #[test]
+    fn os_release() {
+        let os_release = "NAME=\"Ubuntu\"\nPRETTY_NAME=\"Ubuntu 22.04.3 LTS\"\nID=ubuntu\n";
+        assert_eq!(
+            parse_os_release(os_release, "PRETTY_NAME").as_deref(),
+            Some("Ubuntu 22.04.3 LTS")
+        );
+        assert_eq!(
+            parse_os_release(os_release, "ID").as_deref(),
+            Some("ubuntu")
+        );
+        assert_eq!(parse_os_release(os_release, "VERSION_ID"), None);
+    }
+}
@@ -0,0 +1,50 @@
+use std::{io, sync::Mutex};
+
+use async_trait::async_trait;
+use proto::prelude::{host, UefiId};
+
+use super::{BootEntry, Platform};
+
+/// platform of tests on any os, recording what it was asked
+#[derive(Default)]
+pub struct Mock {
+    pub entries: Vec<BootEntry>,
+    pub calls: Mutex<Vec<String>>,
+}
+
+impl Mock {
+    fn call(&self, call: String) -> io::Result<()> {
+        self.calls.lock().unwrap().push(call);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Platform for Mock {
+    async fn os_query(&self) -> io::Result<host::OsQuery> {
+        Ok(host::OsQuery {
+            display_name: "Windows 10 Pro".to_owned(),
+            kernel_version: "10.0.19045.3570".to_owned(),
+            distro_id: "windows".to_owned(),
+            hostname: "mock".to_owned(),
+        })
+    }
+    async fn shutdown(&self) -> io::Result<()> {
+        self.call("shutdown".to_owned())
+    }
+    async fn reboot(&self) -> io::Result<()> {
+        self.call("reboot".to_owned())
+    }
+    async fn boot_entries(&self) -> io::Result<Vec<BootEntry>> {
+        Ok(self.entries.clone())
+    }
+    async fn boot_once(&self, entry: &BootEntry) -> io::Result<()> {
+        self.call(format!("boot_once {}", entry.id))
+    }
+    async fn uefi_entries(&self) -> io::Result<Vec<host::UefiInfo>> {
+        Ok(Vec::new())
+    }
+    async fn boot_next(&self, boot_num: UefiId) -> io::Result<()> {
+        self.call(format!("boot_next {:04X}", boot_num))
+    }
+}
@@ -0,0 +1,53 @@
+#[cfg(any(windows, test))]
+mod bcdedit;
+#[cfg(target_os = "linux")]
+pub mod linux;
+#[cfg(test)]
+pub mod mock;
+#[cfg(windows)]
+pub mod windows;
+
+use std::io;
+
+use async_trait::async_trait;
+use proto::prelude::{host, GrubId, UefiId};
+
+/// entry of the boot manager of the os, which can be booted once
+#[derive(Clone, Debug, PartialEq)]
+pub struct BootEntry {
+    // identifier given by the boot manager, like "{guid}" of bcdedit
+    pub id: String,
+    pub description: String,
+}
+
+impl BootEntry {
+    /// stable across restarts of the agent, fnv-1a of the identifier
+    pub fn grub_id(&self) -> GrubId {
+        self.id.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        })
+    }
+}
+
+/// what the agent does on the os it runs on
+#[async_trait]
+pub trait Platform: Send + Sync {
+    async fn os_query(&self) -> io::Result<host::OsQuery>;
+    /// called once the answer is sent, the connection is lost after
+    async fn shutdown(&self) -> io::Result<()>;
+    async fn reboot(&self) -> io::Result<()>;
+    /// entries the os can boot once, reported as grub entries
+    async fn boot_entries(&self) -> io::Result<Vec<BootEntry>>;
+    /// boot the entry on next reboot only
+    async fn boot_once(&self, entry: &BootEntry) -> io::Result<()>;
+    /// firmware entries the host can boot once by BootNext, none by default
+    async fn uefi_entries(&self) -> io::Result<Vec<host::UefiInfo>> {
+        Ok(Vec::new())
+    }
+    async fn boot_next(&self, _boot_num: UefiId) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "firmware entries aren't supported on this os",
+        ))
+    }
+}
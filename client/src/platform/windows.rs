@@ -0,0 +1,77 @@
+use std::io;
+
+use async_std::process::Command;
+use async_trait::async_trait;
+use proto::prelude::host;
+
+use super::{bcdedit, BootEntry, Platform};
+
+const CURRENT_VERSION: &str = r"HKLM\SOFTWARE\Microsoft\Windows NT\CurrentVersion";
+
+/// windows by its own tools, which the service running as LocalSystem may use
+///
+/// firmware entries are reported by bcdedit as boot entries, windows has no efivarfs
+pub struct Windows {
+    // report firmware applications as one-time boot entries, or only shutdown
+    pub bcdedit: bool,
+}
+
+async fn run(program: &str, args: &[&str]) -> io::Result<String> {
+    let output = Command::new(program).args(args).output().await?;
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    match output.status.success() {
+        true => Ok(stdout),
+        // bcdedit and reg tell why on stdout
+        false => Err(io::Error::other(format!(
+            "{} {} fail: {}",
+            program,
+            args.join(" "),
+            stdout.trim()
+        ))),
+    }
+}
+
+#[async_trait]
+impl Platform for Windows {
+    async fn os_query(&self) -> io::Result<host::OsQuery> {
+        // "    ProductName    REG_SZ    Windows 10 Pro"
+        let product = run("reg", &["query", CURRENT_VERSION, "/v", "ProductName"]).await?;
+        let display_name = product
+            .lines()
+            .find_map(|line| line.split_once("REG_SZ"))
+            .map(|(_, name)| name.trim().to_owned())
+            .unwrap_or_else(|| "Windows".to_owned());
+        // "Microsoft Windows [Version 10.0.19045.3570]"
+        let ver = run("cmd", &["/c", "ver"]).await?;
+        let kernel_version = ver
+            .split(['[', ']'])
+            .nth(1)
+            .and_then(|x| x.split_whitespace().last())
+            .unwrap_or_default()
+            .to_owned();
+        Ok(host::OsQuery {
+            display_name,
+            kernel_version,
+            distro_id: "windows".to_owned(),
+            hostname: std::env::var("COMPUTERNAME").unwrap_or_default(),
+        })
+    }
+    async fn shutdown(&self) -> io::Result<()> {
+        run("shutdown", &["/s", "/t", "0"]).await.map(|_| ())
+    }
+    async fn reboot(&self) -> io::Result<()> {
+        run("shutdown", &["/r", "/t", "0"]).await.map(|_| ())
+    }
+    async fn boot_entries(&self) -> io::Result<Vec<BootEntry>> {
+        if !self.bcdedit {
+            return Ok(Vec::new());
+        }
+        let output = run("bcdedit", &["/enum", "firmware"]).await?;
+        Ok(bcdedit::parse_firmware(&output))
+    }
+    async fn boot_once(&self, entry: &BootEntry) -> io::Result<()> {
+        run("bcdedit", &bcdedit::bootsequence_args(entry))
+            .await
+            .map(|_| ())
+    }
+}
@@ -1,31 +1,268 @@
-use async_std::{fs, io::ReadExt};
-use proto::prelude::ID;
+use std::{
+    io,
+    net::Ipv4Addr,
+    path::{Path, PathBuf},
+};
 
+use async_std::{fs, net::UdpSocket, process::Command};
+use proto::prelude::{host, server, ID};
 
-struct Responder;
+use crate::platform::Platform;
 
-const PATH:&'static str="host_save";
-// const PATH: &'static Path = Path::new("./");
-
-async fn get_uid()->ID{
-    todo!();
-    // if fs::File:;exs
-    let mut file=fs::File::open(PATH).await.unwrap();
+/// done by the platform once the answer is sent
+#[derive(Debug, PartialEq)]
+pub enum Power {
+    Shutdown,
+    Reboot,
+}
 
-    let mut buf=Vec::new();
-    file.read_to_end(&mut buf).await.unwrap();
+/// answer requests of the server for the os the agent runs on
+pub struct Responder<P: Platform> {
+    pub platform: P,
+    uid: ID,
+    // where the os id issued by the server is kept across boots
+    uid_path: PathBuf,
+    // resets a hung machine this agent relays for, none if the agent can't
+    reset_hook: Option<PathBuf>,
+}
 
-    let id:ID=bincode::deserialize(&buf).unwrap();
+impl<P: Platform> Responder<P> {
+    /// the os id is 0 until the server issue one
+    pub async fn new(platform: P, uid_path: PathBuf, reset_hook: Option<PathBuf>) -> Self {
+        Self {
+            platform,
+            uid: load_uid(&uid_path).await,
+            uid_path,
+            reset_hook,
+        }
+    }
+    pub fn uid(&self) -> ID {
+        self.uid
+    }
+    /// none if the request has no answer
+    pub async fn respond(&mut self, req: server::Packet) -> Option<(host::Packet, Option<Power>)> {
+        let res = match req {
+            server::Packet::Handshake(_) => return None,
+            server::Packet::Reboot(grub_id) => {
+                self.boot_once(grub_id).await;
+                return Some((host::Packet::Reboot, Some(Power::Reboot)));
+            }
+            server::Packet::InitId(uid) => {
+                self.uid = uid;
+                if let Err(err) = save_uid(&self.uid_path, uid).await {
+                    log::error!("fail to save os id to {}: {}", self.uid_path.display(), err);
+                }
+                host::Packet::InitId
+            }
+            server::Packet::Shutdown => {
+                return Some((host::Packet::Shutdown, Some(Power::Shutdown)))
+            }
+            server::Packet::GrubQuery => host::Packet::GrubQuery(
+                self.boot_entries()
+                    .await
+                    .iter()
+                    .map(|entry| host::GrubInfo {
+                        grub_sec: entry.grub_id(),
+                    })
+                    .collect(),
+            ),
+            server::Packet::Ping => host::Packet::Ping(self.uid),
+            server::Packet::OsQuery => {
+                host::Packet::OsQuery(match self.platform.os_query().await {
+                    Ok(query) => query,
+                    Err(err) => {
+                        log::error!("fail to query os: {}", err);
+                        host::OsQuery {
+                            display_name: "unknown".to_owned(),
+                            kernel_version: String::new(),
+                            distro_id: String::new(),
+                            hostname: String::new(),
+                        }
+                    }
+                })
+            }
+            server::Packet::WakeOnLan(req) => {
+                log::info!("relay magic packet to {:x?}", req.mac_address);
+                host::Packet::WakeOnLan(relay_wol(&req).await.map_err(|err| err.to_string()))
+            }
+            server::Packet::Reset(mac_address) => {
+                log::warn!("reset hung machine {:x?} as its relay", mac_address);
+                host::Packet::Reset(
+                    relay_reset(self.reset_hook.as_deref(), &mac_address)
+                        .await
+                        .map_err(|err| err.to_string()),
+                )
+            }
+            server::Packet::UefiQuery => {
+                host::Packet::UefiQuery(self.platform.uefi_entries().await.unwrap_or_else(|err| {
+                    log::warn!("fail to read firmware boot entries: {}", err);
+                    Vec::new()
+                }))
+            }
+            server::Packet::BootNext(boot_num) => {
+                return match self.platform.boot_next(boot_num).await {
+                    Ok(()) => Some((host::Packet::BootNext(Ok(())), Some(Power::Reboot))),
+                    Err(err) => Some((host::Packet::BootNext(Err(err.to_string())), None)),
+                };
+            }
+        };
+        Some((res, None))
+    }
+    async fn boot_entries(&self) -> Vec<crate::platform::BootEntry> {
+        self.platform.boot_entries().await.unwrap_or_else(|err| {
+            log::warn!("fail to list boot entries: {}", err);
+            Vec::new()
+        })
+    }
+    /// the host reboots anyway like grub-reboot of an unknown entry, the server sees where it lands
+    async fn boot_once(&self, grub_id: u64) {
+        let entries = self.boot_entries().await;
+        match entries.iter().find(|entry| entry.grub_id() == grub_id) {
+            Some(entry) => {
+                log::info!("boot {} once", entry.description);
+                if let Err(err) = self.platform.boot_once(entry).await {
+                    log::error!("fail to boot {} once: {}", entry.description, err);
+                }
+            }
+            None => log::warn!("no boot entry of id {}", grub_id),
+        }
+    }
+}
 
-    id
+async fn load_uid(path: &Path) -> ID {
+    match fs::read(path).await {
+        Ok(buf) => bincode::deserialize(&buf).unwrap_or_else(|err| {
+            log::warn!("discard broken os id of {}: {}", path.display(), err);
+            0
+        }),
+        Err(_) => 0,
+    }
 }
 
+async fn save_uid(path: &Path, uid: ID) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).await?;
+    }
+    fs::write(path, bincode::serialize(&uid).unwrap()).await
+}
 
-// no buffer, interact directly with underlying storage
-// However, grub-query(disk scan should only 'take once')
-impl Responder {
-    async fn respond_handshake(){
+/// magic packet sent on local segment, as a relay of server
+async fn relay_wol(req: &server::WakeOnLan) -> io::Result<()> {
+    let mut packet = vec![0xff; 6];
+    (0..16).for_each(|_| packet.extend_from_slice(&req.mac_address));
+    if let Some(password) = &req.password {
+        packet.extend_from_slice(password);
+    }
+    let target = req.target.unwrap_or(Ipv4Addr::BROADCAST);
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+    socket.set_broadcast(true)?;
+    socket.send_to(&packet, (target, req.port)).await?;
+    Ok(())
+}
 
+/// reset hook run on a hung machine of local segment, as a relay of server
+///
+/// it gets the mac address like "00:1a:2b:3c:4d:5e", and may drive a bmc or a plug only reachable from here
+async fn relay_reset(hook: Option<&Path>, mac_address: &[u8; 6]) -> io::Result<()> {
+    let hook = hook.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::Unsupported, "no reset hook configured")
+    })?;
+    let mac = mac_address
+        .iter()
+        .map(|x| format!("{:02x}", x))
+        .collect::<Vec<_>>()
+        .join(":");
+    let output = Command::new(hook).arg(&mac).output().await?;
+    match output.status.success() {
+        true => Ok(()),
+        false => Err(io::Error::other(format!(
+            "{} {} fail: {}",
+            hook.display(),
+            mac,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))),
     }
 }
 
+#[cfg(test)]
+mod test {
+    use async_std::task::block_on;
+    use proto::prelude::{host, server};
+
+    use super::{Power, Responder};
+    use crate::platform::{mock::Mock, BootEntry};
+
+    #[test]
+    fn respond() {
+        let uid_path = std::env::temp_dir().join(format!("grub-wol-uid-{}", std::process::id()));
+        std::fs::remove_file(&uid_path).ok();
+        let ubuntu = BootEntry {
+            id: "{7c3a4b52-7a23-11ee-b962-0242ac120002}".to_owned(),
+            description: "ubuntu".to_owned(),
+        };
+        let platform = Mock {
+            entries: vec![ubuntu.clone()],
+            ..Default::default()
+        };
+        let mut responder = block_on(Responder::new(platform, uid_path.clone(), None));
+        assert_eq!(responder.uid(), 0);
+
+        let res = block_on(responder.respond(server::Packet::OsQuery));
+        assert!(matches!(
+            res,
+            Some((host::Packet::OsQuery(query), None)) if query.distro_id == "windows"
+        ));
+
+        // every entry is reported with an id stable across restarts
+        let res = block_on(responder.respond(server::Packet::GrubQuery));
+        let grub_sec = ubuntu.grub_id();
+        assert_eq!(
+            res,
+            Some((
+                host::Packet::GrubQuery(vec![host::GrubInfo { grub_sec }]),
+                None
+            ))
+        );
+        let res = block_on(responder.respond(server::Packet::Reboot(grub_sec)));
+        assert_eq!(res, Some((host::Packet::Reboot, Some(Power::Reboot))));
+        // an unknown entry reboot into the default one
+        block_on(responder.respond(server::Packet::Reboot(grub_sec + 1)));
+        assert_eq!(
+            *responder.platform.calls.lock().unwrap(),
+            vec![format!("boot_once {}", ubuntu.id)]
+        );
+
+        let res = block_on(responder.respond(server::Packet::Shutdown));
+        assert_eq!(res, Some((host::Packet::Shutdown, Some(Power::Shutdown))));
+
+        // the os id is kept by the next boot
+        block_on(responder.respond(server::Packet::InitId(7)));
+        let responder = block_on(Responder::new(Mock::default(), uid_path.clone(), None));
+        assert_eq!(responder.uid(), 7);
+        std::fs::remove_file(&uid_path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reset_hook() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("grub-wol-reset-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (hook, out) = (dir.join("reset.sh"), dir.join("reset.out"));
+        std::fs::write(&hook, format!("#!/bin/sh\necho \"$1\" > {}\n", out.display())).unwrap();
+        std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let mac = [0x00, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e];
+
+        // without hook, the server is told the relay can't reset
+        let mut responder = block_on(Responder::new(Mock::default(), dir.join("uid"), None));
+        let res = block_on(responder.respond(server::Packet::Reset(mac)));
+        assert!(matches!(res, Some((host::Packet::Reset(Err(_)), None))));
+
+        let mut responder = block_on(Responder::new(Mock::default(), dir.join("uid"), Some(hook)));
+        let res = block_on(responder.respond(server::Packet::Reset(mac)));
+        assert_eq!(res, Some((host::Packet::Reset(Ok(())), None)));
+        assert_eq!(std::fs::read_to_string(&out).unwrap(), "00:1a:2b:3c:4d:5e\n");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
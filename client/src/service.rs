@@ -0,0 +1,72 @@
+use std::{ffi::OsString, sync::mpsc, time::Duration};
+
+use async_std::task::{block_on, spawn};
+use windows_service::{
+    define_windows_service,
+    service::{
+        ServiceControl, ServiceControlAccept, ServiceExitCode, ServiceState, ServiceStatus,
+        ServiceType,
+    },
+    service_control_handler::{self, ServiceControlHandlerResult},
+    service_dispatcher,
+};
+
+use crate::{
+    agent::{self, Config},
+    platform::windows::Windows,
+};
+
+/// installed by `sc create grubwol binPath= "<path>\client.exe --service" start= auto`
+pub const SERVICE_NAME: &str = "grubwol";
+
+define_windows_service!(ffi_service_main, service_main);
+
+/// block until the service control manager stops the service
+pub fn run() -> windows_service::Result<()> {
+    service_dispatcher::start(SERVICE_NAME, ffi_service_main)
+}
+
+fn service_main(_arguments: Vec<OsString>) {
+    if let Err(err) = run_service() {
+        log::error!("service fail: {}", err);
+    }
+}
+
+fn status(current_state: ServiceState, controls_accepted: ServiceControlAccept) -> ServiceStatus {
+    ServiceStatus {
+        service_type: ServiceType::OWN_PROCESS,
+        current_state,
+        controls_accepted,
+        exit_code: ServiceExitCode::Win32(0),
+        checkpoint: 0,
+        wait_hint: Duration::default(),
+        process_id: None,
+    }
+}
+
+fn run_service() -> windows_service::Result<()> {
+    let (stop_tx, stop_rx) = mpsc::channel();
+    let status_handle =
+        service_control_handler::register(SERVICE_NAME, move |control| match control {
+            ServiceControl::Stop | ServiceControl::Shutdown => {
+                stop_tx.send(()).ok();
+                ServiceControlHandlerResult::NoError
+            }
+            ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+            _ => ServiceControlHandlerResult::NotImplemented,
+        })?;
+    status_handle.set_service_status(status(
+        ServiceState::Running,
+        ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN,
+    ))?;
+
+    let config = Config::from_env();
+    let platform = Windows {
+        bcdedit: config.bcdedit,
+    };
+    let agent = spawn(agent::run(config, platform));
+    stop_rx.recv().ok();
+    block_on(agent.cancel());
+
+    status_handle.set_service_status(status(ServiceState::Stopped, ServiceControlAccept::empty()))
+}
@@ -0,0 +1,221 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use async_std::fs;
+use proto::prelude::{host, UefiId};
+
+pub const EFIVARS: &str = "/sys/firmware/efi/efivars";
+// vendor guid of BootOrder, BootNext and BootXXXX
+const GLOBAL_GUID: &str = "8be4df61-93ca-11d2-aa0d-00e098032b8c";
+// non volatile, boot service access, runtime access
+const BOOT_NEXT_ATTRIBUTES: u32 = 0x7;
+const LOAD_OPTION_ACTIVE: u32 = 0x1;
+// device path node of a partition, which a boot entry loading from disk has
+const MEDIA_DEVICE_PATH: u8 = 0x04;
+const MEDIA_HARDDRIVE_DP: u8 = 0x01;
+const END_DEVICE_PATH: u8 = 0x7f;
+
+/// firmware variables exposed by efivarfs, each file begins with 4 bytes of attributes
+pub struct Efivars {
+    root: PathBuf,
+}
+
+impl Efivars {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+    /// none if the host didn't boot by uefi
+    pub fn system() -> Option<Self> {
+        Path::new(EFIVARS).is_dir().then(|| Self::new(EFIVARS))
+    }
+    /// active entries loading from disk, in BootOrder
+    pub async fn entries(&self) -> io::Result<Vec<host::UefiInfo>> {
+        let order = self.read("BootOrder").await?;
+        let mut entries = Vec::new();
+        for boot_num in order.chunks_exact(2) {
+            let boot_num = u16::from_le_bytes([boot_num[0], boot_num[1]]);
+            // BootOrder may name an entry already deleted
+            let option = match self.read(&boot_name(boot_num)).await {
+                Ok(option) => option,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err),
+            };
+            match LoadOption::parse(&option) {
+                Some(option) if option.active && option.disk => entries.push(host::UefiInfo {
+                    boot_num,
+                    description: option.description,
+                }),
+                Some(_) => {}
+                None => log::warn!("skip malformed {}", boot_name(boot_num)),
+            }
+        }
+        Ok(entries)
+    }
+    /// boot the entry once on next reboot, the firmware clears BootNext after
+    pub async fn set_boot_next(&self, boot_num: UefiId) -> io::Result<()> {
+        // the firmware falls back to BootOrder on a missing entry, fail early instead
+        self.read(&boot_name(boot_num)).await?;
+        let mut buf = BOOT_NEXT_ATTRIBUTES.to_le_bytes().to_vec();
+        buf.extend_from_slice(&boot_num.to_le_bytes());
+        // efivarfs takes a variable in a single write
+        fs::write(self.path("BootNext"), buf).await
+    }
+    fn path(&self, name: &str) -> PathBuf {
+        self.root.join(format!("{}-{}", name, GLOBAL_GUID))
+    }
+    async fn read(&self, name: &str) -> io::Result<Vec<u8>> {
+        let buf = fs::read(self.path(name)).await?;
+        if buf.len() < 4 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} has no attributes", name),
+            ));
+        }
+        Ok(buf[4..].to_vec())
+    }
+}
+
+fn boot_name(boot_num: UefiId) -> String {
+    format!("Boot{:04X}", boot_num)
+}
+
+/// EFI_LOAD_OPTION of a BootXXXX variable
+struct LoadOption {
+    active: bool,
+    description: String,
+    disk: bool,
+}
+
+impl LoadOption {
+    fn parse(buf: &[u8]) -> Option<Self> {
+        let attributes = u32::from_le_bytes(buf.get(0..4)?.try_into().ok()?);
+        let path_len = u16::from_le_bytes(buf.get(4..6)?.try_into().ok()?) as usize;
+        // description is a null terminated ucs-2 string
+        let mut description = Vec::new();
+        let mut offset = 6;
+        loop {
+            let c = u16::from_le_bytes(buf.get(offset..offset + 2)?.try_into().ok()?);
+            offset += 2;
+            if c == 0 {
+                break;
+            }
+            description.push(c);
+        }
+        let path = buf.get(offset..offset + path_len)?;
+        Some(Self {
+            active: attributes & LOAD_OPTION_ACTIVE != 0,
+            description: String::from_utf16_lossy(&description),
+            disk: has_harddrive(path)?,
+        })
+    }
+}
+
+/// walk the device path nodes of the first path of the list
+fn has_harddrive(mut path: &[u8]) -> Option<bool> {
+    while path.len() >= 4 {
+        let (ty, subtype) = (path[0], path[1]);
+        let len = u16::from_le_bytes([path[2], path[3]]) as usize;
+        if ty == END_DEVICE_PATH || len < 4 {
+            break;
+        }
+        if ty == MEDIA_DEVICE_PATH && subtype == MEDIA_HARDDRIVE_DP {
+            return Some(true);
+        }
+        path = path.get(len..)?;
+    }
+    Some(false)
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::{Path, PathBuf};
+
+    use async_std::task::block_on;
+
+    use super::{Efivars, GLOBAL_GUID};
+
+    /// efivars directory of a temporary fake firmware
+    fn fake_efivars(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("grub-wol-{}-{}", name, std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_var(dir: &Path, name: &str, data: &[u8]) {
+        let buf = [&0x7u32.to_le_bytes()[..], data].concat();
+        std::fs::write(dir.join(format!("{}-{}", name, GLOBAL_GUID)), buf).unwrap();
+    }
+
+    fn load_option(active: bool, description: &str, path: &[u8]) -> Vec<u8> {
+        let mut buf = (active as u32).to_le_bytes().to_vec();
+        buf.extend_from_slice(&(path.len() as u16).to_le_bytes());
+        for c in description.encode_utf16().chain([0]) {
+            buf.extend_from_slice(&c.to_le_bytes());
+        }
+        buf.extend_from_slice(path);
+        buf
+    }
+
+    // HD(...)/File(...)/End, lengths of the real nodes
+    fn disk_path() -> Vec<u8> {
+        let mut path = vec![0x04, 0x01, 42, 0];
+        path.resize(42, 0);
+        path.extend_from_slice(&[0x04, 0x04, 8, 0, b'\\', 0, 0, 0]);
+        path.extend_from_slice(&[0x7f, 0xff, 4, 0]);
+        path
+    }
+
+    // PciRoot(...)/MAC(...)/IPv4(...)/End of pxe
+    fn network_path() -> Vec<u8> {
+        let mut path = vec![0x02, 0x01, 12, 0];
+        path.resize(12, 0);
+        path.extend_from_slice(&[0x03, 0x0b, 8, 0, 0, 0, 0, 0]);
+        path.extend_from_slice(&[0x7f, 0xff, 4, 0]);
+        path
+    }
+
+    #[test]
+    fn entries() {
+        let dir = fake_efivars("efivars");
+        write_var(
+            &dir,
+            "BootOrder",
+            &[0x01, 0x00, 0x00, 0x00, 0x0a, 0x00, 0x02, 0x00, 0x03, 0x00],
+        );
+        write_var(&dir, "Boot0000", &load_option(true, "ubuntu", &disk_path()));
+        write_var(
+            &dir,
+            "Boot0001",
+            &load_option(true, "Windows Boot Manager", &disk_path()),
+        );
+        write_var(
+            &dir,
+            "Boot0002",
+            &load_option(true, "UEFI PXEv4", &network_path()),
+        );
+        write_var(
+            &dir,
+            "Boot0003",
+            &load_option(false, "fedora", &disk_path()),
+        );
+        // Boot000A is missing
+
+        let efivars = Efivars::new(&dir);
+        let entries = block_on(efivars.entries()).unwrap();
+        let entries: Vec<(u16, &str)> = entries
+            .iter()
+            .map(|x| (x.boot_num, x.description.as_str()))
+            .collect();
+        assert_eq!(entries, vec![(1, "Windows Boot Manager"), (0, "ubuntu")]);
+
+        block_on(efivars.set_boot_next(1)).unwrap();
+        let boot_next = std::fs::read(dir.join(format!("BootNext-{}", GLOBAL_GUID))).unwrap();
+        assert_eq!(boot_next, vec![0x07, 0, 0, 0, 0x01, 0x00]);
+        assert!(block_on(efivars.set_boot_next(0x0a)).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
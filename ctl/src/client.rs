@@ -0,0 +1,389 @@
+use proto::prelude::{web as api, ID};
+use serde::{de::DeserializeOwned, Serialize};
+use std::borrow::Cow;
+use surf::http::headers;
+
+pub type MacAddress = [u8; 6];
+
+pub enum Credential {
+    Password(String),
+    Token(String),
+}
+
+pub struct Client {
+    http: surf::Client,
+    base: surf::Url,
+    // value of the header authenticating every request
+    auth: (headers::HeaderName, String),
+}
+
+impl Client {
+    pub async fn connect(server: &str, credential: Credential) -> Result<Self, Error> {
+        let base = surf::Url::parse(server).map_err(|_| Error::BadServer(server.to_owned()))?;
+        let http = surf::Client::new();
+        let auth = match credential {
+            Credential::Token(token) => (headers::AUTHORIZATION, format!("Bearer {}", token)),
+            Credential::Password(password) => {
+                let url = base.join("login").unwrap();
+                let mut res = http
+                    .post(url)
+                    .body_json(&api::LoginReq {
+                        password: Cow::Owned(password),
+                    })
+                    .map_err(Error::Http)?
+                    .await
+                    .map_err(Error::Http)?;
+                let cookie = res
+                    .header(headers::SET_COOKIE)
+                    .and_then(|cookie| cookie.as_str().split(';').next())
+                    .map(|cookie| cookie.to_owned());
+                match Self::parse::<api::LoginRes>(&mut res).await? {
+                    api::LoginRes::Success => {}
+                    api::LoginRes::Fail => return Err(Error::LoginFailed),
+                }
+                (headers::COOKIE, cookie.ok_or(Error::LoginFailed)?)
+            }
+        };
+        Ok(Self { http, base, auth })
+    }
+    async fn parse<Res>(res: &mut surf::Response) -> Result<Res, Error>
+    where
+        Res: DeserializeOwned,
+    {
+        if !res.status().is_success() {
+            return Err(Error::Status(res.status().into()));
+        }
+        let body = res.body_bytes().await.map_err(Error::Http)?;
+        serde_json::from_slice(&body).map_err(Error::Deserialize)
+    }
+    async fn post<Req, Res>(&self, path: &str, req: &Req) -> Result<Res, Error>
+    where
+        Req: Serialize,
+        Res: DeserializeOwned,
+    {
+        let url = self.base.join(path).unwrap();
+        let mut res = self
+            .http
+            .post(url)
+            .header(self.auth.0.clone(), self.auth.1.as_str())
+            .body_json(req)
+            .map_err(Error::Http)?
+            .await
+            .map_err(Error::Http)?;
+        Self::parse(&mut res).await
+    }
+    pub async fn list_machine(&self) -> Result<api::MachineList<'static>, Error> {
+        self.post("api/get/machines", &()).await
+    }
+    pub async fn info_machine(
+        &self,
+        mac_address: MacAddress,
+    ) -> Result<api::MachineInfo<'static>, Error> {
+        self.post(
+            "api/get/machine",
+            &api::MachineInfoReq {
+                mac_address: Cow::Owned(mac_address),
+            },
+        )
+        .await
+    }
+    pub async fn list_os(&self, mac_address: MacAddress) -> Result<api::OsList<'static>, Error> {
+        self.post(
+            "api/get/oss",
+            &api::OsListReq {
+                mac_address: Cow::Owned(mac_address),
+            },
+        )
+        .await
+    }
+    pub async fn list_edge(&self, mac_address: MacAddress) -> Result<api::EdgeList, Error> {
+        self.post(
+            "api/get/edges",
+            &api::EdgeListReq {
+                mac_address: Cow::Owned(mac_address),
+            },
+        )
+        .await
+    }
+    pub async fn export_graph(&self, mac_address: MacAddress) -> Result<api::GraphExport, Error> {
+        self.post(
+            "api/get/graph",
+            &api::GraphReq {
+                mac_address: Cow::Owned(mac_address),
+            },
+        )
+        .await
+    }
+    pub async fn plan(
+        &self,
+        mac_address: MacAddress,
+        os: api::OsStatus,
+    ) -> Result<api::PlanRes, Error> {
+        self.post(
+            "api/get/plan",
+            &api::PlanReq {
+                mac_address: Cow::Owned(mac_address),
+                os,
+            },
+        )
+        .await
+    }
+    pub async fn new_standalone(
+        &self,
+        mac_address: MacAddress,
+        display_name: String,
+        probe: Option<api::ProbeSettings>,
+    ) -> Result<api::NewStandaloneRes, Error> {
+        self.post(
+            "api/op/standalone",
+            &api::NewStandaloneReq {
+                mac_address: Cow::Owned(mac_address),
+                display_name: Cow::Owned(display_name),
+                wol: api::WolSettings {
+                    interface: None,
+                    source: None,
+                    target: None,
+                    port: 9,
+                    password: None,
+                    relay: None,
+                },
+                probe,
+            },
+        )
+        .await
+    }
+    pub async fn probe(&self, mac_address: MacAddress) -> Result<api::ProbeRes, Error> {
+        self.post(
+            "api/get/probe",
+            &api::ProbeReq {
+                mac_address: Cow::Owned(mac_address),
+            },
+        )
+        .await
+    }
+    pub async fn edit_probe(
+        &self,
+        mac_address: MacAddress,
+        probe: Option<api::ProbeSettings>,
+    ) -> Result<api::EditProbeRes, Error> {
+        self.post(
+            "api/op/probe",
+            &api::EditProbeReq {
+                mac_address: Cow::Owned(mac_address),
+                probe,
+            },
+        )
+        .await
+    }
+    pub async fn bmc(&self, mac_address: MacAddress) -> Result<api::BmcRes, Error> {
+        self.post(
+            "api/get/bmc",
+            &api::BmcReq {
+                mac_address: Cow::Owned(mac_address),
+            },
+        )
+        .await
+    }
+    pub async fn edit_bmc(
+        &self,
+        mac_address: MacAddress,
+        settings: Option<api::BmcSettings>,
+    ) -> Result<api::EditBmcRes, Error> {
+        self.post(
+            "api/op/bmc",
+            &api::EditBmcReq {
+                mac_address: Cow::Owned(mac_address),
+                settings,
+            },
+        )
+        .await
+    }
+    pub async fn plug(&self, mac_address: MacAddress) -> Result<api::PlugRes, Error> {
+        self.post(
+            "api/get/plug",
+            &api::PlugReq {
+                mac_address: Cow::Owned(mac_address),
+            },
+        )
+        .await
+    }
+    pub async fn edit_plug(
+        &self,
+        mac_address: MacAddress,
+        settings: Option<api::PlugSettings>,
+    ) -> Result<api::EditPlugRes, Error> {
+        self.post(
+            "api/op/plug",
+            &api::EditPlugReq {
+                mac_address: Cow::Owned(mac_address),
+                settings,
+            },
+        )
+        .await
+    }
+    pub async fn escalation(&self, mac_address: MacAddress) -> Result<api::EscalationRes, Error> {
+        self.post(
+            "api/get/escalation",
+            &api::EscalationReq {
+                mac_address: Cow::Owned(mac_address),
+            },
+        )
+        .await
+    }
+    pub async fn edit_escalation(
+        &self,
+        mac_address: MacAddress,
+        escalation: Option<api::EscalationSettings>,
+    ) -> Result<api::EditEscalationRes, Error> {
+        self.post(
+            "api/op/escalation",
+            &api::EditEscalationReq {
+                mac_address: Cow::Owned(mac_address),
+                escalation,
+            },
+        )
+        .await
+    }
+    pub async fn audit(&self, mac_address: MacAddress) -> Result<api::AuditRes, Error> {
+        self.post(
+            "api/get/audit",
+            &api::AuditReq {
+                mac_address: Cow::Owned(mac_address),
+            },
+        )
+        .await
+    }
+    pub async fn wol(&self, mac_address: MacAddress) -> Result<api::WolRes, Error> {
+        self.post(
+            "api/get/wol",
+            &api::WolReq {
+                mac_address: Cow::Owned(mac_address),
+            },
+        )
+        .await
+    }
+    pub async fn edit_wol(
+        &self,
+        mac_address: MacAddress,
+        settings: &api::WolSettings,
+    ) -> Result<api::EditWolRes, Error> {
+        self.post(
+            "api/op/wol",
+            &api::EditWolReq {
+                mac_address: Cow::Owned(mac_address),
+                settings: settings.clone(),
+            },
+        )
+        .await
+    }
+    pub async fn boot(
+        &self,
+        mac_address: MacAddress,
+        os: api::OsStatus,
+    ) -> Result<api::BootRes, Error> {
+        self.post(
+            "api/op/boot",
+            &api::BootReq {
+                mac_address: Cow::Owned(mac_address),
+                os,
+            },
+        )
+        .await
+    }
+    pub async fn new_machine(
+        &self,
+        mac_address: MacAddress,
+        display_name: String,
+    ) -> Result<api::NewMachineRes, Error> {
+        self.post(
+            "api/op/new",
+            &api::NewMachineReq {
+                display_name: Cow::Owned(display_name),
+                mac_address: Cow::Owned(mac_address),
+            },
+        )
+        .await
+    }
+    pub async fn edit_os(
+        &self,
+        mac_address: MacAddress,
+        id: ID,
+        label: api::OsLabel<'static>,
+    ) -> Result<api::EditOsRes, Error> {
+        self.post(
+            "api/op/os",
+            &api::EditOsReq {
+                mac_address: Cow::Owned(mac_address),
+                id,
+                label,
+            },
+        )
+        .await
+    }
+    pub async fn edit_graph(
+        &self,
+        mac_address: MacAddress,
+        edit: api::GraphEdit,
+    ) -> Result<api::EditGraphRes, Error> {
+        self.post(
+            "api/op/graph",
+            &api::EditGraphReq {
+                mac_address: Cow::Owned(mac_address),
+                edit,
+            },
+        )
+        .await
+    }
+    pub async fn rename_machine(
+        &self,
+        mac_address: MacAddress,
+        display_name: String,
+    ) -> Result<api::RenameMachineRes, Error> {
+        self.post(
+            "api/op/rename",
+            &api::RenameMachineReq {
+                display_name: Cow::Owned(display_name),
+                mac_address: Cow::Owned(mac_address),
+            },
+        )
+        .await
+    }
+    pub async fn forget_machine(
+        &self,
+        mac_address: MacAddress,
+    ) -> Result<api::ForgetMachineRes, Error> {
+        self.post(
+            "api/op/forget",
+            &api::ForgetMachineReq {
+                mac_address: Cow::Owned(mac_address),
+            },
+        )
+        .await
+    }
+    pub async fn renew_machine(
+        &self,
+        mac_address: MacAddress,
+    ) -> Result<api::RenewMachineRes, Error> {
+        self.post(
+            "api/op/renew",
+            &api::RenewMachineReq {
+                mac_address: Cow::Owned(mac_address),
+            },
+        )
+        .await
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("invalid server url {0}")]
+    BadServer(String),
+    #[error("http error: {0}")]
+    Http(surf::Error),
+    #[error("server responded with status {0}")]
+    Status(u16),
+    #[error("unexpected response: {0}")]
+    Deserialize(serde_json::Error),
+    #[error("wrong password")]
+    LoginFailed,
+}
@@ -0,0 +1,1107 @@
+mod client;
+mod output;
+
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::process;
+use std::time::Duration;
+
+use async_std::future::timeout;
+use clap::{Parser, Subcommand};
+use client::{Client, Credential, Error, MacAddress};
+use monostate::MustBe;
+use proto::prelude::web as api;
+use serde::Serialize;
+
+// a boot without --wait is detached once the server hold the request this long
+const DETACH_AFTER: Duration = Duration::from_secs(2);
+// seconds a boot with --wait is waited for, a boot over several hops takes minutes
+const BOOT_TIMEOUT: u64 = 900;
+
+/// command-line admin tool of grub-wol
+#[derive(Parser)]
+#[command(name = "grubwolctl")]
+struct Args {
+    /// address of the web server
+    #[arg(long, env = "GRUBWOL_SERVER", default_value = "http://127.0.0.1:8000/")]
+    server: String,
+    /// login with password
+    #[arg(long, env = "GRUBWOL_PASSWORD", conflicts_with = "token")]
+    password: Option<String>,
+    /// authenticate with api token instead of login
+    #[arg(long, env = "GRUBWOL_TOKEN")]
+    token: Option<String>,
+    /// print json instead of table
+    #[arg(long)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// list machines, including unknown hosts waiting for enrollment
+    Machines,
+    /// list operating systems of a machine
+    Oss { mac_address: String },
+    /// show how long each boot transition of a machine take
+    Edges { mac_address: String },
+    /// print the boot graph of a machine as graphviz dot
+    Graph { mac_address: String },
+    /// show the hops a boot would take, without booting
+    Plan { mac_address: String, os: String },
+    /// boot a machine into an os, given by id, display name or "down"
+    Boot {
+        mac_address: String,
+        os: String,
+        /// wait until the machine reach the os
+        #[arg(long)]
+        wait: bool,
+        /// give up waiting after seconds
+        #[arg(long, requires = "wait", default_value_t = BOOT_TIMEOUT)]
+        timeout: u64,
+    },
+    /// enroll an unknown host, discovering its operating systems
+    Enroll {
+        mac_address: String,
+        display_name: String,
+    },
+    /// edit labels of an os, given by id, display name or alias
+    Label {
+        mac_address: String,
+        os: String,
+        /// override the name reported by host
+        #[arg(long, conflicts_with = "reset_name")]
+        name: Option<String>,
+        /// use the name reported by host again
+        #[arg(long)]
+        reset_name: bool,
+        /// replace aliases, repeatable
+        #[arg(long = "alias")]
+        aliases: Option<Vec<String>>,
+        #[arg(long)]
+        icon: Option<String>,
+        /// replace tags, repeatable
+        #[arg(long = "tag")]
+        tags: Option<Vec<String>>,
+        #[arg(long)]
+        notes: Option<String>,
+        /// mark as the default os of the machine
+        #[arg(long)]
+        default: Option<bool>,
+    },
+    /// change display name of a machine
+    Rename {
+        mac_address: String,
+        display_name: String,
+    },
+    /// forget a machine, its host show up as unknown again
+    Delete { mac_address: String },
+    /// discover operating systems of a machine again, keeping name and os id
+    Reenroll { mac_address: String },
+    /// dump every machine with its operating systems as json
+    Export,
+    /// show how magic packets of a machine are sent, changing the given fields
+    Wol {
+        mac_address: String,
+        /// forget every setting before changing the given fields
+        #[arg(long)]
+        reset: bool,
+        /// send through interface, binding its address and targeting its subnet broadcast
+        #[arg(long)]
+        interface: Option<String>,
+        /// bind this address instead of the one of interface
+        #[arg(long)]
+        source: Option<Ipv4Addr>,
+        /// directed subnet broadcast or unicast address
+        #[arg(long)]
+        target: Option<Ipv4Addr>,
+        /// 7 or 9
+        #[arg(long)]
+        port: Option<u16>,
+        /// SecureOn password, "aa:bb:cc:dd:ee:ff" or "1.2.3.4", kept if left out
+        #[arg(long, value_parser = parse_password)]
+        password: Option<Password>,
+        /// let an enrolled machine on the same segment send magic packets
+        #[arg(long, value_parser = parse_mac, conflicts_with = "no_relay")]
+        relay: Option<MacAddress>,
+        /// send magic packets from the server again
+        #[arg(long)]
+        no_relay: bool,
+    },
+    /// register a machine without agent, which can only be woken with "boot <mac> awake"
+    AddStandalone {
+        mac_address: String,
+        display_name: String,
+        #[command(flatten)]
+        probe: ProbeArgs,
+    },
+    /// show how a machine is checked to be up without its agent, changing it if given
+    Probe {
+        mac_address: String,
+        #[command(flatten)]
+        probe: ProbeArgs,
+        /// stop probing
+        #[arg(long, conflicts_with_all = ["tcp", "icmp"])]
+        none: bool,
+    },
+    /// show the baseboard management controller of a machine, replacing it if given
+    Bmc {
+        mac_address: String,
+        /// base url of the redfish service, like "https://10.0.0.2"
+        #[arg(long, conflicts_with = "ipmi", requires = "username")]
+        redfish: Option<String>,
+        /// "host[:port]" of ipmi over lan, reached by ipmitool on the server
+        #[arg(long, requires = "username")]
+        ipmi: Option<String>,
+        #[arg(long)]
+        username: Option<String>,
+        /// keep the current one if left out
+        #[arg(long, env = "GRUBWOL_BMC_PASSWORD", hide_env_values = true)]
+        password: Option<String>,
+        /// accept the self-signed certificate of the controller
+        #[arg(long)]
+        insecure: bool,
+        /// forget the controller
+        #[arg(long, conflicts_with_all = ["redfish", "ipmi"])]
+        none: bool,
+    },
+    /// show the smart plug feeding a machine, replacing it if given
+    Plug {
+        mac_address: String,
+        /// base url of a plug running tasmota, like "http://10.0.0.3"
+        #[arg(long, conflicts_with = "shelly")]
+        tasmota: Option<String>,
+        /// base url of a shelly plug
+        #[arg(long)]
+        shelly: Option<String>,
+        /// outlet of a multi-outlet plug, starting at 0
+        #[arg(long, default_value_t = 0)]
+        channel: u8,
+        /// web admin password, keep the current one if left out
+        #[arg(long, env = "GRUBWOL_PLUG_PASSWORD", hide_env_values = true)]
+        password: Option<String>,
+        /// seconds without power when power cycling
+        #[arg(long, default_value_t = 10)]
+        off_time: u64,
+        /// forget the plug
+        #[arg(long, conflicts_with_all = ["tasmota", "shelly"])]
+        none: bool,
+    },
+    /// show what is done when the agent of a machine doesn't answer shutdown or reboot,
+    /// changing it if given
+    Escalation {
+        mac_address: String,
+        /// "reset" or "off" done by the bmc, "cycle" done by the smart plug,
+        /// or "relay" done by the reset hook of the wake on lan relay
+        #[arg(long, value_parser = parse_escalation)]
+        action: Option<api::EscalationAction>,
+        /// seconds to wait for the answer
+        #[arg(long, default_value_t = 30)]
+        timeout: u64,
+        /// wait for the answer forever
+        #[arg(long, conflicts_with = "action")]
+        none: bool,
+    },
+    /// list recent out of band actions taken on a machine
+    Audit { mac_address: String },
+    /// edit the boot graph of a machine by hand, os are given like boot
+    Edit {
+        mac_address: String,
+        #[command(subcommand)]
+        edit: EditCommand,
+    },
+}
+
+#[derive(clap::Args)]
+struct ProbeArgs {
+    /// up once a connection to "address:port" is accepted
+    #[arg(long, conflicts_with = "icmp")]
+    tcp: Option<SocketAddrV4>,
+    /// up once address answer ping
+    #[arg(long)]
+    icmp: Option<Ipv4Addr>,
+    /// seconds a woken machine may take to answer, then its boot fails
+    #[arg(long, default_value_t = 180)]
+    timeout: u64,
+}
+
+impl ProbeArgs {
+    fn settings(&self) -> Option<api::ProbeSettings> {
+        let method = match (self.tcp, self.icmp) {
+            (Some(addr), _) => api::ProbeMethod::Tcp {
+                address: *addr.ip(),
+                port: addr.port(),
+            },
+            (None, Some(address)) => api::ProbeMethod::Icmp { address },
+            (None, None) => return None,
+        };
+        Some(api::ProbeSettings {
+            method,
+            timeout: self.timeout,
+        })
+    }
+}
+
+/// boot method is "wol", "shutdown", "grub:<entry>", "bmc:on", "bmc:off", "bmc:reset"
+/// "bmc:boot:<pxe|disk|cdrom|bios>", "plug:cycle" or "uefi:<boot number in hex>"
+#[derive(Subcommand)]
+enum EditCommand {
+    /// add a transition which can't be discovered
+    AddEdge {
+        from: String,
+        #[arg(value_parser = parse_method)]
+        method: api::BootMethod,
+        to: String,
+    },
+    RemoveEdge {
+        from: String,
+        #[arg(value_parser = parse_method)]
+        method: api::BootMethod,
+    },
+    /// never take a transition when booting
+    DisableEdge {
+        from: String,
+        #[arg(value_parser = parse_method)]
+        method: api::BootMethod,
+    },
+    EnableEdge {
+        from: String,
+        #[arg(value_parser = parse_method)]
+        method: api::BootMethod,
+    },
+    /// add an os which can't be discovered, reached by method from another os
+    AddNode {
+        display_name: String,
+        from: String,
+        #[arg(value_parser = parse_method)]
+        method: api::BootMethod,
+    },
+    RemoveNode {
+        os: String,
+    },
+}
+
+#[derive(Serialize)]
+struct ExportMachine<'a> {
+    #[serde(flatten)]
+    machine: api::MachineInfoInner<'a>,
+    oss: Vec<api::OsInfoInner<'a>>,
+}
+
+fn parse_mac(src: &str) -> Result<MacAddress, String> {
+    let bytes = src
+        .split([':', '-'])
+        .map(|x| u8::from_str_radix(x, 16))
+        .collect::<Result<Vec<u8>, _>>()
+        .map_err(|_| format!("invalid mac address {}", src))?;
+    bytes
+        .try_into()
+        .map_err(|_| format!("invalid mac address {}", src))
+}
+
+// a newtype, as clap would take Vec<u8> as repeated bytes
+#[derive(Clone)]
+struct Password(Vec<u8>);
+
+fn parse_password(src: &str) -> Result<Password, String> {
+    if let Ok(addr) = src.parse::<Ipv4Addr>() {
+        return Ok(Password(addr.octets().to_vec()));
+    }
+    src.split([':', '-'])
+        .map(|x| u8::from_str_radix(x, 16))
+        .collect::<Result<Vec<u8>, _>>()
+        .map(Password)
+        .map_err(|_| format!("invalid password {}", src))
+}
+
+fn parse_escalation(src: &str) -> Result<api::EscalationAction, String> {
+    match src.to_ascii_lowercase().as_str() {
+        "reset" => Ok(api::EscalationAction::BmcHardReset),
+        "off" => Ok(api::EscalationAction::BmcPowerOff),
+        "cycle" => Ok(api::EscalationAction::PowerCycle),
+        "relay" => Ok(api::EscalationAction::Relay),
+        _ => Err(format!(
+            "invalid escalation {}, expect reset, off, cycle or relay",
+            src
+        )),
+    }
+}
+
+fn parse_method(src: &str) -> Result<api::BootMethod, String> {
+    match src.to_ascii_lowercase().as_str() {
+        "wol" => Ok(api::BootMethod::WOL),
+        "shutdown" => Ok(api::BootMethod::Shutdown),
+        "bmc:on" => Ok(api::BootMethod::Bmc {
+            action: api::BmcAction::PowerOn,
+        }),
+        "bmc:off" => Ok(api::BootMethod::Bmc {
+            action: api::BmcAction::PowerOff,
+        }),
+        "bmc:reset" => Ok(api::BootMethod::Bmc {
+            action: api::BmcAction::HardReset,
+        }),
+        "plug:cycle" => Ok(api::BootMethod::PowerCycle),
+        method if method.starts_with("bmc:boot:") => {
+            let device = match &method["bmc:boot:".len()..] {
+                "pxe" => api::BootDevice::Pxe,
+                "disk" => api::BootDevice::Disk,
+                "cdrom" => api::BootDevice::Cdrom,
+                "bios" => api::BootDevice::BiosSetup,
+                _ => return Err(format!("invalid boot method {}", src)),
+            };
+            Ok(api::BootMethod::Bmc {
+                action: api::BmcAction::OneTimeBoot { device },
+            })
+        }
+        method if method.starts_with("uefi:") => {
+            // boot numbers are hex, as efibootmgr shows them
+            u16::from_str_radix(&method["uefi:".len()..], 16)
+                .map(|entry| api::BootMethod::Uefi { entry })
+                .map_err(|_| format!("invalid boot method {}", src))
+        }
+        method => method
+            .strip_prefix("grub:")
+            .and_then(|entry| entry.parse().ok())
+            .map(|entry| api::BootMethod::Grub { entry })
+            .ok_or_else(|| format!("invalid boot method {}", src)),
+    }
+}
+
+async fn find_os(
+    client: &Client,
+    mac_address: MacAddress,
+    os: &str,
+) -> Result<api::OsStatus, String> {
+    if os.eq_ignore_ascii_case("down") {
+        return Ok(api::OsStatus::Down {
+            kind: MustBe!("Down"),
+        });
+    }
+    let oss = client
+        .list_os(mac_address)
+        .await
+        .map_err(|e| e.to_string())?
+        .oss;
+    oss.iter()
+        .find(|info| {
+            info.id.to_string() == os
+                || info.display_name == os
+                || info.label.aliases.iter().any(|alias| alias == os)
+        })
+        .map(|info| api::OsStatus::Up {
+            kind: MustBe!("Up"),
+            id: info.id,
+        })
+        .ok_or_else(|| format!("no os named {}", os))
+}
+
+fn is_reached(state: &api::MachineState, os: &api::OsStatus) -> bool {
+    match (state, os) {
+        (api::MachineState::Down { .. }, api::OsStatus::Down { .. }) => true,
+        (api::MachineState::Up { id, .. }, api::OsStatus::Up { id: target, .. }) => id == target,
+        // without probe there is nothing to wait for
+        (api::MachineState::Standalone { awake, .. }, api::OsStatus::Up { .. }) => {
+            awake.unwrap_or(true)
+        }
+        _ => false,
+    }
+}
+
+async fn boot(
+    client: &Client,
+    mac_address: MacAddress,
+    os: api::OsStatus,
+    wait: bool,
+) -> Result<Option<api::BootRes>, Error> {
+    if !wait {
+        return match timeout(DETACH_AFTER, client.boot(mac_address, os)).await {
+            Ok(res) => res.map(Some),
+            Err(_) => Ok(None),
+        };
+    }
+    let res = client.boot(mac_address, os.clone()).await?;
+    if let api::BootRes::Success = res {
+        // server reply once the last hop finish, confirm the machine report the os
+        loop {
+            match client.info_machine(mac_address).await? {
+                Some(info) if is_reached(&info.state, &os) => break,
+                _ => async_std::task::sleep(Duration::from_secs(1)).await,
+            }
+        }
+    }
+    Ok(Some(res))
+}
+
+async fn run(args: Args) -> Result<(), String> {
+    let credential = match (args.token, args.password) {
+        (Some(token), _) => Credential::Token(token),
+        (None, Some(password)) => Credential::Password(password),
+        (None, None) => return Err("either --password or --token is required".to_owned()),
+    };
+    let client = Client::connect(&args.server, credential)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    match args.command {
+        Command::Machines => {
+            let list = client.list_machine().await.map_err(|e| e.to_string())?;
+            if args.json {
+                output::json(&list);
+            } else {
+                let rows: Vec<Vec<String>> = list
+                    .machines
+                    .iter()
+                    .map(|machine| {
+                        vec![
+                            output::mac_address(&machine.mac_address),
+                            machine.display_name.as_deref().unwrap_or("-").to_owned(),
+                            output::machine_state(&machine.state),
+                        ]
+                    })
+                    .collect();
+                output::table(&["MAC ADDRESS", "NAME", "STATE"], &rows);
+            }
+        }
+        Command::Oss { mac_address } => {
+            let mac_address = parse_mac(&mac_address)?;
+            let list = client
+                .list_os(mac_address)
+                .await
+                .map_err(|e| e.to_string())?;
+            if args.json {
+                output::json(&list);
+            } else {
+                let rows: Vec<Vec<String>> = list
+                    .oss
+                    .iter()
+                    .map(|os| {
+                        vec![
+                            os.id.to_string(),
+                            os.display_name.to_string(),
+                            if os.label.default { "*" } else { "" }.to_owned(),
+                            os.facts.distro_id.to_string(),
+                            os.facts.kernel_version.to_string(),
+                            os.label.tags.join(","),
+                        ]
+                    })
+                    .collect();
+                output::table(
+                    &["ID", "NAME", "DEFAULT", "DISTRO", "KERNEL", "TAGS"],
+                    &rows,
+                );
+            }
+        }
+        Command::Edges { mac_address } => {
+            let mac_address = parse_mac(&mac_address)?;
+            let list = client
+                .list_edge(mac_address)
+                .await
+                .map_err(|e| e.to_string())?;
+            if args.json {
+                output::json(&list);
+            } else {
+                let rows: Vec<Vec<String>> = list
+                    .edges
+                    .iter()
+                    .map(|edge| {
+                        vec![
+                            output::os_status(&edge.from),
+                            output::boot_method(&edge.method),
+                            output::os_status(&edge.to),
+                            output::millis(edge.min),
+                            output::millis(edge.median),
+                            output::millis(edge.p95),
+                            edge.samples.to_string(),
+                            edge.failures.to_string(),
+                            edge.misroutes.to_string(),
+                            output::edge_flags(edge),
+                        ]
+                    })
+                    .collect();
+                output::table(
+                    &[
+                        "FROM",
+                        "METHOD",
+                        "TO",
+                        "MIN",
+                        "MEDIAN",
+                        "P95",
+                        "SAMPLES",
+                        "FAILURES",
+                        "MISROUTES",
+                        "FLAGS",
+                    ],
+                    &rows,
+                );
+            }
+        }
+        Command::Graph { mac_address } => {
+            let mac_address = parse_mac(&mac_address)?;
+            let graph = client
+                .export_graph(mac_address)
+                .await
+                .map_err(|e| e.to_string())?;
+            if args.json {
+                output::json(&graph);
+            } else {
+                print!("{}", graph.dot);
+            }
+        }
+        Command::Plan { mac_address, os } => {
+            let mac_address = parse_mac(&mac_address)?;
+            let os = find_os(&client, mac_address, &os).await?;
+            let res = client
+                .plan(mac_address, os)
+                .await
+                .map_err(|e| e.to_string())?;
+            if args.json {
+                output::json(&res);
+                return Ok(());
+            }
+            match res {
+                api::PlanRes::Success {
+                    from,
+                    hops,
+                    estimate,
+                } => {
+                    let rows: Vec<Vec<String>> = hops
+                        .iter()
+                        .map(|hop| {
+                            vec![
+                                output::boot_method(&hop.method),
+                                output::os_status(&hop.to),
+                                output::millis(Some(hop.estimate)),
+                            ]
+                        })
+                        .collect();
+                    println!("from {}", output::os_status(&from));
+                    output::table(&["METHOD", "TO", "ESTIMATE"], &rows);
+                    println!("total {}", output::millis(Some(estimate)));
+                }
+                api::PlanRes::BadGraph => return Err("os is unreachable".to_owned()),
+                api::PlanRes::NotFound => return Err("machine not found".to_owned()),
+            }
+        }
+        Command::Boot {
+            mac_address,
+            os,
+            wait,
+            timeout: timeout_,
+        } => {
+            let mac_address = parse_mac(&mac_address)?;
+            let os = find_os(&client, mac_address, &os).await?;
+            let res = timeout(
+                Duration::from_secs(timeout_),
+                boot(&client, mac_address, os, wait),
+            )
+            .await
+            .map_err(|_| "timeout waiting for the boot, it keeps running on server".to_owned())?
+            .map_err(|e| e.to_string())?;
+            match res {
+                Some(api::BootRes::Success) => println!("boot finished"),
+                Some(api::BootRes::Fail) => return Err("boot failed".to_owned()),
+                Some(api::BootRes::NotFound) => {
+                    return Err("machine not found or not connected".to_owned())
+                }
+                Some(api::BootRes::AgentMissing) => {
+                    return Err("machine woke, but its agent didn't connect".to_owned())
+                }
+                Some(api::BootRes::NotWoken) => return Err("machine didn't wake".to_owned()),
+                None => println!("boot started"),
+            }
+        }
+        Command::Enroll {
+            mac_address,
+            display_name,
+        } => {
+            let mac_address = parse_mac(&mac_address)?;
+            match client
+                .new_machine(mac_address, display_name)
+                .await
+                .map_err(|e| e.to_string())?
+            {
+                api::NewMachineRes::Success => println!("machine enrolled"),
+                api::NewMachineRes::Fail => return Err("enrollment failed".to_owned()),
+                api::NewMachineRes::NotFound => {
+                    return Err("no unknown host with this mac address".to_owned())
+                }
+            }
+        }
+        Command::Label {
+            mac_address,
+            os,
+            name,
+            reset_name,
+            aliases,
+            icon,
+            tags,
+            notes,
+            default,
+        } => {
+            let mac_address = parse_mac(&mac_address)?;
+            let id = match find_os(&client, mac_address, &os).await? {
+                api::OsStatus::Up { id, .. } => id,
+                api::OsStatus::Down { .. } => return Err("cannot label down".to_owned()),
+            };
+            // start from current label, only the given fields change
+            let mut label = client
+                .list_os(mac_address)
+                .await
+                .map_err(|e| e.to_string())?
+                .oss
+                .into_iter()
+                .find(|info| info.id == id)
+                .map(|info| info.label)
+                .unwrap_or_default();
+            if reset_name {
+                label.display_name = None;
+            }
+            if let Some(name) = name {
+                label.display_name = Some(name.into());
+            }
+            if let Some(aliases) = aliases {
+                label.aliases = aliases.into_iter().map(Into::into).collect();
+            }
+            if let Some(icon) = icon {
+                label.icon = Some(icon.into());
+            }
+            if let Some(tags) = tags {
+                label.tags = tags.into_iter().map(Into::into).collect();
+            }
+            if let Some(notes) = notes {
+                label.notes = notes.into();
+            }
+            if let Some(default) = default {
+                label.default = default;
+            }
+            match client
+                .edit_os(mac_address, id, label)
+                .await
+                .map_err(|e| e.to_string())?
+            {
+                api::EditOsRes::Success => println!("os labeled"),
+                api::EditOsRes::Invalid { reason } => return Err(reason),
+                api::EditOsRes::NotFound => return Err("os not found".to_owned()),
+            }
+        }
+        Command::Rename {
+            mac_address,
+            display_name,
+        } => {
+            let mac_address = parse_mac(&mac_address)?;
+            match client
+                .rename_machine(mac_address, display_name)
+                .await
+                .map_err(|e| e.to_string())?
+            {
+                api::RenameMachineRes::Success => println!("machine renamed"),
+                api::RenameMachineRes::NotFound => return Err("machine not found".to_owned()),
+            }
+        }
+        Command::Edit { mac_address, edit } => {
+            let mac_address = parse_mac(&mac_address)?;
+            let os = |name: String| {
+                let client = &client;
+                async move { find_os(client, mac_address, &name).await }
+            };
+            let edit = match edit {
+                EditCommand::AddEdge { from, method, to } => api::GraphEdit::AddEdge {
+                    from: os(from).await?,
+                    method,
+                    to: os(to).await?,
+                },
+                EditCommand::RemoveEdge { from, method } => api::GraphEdit::RemoveEdge {
+                    from: os(from).await?,
+                    method,
+                },
+                EditCommand::DisableEdge { from, method } => api::GraphEdit::DisableEdge {
+                    from: os(from).await?,
+                    method,
+                    disabled: true,
+                },
+                EditCommand::EnableEdge { from, method } => api::GraphEdit::DisableEdge {
+                    from: os(from).await?,
+                    method,
+                    disabled: false,
+                },
+                EditCommand::AddNode {
+                    display_name,
+                    from,
+                    method,
+                } => api::GraphEdit::AddNode {
+                    display_name,
+                    from: os(from).await?,
+                    method,
+                },
+                EditCommand::RemoveNode { os: name } => match os(name).await? {
+                    api::OsStatus::Up { id, .. } => api::GraphEdit::RemoveNode { id },
+                    api::OsStatus::Down { .. } => return Err("cannot remove down".to_owned()),
+                },
+            };
+            match client
+                .edit_graph(mac_address, edit)
+                .await
+                .map_err(|e| e.to_string())?
+            {
+                api::EditGraphRes::Success => println!("graph edited"),
+                api::EditGraphRes::Added { id } => println!("os added with id {}", id),
+                api::EditGraphRes::Invalid { reason } => return Err(reason),
+                api::EditGraphRes::NotFound => return Err("machine not found".to_owned()),
+            }
+        }
+        Command::Wol {
+            mac_address,
+            reset,
+            interface,
+            source,
+            target,
+            port,
+            password,
+            relay,
+            no_relay,
+        } => {
+            let mac_address = parse_mac(&mac_address)?;
+            let mut settings = match client.wol(mac_address).await.map_err(|e| e.to_string())? {
+                api::WolRes::Success { settings } => settings,
+                api::WolRes::NotFound => return Err("machine not found".to_owned()),
+            };
+            let edited = reset
+                || interface.is_some()
+                || source.is_some()
+                || target.is_some()
+                || port.is_some()
+                || password.is_some()
+                || relay.is_some()
+                || no_relay;
+            if reset {
+                settings = api::WolSettings {
+                    interface: None,
+                    source: None,
+                    target: None,
+                    port: 9,
+                    password: Some(Vec::new()),
+                    relay: None,
+                };
+            }
+            settings.interface = interface.or(settings.interface);
+            settings.source = source.or(settings.source);
+            settings.target = target.or(settings.target);
+            settings.port = port.unwrap_or(settings.port);
+            settings.password = password.map(|x| x.0).or(settings.password);
+            settings.relay = relay.or(settings.relay).filter(|_| !no_relay);
+            if edited {
+                match client
+                    .edit_wol(mac_address, &settings)
+                    .await
+                    .map_err(|e| e.to_string())?
+                {
+                    api::EditWolRes::Success => {}
+                    api::EditWolRes::Invalid { reason } => return Err(reason),
+                    api::EditWolRes::NotFound => return Err("machine not found".to_owned()),
+                }
+            }
+            // shown like the server would
+            settings.password = None;
+            if args.json {
+                output::json(&settings);
+            } else {
+                output::wol(&settings);
+            }
+        }
+        Command::AddStandalone {
+            mac_address,
+            display_name,
+            probe,
+        } => {
+            let mac_address = parse_mac(&mac_address)?;
+            match client
+                .new_standalone(mac_address, display_name, probe.settings())
+                .await
+                .map_err(|e| e.to_string())?
+            {
+                api::NewStandaloneRes::Success => println!("standalone machine added"),
+                api::NewStandaloneRes::Exists => {
+                    return Err("a machine or unknown host has this mac address".to_owned())
+                }
+                api::NewStandaloneRes::Invalid { reason } => return Err(reason),
+            }
+        }
+        Command::Probe {
+            mac_address,
+            probe,
+            none,
+        } => {
+            let mac_address = parse_mac(&mac_address)?;
+            let mut settings = match client.probe(mac_address).await.map_err(|e| e.to_string())? {
+                api::ProbeRes::Success { probe } => probe,
+                api::ProbeRes::NotFound => return Err("machine not found".to_owned()),
+            };
+            if none || probe.settings().is_some() {
+                settings = probe.settings();
+                match client
+                    .edit_probe(mac_address, settings.clone())
+                    .await
+                    .map_err(|e| e.to_string())?
+                {
+                    api::EditProbeRes::Success => {}
+                    api::EditProbeRes::NotFound => return Err("machine not found".to_owned()),
+                }
+            }
+            if args.json {
+                output::json(&settings);
+            } else {
+                println!("{}", output::probe(settings.as_ref()));
+            }
+        }
+        Command::Bmc {
+            mac_address,
+            redfish,
+            ipmi,
+            username,
+            password,
+            insecure,
+            none,
+        } => {
+            let mac_address = parse_mac(&mac_address)?;
+            let mut settings = match client.bmc(mac_address).await.map_err(|e| e.to_string())? {
+                api::BmcRes::Success { settings } => settings,
+                api::BmcRes::NotFound => return Err("machine not found".to_owned()),
+            };
+            let replaced = match (redfish, ipmi) {
+                (Some(address), _) => Some((api::BmcProtocol::Redfish, address)),
+                (None, Some(address)) => Some((api::BmcProtocol::Ipmi, address)),
+                (None, None) => None,
+            };
+            if none || replaced.is_some() {
+                settings = replaced.map(|(protocol, address)| api::BmcSettings {
+                    protocol,
+                    address,
+                    username: username.unwrap_or_default(),
+                    password,
+                    insecure,
+                });
+                match client
+                    .edit_bmc(mac_address, settings.clone())
+                    .await
+                    .map_err(|e| e.to_string())?
+                {
+                    api::EditBmcRes::Success => {}
+                    api::EditBmcRes::Invalid { reason } => return Err(reason),
+                    api::EditBmcRes::NotFound => return Err("machine not found".to_owned()),
+                }
+            }
+            if args.json {
+                output::json(&settings);
+            } else {
+                output::bmc(settings.as_ref());
+            }
+        }
+        Command::Plug {
+            mac_address,
+            tasmota,
+            shelly,
+            channel,
+            password,
+            off_time,
+            none,
+        } => {
+            let mac_address = parse_mac(&mac_address)?;
+            let mut settings = match client.plug(mac_address).await.map_err(|e| e.to_string())? {
+                api::PlugRes::Success { settings } => settings,
+                api::PlugRes::NotFound => return Err("machine not found".to_owned()),
+            };
+            let replaced = match (tasmota, shelly) {
+                (Some(address), _) => Some((api::PlugKind::Tasmota, address)),
+                (None, Some(address)) => Some((api::PlugKind::Shelly, address)),
+                (None, None) => None,
+            };
+            if none || replaced.is_some() {
+                settings = replaced.map(|(kind, address)| api::PlugSettings {
+                    kind,
+                    address,
+                    channel,
+                    password,
+                    off_time,
+                });
+                match client
+                    .edit_plug(mac_address, settings.clone())
+                    .await
+                    .map_err(|e| e.to_string())?
+                {
+                    api::EditPlugRes::Success => {}
+                    api::EditPlugRes::Invalid { reason } => return Err(reason),
+                    api::EditPlugRes::NotFound => return Err("machine not found".to_owned()),
+                }
+            }
+            if args.json {
+                output::json(&settings);
+            } else {
+                output::plug(settings.as_ref());
+            }
+        }
+        Command::Escalation {
+            mac_address,
+            action,
+            timeout,
+            none,
+        } => {
+            let mac_address = parse_mac(&mac_address)?;
+            let mut escalation = match client
+                .escalation(mac_address)
+                .await
+                .map_err(|e| e.to_string())?
+            {
+                api::EscalationRes::Success { escalation } => escalation,
+                api::EscalationRes::NotFound => return Err("machine not found".to_owned()),
+            };
+            if none || action.is_some() {
+                escalation = action.map(|action| api::EscalationSettings { action, timeout });
+                match client
+                    .edit_escalation(mac_address, escalation.clone())
+                    .await
+                    .map_err(|e| e.to_string())?
+                {
+                    api::EditEscalationRes::Success => {}
+                    api::EditEscalationRes::Invalid { reason } => return Err(reason),
+                    api::EditEscalationRes::NotFound => return Err("machine not found".to_owned()),
+                }
+            }
+            if args.json {
+                output::json(&escalation);
+            } else {
+                println!("{}", output::escalation(escalation.as_ref()));
+            }
+        }
+        Command::Audit { mac_address } => {
+            let mac_address = parse_mac(&mac_address)?;
+            let entries = match client.audit(mac_address).await.map_err(|e| e.to_string())? {
+                api::AuditRes::Success { entries } => entries,
+                api::AuditRes::NotFound => return Err("machine not found".to_owned()),
+            };
+            if args.json {
+                output::json(&entries);
+            } else {
+                output::audit(&entries);
+            }
+        }
+        Command::Delete { mac_address } => {
+            let mac_address = parse_mac(&mac_address)?;
+            match client
+                .forget_machine(mac_address)
+                .await
+                .map_err(|e| e.to_string())?
+            {
+                api::ForgetMachineRes::Success => println!("machine deleted"),
+                api::ForgetMachineRes::NotFound => return Err("machine not found".to_owned()),
+            }
+        }
+        Command::Reenroll { mac_address } => {
+            let mac_address = parse_mac(&mac_address)?;
+            match client
+                .renew_machine(mac_address)
+                .await
+                .map_err(|e| e.to_string())?
+            {
+                api::RenewMachineRes::Success => println!("machine re-enrolled"),
+                api::RenewMachineRes::Fail => return Err("re-enrollment failed".to_owned()),
+                api::RenewMachineRes::NotFound => {
+                    return Err("machine not found or not connected".to_owned())
+                }
+            }
+        }
+        Command::Export => {
+            let machines = client
+                .list_machine()
+                .await
+                .map_err(|e| e.to_string())?
+                .machines;
+            let mut export = Vec::new();
+            for machine in machines {
+                let oss = match machine.state {
+                    api::MachineState::Uninited { .. } => Vec::new(),
+                    _ => {
+                        client
+                            .list_os(*machine.mac_address)
+                            .await
+                            .map_err(|e| e.to_string())?
+                            .oss
+                    }
+                };
+                export.push(ExportMachine { machine, oss });
+            }
+            output::json(&export);
+        }
+    }
+    Ok(())
+}
+
+#[async_std::main]
+async fn main() {
+    if let Err(err) = run(Args::parse()).await {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_mac, parse_method, parse_password};
+    use proto::prelude::web as api;
+
+    #[test]
+    fn mac_address() {
+        assert_eq!(
+            parse_mac("00:1a:2B:3c:4d:ff"),
+            Ok([0x00, 0x1a, 0x2b, 0x3c, 0x4d, 0xff])
+        );
+        assert_eq!(
+            parse_mac("00-1a-2b-3c-4d-ff"),
+            Ok([0x00, 0x1a, 0x2b, 0x3c, 0x4d, 0xff])
+        );
+        assert!(parse_mac("00:1a:2b:3c:4d").is_err());
+        assert!(parse_mac("00:1a:2b:3c:4d:zz").is_err());
+    }
+
+    #[test]
+    fn password() {
+        assert_eq!(parse_password("1.2.3.4").unwrap().0, [1, 2, 3, 4]);
+        assert_eq!(
+            parse_password("aa:bb:cc:dd:ee:ff").unwrap().0,
+            [0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff]
+        );
+        assert!(parse_password("secret").is_err());
+    }
+
+    #[test]
+    fn method() {
+        assert!(matches!(parse_method("WOL"), Ok(api::BootMethod::WOL)));
+        assert!(matches!(
+            parse_method("shutdown"),
+            Ok(api::BootMethod::Shutdown)
+        ));
+        assert!(matches!(
+            parse_method("grub:42"),
+            Ok(api::BootMethod::Grub { entry: 42 })
+        ));
+        assert!(parse_method("grub:").is_err());
+        assert!(matches!(
+            parse_method("uefi:000A"),
+            Ok(api::BootMethod::Uefi { entry: 10 })
+        ));
+        assert!(parse_method("uefi:10000").is_err());
+        assert!(matches!(
+            parse_method("bmc:boot:pxe"),
+            Ok(api::BootMethod::Bmc {
+                action: api::BmcAction::OneTimeBoot {
+                    device: api::BootDevice::Pxe
+                }
+            })
+        ));
+        assert!(parse_method("bmc:boot:floppy").is_err());
+        assert!(matches!(
+            parse_method("plug:cycle"),
+            Ok(api::BootMethod::PowerCycle)
+        ));
+        assert!(parse_method("reboot").is_err());
+    }
+}
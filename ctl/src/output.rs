@@ -0,0 +1,245 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use proto::prelude::web as api;
+
+pub fn mac_address(mac_address: &[u8; 6]) -> String {
+    mac_address
+        .iter()
+        .map(|x| format!("{:02x}", x))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+pub fn machine_state(state: &api::MachineState) -> String {
+    match state {
+        api::MachineState::Down { .. } => "Down".to_owned(),
+        api::MachineState::Uninited { .. } => "Uninited".to_owned(),
+        api::MachineState::Up { id, .. } => format!("Up({})", id),
+        api::MachineState::Standalone { awake, .. } => match awake {
+            Some(true) => "Standalone(awake)".to_owned(),
+            Some(false) => "Standalone(asleep)".to_owned(),
+            None => "Standalone".to_owned(),
+        },
+    }
+}
+
+pub fn os_status(os: &api::OsStatus) -> String {
+    match os {
+        api::OsStatus::Down { .. } => "Down".to_owned(),
+        api::OsStatus::Up { id, .. } => format!("Up({})", id),
+    }
+}
+
+pub fn boot_method(method: &api::BootMethod) -> String {
+    match method {
+        api::BootMethod::WOL => "WOL".to_owned(),
+        api::BootMethod::Grub { entry } => format!("Grub({})", entry),
+        api::BootMethod::Uefi { entry } => format!("Uefi({:04X})", entry),
+        api::BootMethod::Shutdown => "Shutdown".to_owned(),
+        api::BootMethod::Bmc { action } => match action {
+            api::BmcAction::PowerOn => "Bmc(PowerOn)".to_owned(),
+            api::BmcAction::PowerOff => "Bmc(PowerOff)".to_owned(),
+            api::BmcAction::HardReset => "Bmc(HardReset)".to_owned(),
+            api::BmcAction::OneTimeBoot { device } => {
+                let device = match device {
+                    api::BootDevice::Pxe => "Pxe",
+                    api::BootDevice::Disk => "Disk",
+                    api::BootDevice::Cdrom => "Cdrom",
+                    api::BootDevice::BiosSetup => "BiosSetup",
+                };
+                format!("Bmc(OneTimeBoot({}))", device)
+            }
+        },
+        api::BootMethod::PowerCycle => "PowerCycle".to_owned(),
+    }
+}
+
+/// degraded, manual and disabled of an edge, comma separated
+pub fn edge_flags(edge: &api::EdgeInfo) -> String {
+    [
+        (edge.degraded, "degraded"),
+        (edge.manual, "manual"),
+        (edge.disabled, "disabled"),
+    ]
+    .iter()
+    .filter(|(set, _)| *set)
+    .map(|(_, flag)| *flag)
+    .collect::<Vec<_>>()
+    .join(",")
+}
+
+/// the server never send the password back
+pub fn wol(settings: &api::WolSettings) {
+    let addr = |addr: Option<std::net::Ipv4Addr>| match addr {
+        Some(addr) => addr.to_string(),
+        None => "-".to_owned(),
+    };
+    let rows = vec![
+        vec![
+            "interface".to_owned(),
+            settings.interface.clone().unwrap_or_else(|| "-".to_owned()),
+        ],
+        vec!["source".to_owned(), addr(settings.source)],
+        vec!["target".to_owned(), addr(settings.target)],
+        vec!["port".to_owned(), settings.port.to_string()],
+        vec![
+            "relay".to_owned(),
+            match &settings.relay {
+                Some(relay) => mac_address(relay),
+                None => "-".to_owned(),
+            },
+        ],
+    ];
+    table(&["SETTING", "VALUE"], &rows);
+}
+
+/// the server never send the password back
+pub fn bmc(settings: Option<&api::BmcSettings>) {
+    let settings = match settings {
+        Some(settings) => settings,
+        None => return println!("none"),
+    };
+    let protocol = match settings.protocol {
+        api::BmcProtocol::Redfish => "redfish",
+        api::BmcProtocol::Ipmi => "ipmi",
+    };
+    let rows = vec![
+        vec!["protocol".to_owned(), protocol.to_owned()],
+        vec!["address".to_owned(), settings.address.clone()],
+        vec!["username".to_owned(), settings.username.clone()],
+        vec!["insecure".to_owned(), settings.insecure.to_string()],
+    ];
+    table(&["SETTING", "VALUE"], &rows);
+}
+
+/// the server never send the password back
+pub fn plug(settings: Option<&api::PlugSettings>) {
+    let settings = match settings {
+        Some(settings) => settings,
+        None => return println!("none"),
+    };
+    let kind = match settings.kind {
+        api::PlugKind::Tasmota => "tasmota",
+        api::PlugKind::Shelly => "shelly",
+    };
+    let rows = vec![
+        vec!["kind".to_owned(), kind.to_owned()],
+        vec!["address".to_owned(), settings.address.clone()],
+        vec!["channel".to_owned(), settings.channel.to_string()],
+        vec!["off time".to_owned(), format!("{}s", settings.off_time)],
+    ];
+    table(&["SETTING", "VALUE"], &rows);
+}
+
+fn escalation_action(action: &api::EscalationAction) -> &'static str {
+    match action {
+        api::EscalationAction::BmcHardReset => "bmc reset",
+        api::EscalationAction::BmcPowerOff => "bmc power off",
+        api::EscalationAction::PowerCycle => "plug power cycle",
+        api::EscalationAction::Relay => "relay reset hook",
+    }
+}
+
+pub fn escalation(escalation: Option<&api::EscalationSettings>) -> String {
+    match escalation {
+        Some(escalation) => format!(
+            "{} after {}s",
+            escalation_action(&escalation.action),
+            escalation.timeout
+        ),
+        None => "none".to_owned(),
+    }
+}
+
+/// newest first, with how long ago
+pub fn audit(entries: &[api::AuditEntry]) {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs())
+        .unwrap_or_default();
+    let request = |request: &api::GracefulRequest| match request {
+        api::GracefulRequest::Shutdown => "shutdown",
+        api::GracefulRequest::Reboot => "reboot",
+    };
+    let rows: Vec<Vec<String>> = entries
+        .iter()
+        .rev()
+        .map(|entry| {
+            let ago = match now.saturating_sub(entry.time) {
+                secs if secs < 60 => format!("{}s ago", secs),
+                secs if secs < 3600 => format!("{}m ago", secs / 60),
+                secs if secs < 86400 => format!("{}h ago", secs / 3600),
+                secs => format!("{}d ago", secs / 86400),
+            };
+            match &entry.event {
+                api::AuditEvent::Escalated {
+                    request: req,
+                    action,
+                } => vec![
+                    ago,
+                    format!("{} unanswered", request(req)),
+                    escalation_action(action).to_owned(),
+                ],
+                api::AuditEvent::EscalationFailed {
+                    request: req,
+                    action,
+                    reason,
+                } => vec![
+                    ago,
+                    format!("{} unanswered", request(req)),
+                    format!("{} failed: {}", escalation_action(action), reason),
+                ],
+            }
+        })
+        .collect();
+    table(&["WHEN", "EVENT", "ACTION"], &rows);
+}
+
+pub fn probe(probe: Option<&api::ProbeSettings>) -> String {
+    let probe = match probe {
+        Some(probe) => probe,
+        None => return "none".to_owned(),
+    };
+    match probe.method {
+        api::ProbeMethod::Tcp { address, port } => {
+            format!("tcp {}:{} within {}s", address, port, probe.timeout)
+        }
+        api::ProbeMethod::Icmp { address } => {
+            format!("icmp {} within {}s", address, probe.timeout)
+        }
+    }
+}
+
+pub fn millis(millis: Option<u64>) -> String {
+    match millis {
+        Some(millis) => format!("{:.1}s", millis as f64 / 1000.0),
+        None => "-".to_owned(),
+    }
+}
+
+/// print rows aligned under the header, column width fit the widest cell
+pub fn table(header: &[&str], rows: &[Vec<String>]) {
+    let mut width: Vec<usize> = header.iter().map(|x| x.len()).collect();
+    for row in rows {
+        for (w, cell) in width.iter_mut().zip(row) {
+            *w = (*w).max(cell.len());
+        }
+    }
+    let line = |cells: Vec<&str>| {
+        let line = cells
+            .iter()
+            .zip(&width)
+            .map(|(cell, w)| format!("{:<w$}", cell, w = w))
+            .collect::<Vec<_>>()
+            .join("  ");
+        println!("{}", line.trim_end());
+    };
+    line(header.to_vec());
+    for row in rows {
+        line(row.iter().map(|x| x.as_str()).collect());
+    }
+}
+
+pub fn json<T: serde::Serialize>(value: &T) {
+    println!("{}", serde_json::to_string_pretty(value).unwrap());
+}
@@ -1,8 +1,10 @@
 pub const SERVER_PORT: u16 = 10870;
 pub const SERVICE_TYPE: &str = "_grubwol._udp.local.";
 pub(super) type APIVersionType = u64;
-pub const APIVERSION: APIVersionType = 4;
+pub const APIVERSION: APIVersionType = 7;
 pub type GrubId = u64;
+// number of a firmware boot entry, the XXXX of BootXXXX
+pub type UefiId = u16;
 pub type ID = u64;
 pub type Integer = i64;
 pub type PacketPrefix = u64;
@@ -6,6 +6,10 @@ pub type Ping=constant::ID;
 pub type Reboot=();
 pub type InitId=();
 pub type Shutdown=();
+pub type WakeOnLan=Result<(), String>;
+pub type UefiQuery=Vec<UefiInfo>;
+pub type BootNext=Result<(), String>;
+pub type Reset=Result<(), String>;
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub enum Packet {
     Handshake(Handshake),
@@ -15,6 +19,10 @@ pub enum Packet {
     GrubQuery(GrubQuery),
     Ping(Ping),
     OsQuery(OsQuery),
+    WakeOnLan(WakeOnLan),
+    UefiQuery(UefiQuery),
+    BootNext(BootNext),
+    Reset(Reset),
 }
 
 
@@ -31,7 +39,20 @@ pub struct GrubInfo {
     pub grub_sec: constant::GrubId,
 }
 
+// firmware boot entry loading an os from disk, network and removable entries aren't reported
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct UefiInfo {
+    pub boot_num: constant::UefiId,
+    pub description: String,
+}
+
+// facts of the running os, a field the agent can't tell is left empty
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub struct OsQuery {
     pub display_name: String,
+    // "uname -r" on linux, build number on windows
+    pub kernel_version: String,
+    // ID of os-release, "windows" on windows
+    pub distro_id: String,
+    pub hostname: String,
 }
@@ -1,9 +1,12 @@
 use serde::{Deserialize, Serialize};
+use std::net::Ipv4Addr;
 
 use crate::constant;
 
 pub type Reboot=constant::GrubId;
 pub type InitId=constant::ID;
+pub type BootNext=constant::UefiId;
+pub type Reset=[u8; 6];
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub enum Packet {
@@ -14,6 +17,10 @@ pub enum Packet {
     GrubQuery, // query: query available grub path
     Ping,
     OsQuery, // query: query current os info
+    WakeOnLan(WakeOnLan), // rpc: send magic packet on local segment, relaying for another machine
+    UefiQuery, // query: query firmware boot entries
+    BootNext(constant::UefiId), // rpc: set uefi BootNext and reboot
+    Reset(Reset), // rpc: run the reset hook on a hung machine of local segment, relaying for it
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
@@ -21,3 +28,12 @@ pub struct Handshake {
     pub ident: constant::ProtoIdentType,
     pub version: constant::APIVersionType,
 }
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct WakeOnLan {
+    pub mac_address: [u8; 6],
+    // limited broadcast of the relay if none
+    pub target: Option<Ipv4Addr>,
+    pub port: u16,
+    pub password: Option<Vec<u8>>,
+}
@@ -1 +1,827 @@
+use monostate::MustBe;
+/// types of the http api, shared by the server and its clients
+use crate::constant::{GrubId, UefiId, ID};
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, net::Ipv4Addr};
 
+// boot into a os (request)
+// POsT /api/op/boot
+// cts
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BootReq<'a> {
+    pub mac_address: Cow<'a, [u8; 6]>,
+    pub os: OsStatus,
+}
+// stc
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind")]
+pub enum BootRes {
+    // the os is up with its agent
+    Success,
+    Fail,
+    NotFound,
+    // the probe reached the machine, but its agent never connected
+    AgentMissing,
+    // neither the probe nor the agent reached the machine in time
+    NotWoken,
+}
+
+// get a list of machine
+// POsT /api/get/machines
+// cts: no payload
+// stc
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MachineList<'a> {
+    pub machines: Vec<MachineInfoInner<'a>>,
+}
+
+// get detailed info of a machine
+// POsT /api/get/machine
+// cts
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MachineInfoReq<'a> {
+    pub mac_address: Cow<'a, [u8; 6]>,
+}
+// stc
+// return type is wrapped in option
+pub type MachineInfo<'a> = Option<MachineInfoInner<'a>>;
+
+// get a list of os
+// POsT /api/get/oss
+// cts
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct OsListReq<'a> {
+    pub mac_address: Cow<'a, [u8; 6]>,
+}
+// stc
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct OsList<'a> {
+    pub oss: Vec<OsInfoInner<'a>>,
+}
+
+// // get detailed info of an os
+// // POsT /api/get/os
+// // cts
+// #[derive(Deserialize, Serialize)]
+// pub struct OsInfoReq{
+//     pub mac_address: [u8; 6],
+//     pub os:ID
+// }
+// // stc
+// pub type OsInfo<'a>=Option<OsInfoInner<'a>>;
+
+// init new machine
+// POsT /api/op/new
+// cts
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct NewMachineReq<'a> {
+    pub display_name: Cow<'a, str>,
+    pub mac_address: Cow<'a, [u8; 6]>,
+}
+// stc
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind")]
+pub enum NewMachineRes {
+    Success,
+    Fail,
+    NotFound,
+}
+
+// rename a machine
+// POsT /api/op/rename
+// cts
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RenameMachineReq<'a> {
+    pub display_name: Cow<'a, str>,
+    pub mac_address: Cow<'a, [u8; 6]>,
+}
+// stc
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind")]
+pub enum RenameMachineRes {
+    Success,
+    NotFound,
+}
+
+// forget a machine, the host become uninited
+// POsT /api/op/forget
+// cts
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ForgetMachineReq<'a> {
+    pub mac_address: Cow<'a, [u8; 6]>,
+}
+// stc
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind")]
+pub enum ForgetMachineRes {
+    Success,
+    NotFound,
+}
+
+// discover os of an inited machine again, keeping its name and os id
+// POsT /api/op/renew
+// cts
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RenewMachineReq<'a> {
+    pub mac_address: Cow<'a, [u8; 6]>,
+}
+// stc
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind")]
+pub enum RenewMachineRes {
+    Success,
+    Fail,
+    NotFound,
+}
+
+// edit labels of an os, replacing the previous ones
+// POsT /api/op/os
+// cts
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EditOsReq<'a> {
+    pub mac_address: Cow<'a, [u8; 6]>,
+    pub id: ID,
+    pub label: OsLabel<'a>,
+}
+// stc
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind")]
+pub enum EditOsRes {
+    Success,
+    // refused, the label is unchanged
+    Invalid { reason: String },
+    NotFound,
+}
+
+// get boot timing of every edge of a machine
+// POsT /api/get/edges
+// cts
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EdgeListReq<'a> {
+    pub mac_address: Cow<'a, [u8; 6]>,
+}
+// stc
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EdgeList {
+    pub edges: Vec<EdgeInfo>,
+}
+
+// edit boot graph of a machine by hand
+// POsT /api/op/graph
+// cts
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EditGraphReq<'a> {
+    pub mac_address: Cow<'a, [u8; 6]>,
+    pub edit: GraphEdit,
+}
+// stc
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind")]
+pub enum EditGraphRes {
+    Success,
+    // id issued to an added os
+    Added { id: ID },
+    // refused, the graph is unchanged
+    Invalid { reason: String },
+    NotFound,
+}
+
+// export boot graph of a machine, for drawing
+// POsT /api/get/graph
+// cts
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GraphReq<'a> {
+    pub mac_address: Cow<'a, [u8; 6]>,
+}
+// stc
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GraphExport {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<EdgeInfo>,
+    // same graph in graphviz dot
+    pub dot: String,
+}
+
+// plan a boot from current os without running it
+// POsT /api/get/plan
+// cts
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PlanReq<'a> {
+    pub mac_address: Cow<'a, [u8; 6]>,
+    pub os: OsStatus,
+}
+// stc
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind")]
+pub enum PlanRes {
+    // estimate in milliseconds, sum of every hop
+    Success {
+        from: OsStatus,
+        hops: Vec<PlanHop>,
+        estimate: u64,
+    },
+    // the os can't be reached from current os
+    BadGraph,
+    NotFound,
+}
+
+// get how magic packets of a machine are sent, its password is never sent back
+// POsT /api/get/wol
+// cts
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WolReq<'a> {
+    pub mac_address: Cow<'a, [u8; 6]>,
+}
+// stc
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind")]
+pub enum WolRes {
+    Success { settings: WolSettings },
+    NotFound,
+}
+
+// set how magic packets of a machine are sent
+// POsT /api/op/wol
+// cts
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EditWolReq<'a> {
+    pub mac_address: Cow<'a, [u8; 6]>,
+    pub settings: WolSettings,
+}
+// stc
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind")]
+pub enum EditWolRes {
+    Success,
+    // refused, the settings are unchanged
+    Invalid { reason: String },
+    NotFound,
+}
+
+// register a machine without agent, which can only be woken
+// POsT /api/op/standalone
+// cts
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct NewStandaloneReq<'a> {
+    pub mac_address: Cow<'a, [u8; 6]>,
+    pub display_name: Cow<'a, str>,
+    pub wol: WolSettings,
+    pub probe: Option<ProbeSettings>,
+}
+// stc
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind")]
+pub enum NewStandaloneRes {
+    Success,
+    // a machine or unknown host already has the mac address
+    Exists,
+    Invalid { reason: String },
+}
+
+// get how a machine is checked to be up without its agent
+// POsT /api/get/probe
+// cts
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ProbeReq<'a> {
+    pub mac_address: Cow<'a, [u8; 6]>,
+}
+// stc
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind")]
+pub enum ProbeRes {
+    Success { probe: Option<ProbeSettings> },
+    NotFound,
+}
+
+// set how a machine is checked to be up without its agent, none to disable
+// POsT /api/op/probe
+// cts
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EditProbeReq<'a> {
+    pub mac_address: Cow<'a, [u8; 6]>,
+    pub probe: Option<ProbeSettings>,
+}
+// stc
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind")]
+pub enum EditProbeRes {
+    Success,
+    NotFound,
+}
+
+// get the baseboard management controller of a machine, its password is never sent back
+// POsT /api/get/bmc
+// cts
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BmcReq<'a> {
+    pub mac_address: Cow<'a, [u8; 6]>,
+}
+// stc
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind")]
+pub enum BmcRes {
+    Success { settings: Option<BmcSettings> },
+    NotFound,
+}
+
+// set the baseboard management controller of a machine, none to remove it
+// POsT /api/op/bmc
+// cts
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EditBmcReq<'a> {
+    pub mac_address: Cow<'a, [u8; 6]>,
+    pub settings: Option<BmcSettings>,
+}
+// stc
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind")]
+pub enum EditBmcRes {
+    Success,
+    Invalid { reason: String },
+    NotFound,
+}
+
+// get the smart plug feeding a machine, its password is never sent back
+// POsT /api/get/plug
+// cts
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PlugReq<'a> {
+    pub mac_address: Cow<'a, [u8; 6]>,
+}
+// stc
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind")]
+pub enum PlugRes {
+    Success { settings: Option<PlugSettings> },
+    NotFound,
+}
+
+// set the smart plug feeding a machine, none to remove it
+// POsT /api/op/plug
+// cts
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EditPlugReq<'a> {
+    pub mac_address: Cow<'a, [u8; 6]>,
+    pub settings: Option<PlugSettings>,
+}
+// stc
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind")]
+pub enum EditPlugRes {
+    Success,
+    Invalid { reason: String },
+    NotFound,
+}
+
+// get what is done when the agent of a machine doesn't answer
+// POsT /api/get/escalation
+// cts
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EscalationReq<'a> {
+    pub mac_address: Cow<'a, [u8; 6]>,
+}
+// stc
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind")]
+pub enum EscalationRes {
+    Success { escalation: Option<EscalationSettings> },
+    NotFound,
+}
+
+// set what is done when the agent of a machine doesn't answer, none to wait forever
+// POsT /api/op/escalation
+// cts
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EditEscalationReq<'a> {
+    pub mac_address: Cow<'a, [u8; 6]>,
+    pub escalation: Option<EscalationSettings>,
+}
+// stc
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind")]
+pub enum EditEscalationRes {
+    Success,
+    // the machine has no bmc
+    Invalid { reason: String },
+    NotFound,
+}
+
+// get recent out of band actions taken on a machine, oldest first
+// POsT /api/get/audit
+// cts
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AuditReq<'a> {
+    pub mac_address: Cow<'a, [u8; 6]>,
+}
+// stc
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind")]
+pub enum AuditRes {
+    Success { entries: Vec<AuditEntry> },
+    NotFound,
+}
+
+// login
+// POsT /login
+// cts
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct LoginReq<'a> {
+    pub password: Cow<'a, str>,
+}
+// stc
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind")]
+pub enum LoginRes {
+    Success,
+    Fail,
+}
+
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MachineInfoInner<'a> {
+    pub display_name: Option<Cow<'a, str>>,
+    pub mac_address: Cow<'a, [u8; 6]>,
+    pub state: MachineState,
+}
+
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(untagged)]
+pub enum MachineState {
+    Down {
+        #[cfg_attr(feature = "schema", schemars(schema_with = "kind::down"))]
+        kind: MustBe!("Down"),
+    },
+    Uninited {
+        #[cfg_attr(feature = "schema", schemars(schema_with = "kind::uninited"))]
+        kind: MustBe!("Uninited"),
+    },
+    Up {
+        #[cfg_attr(feature = "schema", schemars(schema_with = "kind::up"))]
+        kind: MustBe!("Up"),
+        id: ID,
+    },
+    // without agent, awake is none if no probe is set
+    Standalone {
+        #[cfg_attr(feature = "schema", schemars(schema_with = "kind::standalone"))]
+        kind: MustBe!("Standalone"),
+        awake: Option<bool>,
+    },
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(untagged)]
+pub enum OsStatus {
+    Down {
+        #[cfg_attr(feature = "schema", schemars(schema_with = "kind::down"))]
+        kind: MustBe!("Down"),
+    },
+    Up {
+        #[cfg_attr(feature = "schema", schemars(schema_with = "kind::up"))]
+        kind: MustBe!("Up"),
+        id: ID,
+    },
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind")]
+pub enum BootMethod {
+    WOL,
+    Grub { entry: GrubId },
+    // firmware boot entry set as uefi BootNext
+    Uefi { entry: UefiId },
+    Shutdown,
+    // out of band, by the baseboard management controller
+    Bmc { action: BmcAction },
+    // cut and restore the power by the smart plug
+    PowerCycle,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind")]
+pub enum BmcAction {
+    PowerOn,
+    PowerOff,
+    HardReset,
+    // boot from device once, resetting the machine if it's up
+    OneTimeBoot { device: BootDevice },
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum BootDevice {
+    Pxe,
+    Disk,
+    Cdrom,
+    BiosSetup,
+}
+
+// durations are in milliseconds, none until the edge succeed once
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EdgeInfo {
+    pub from: OsStatus,
+    pub to: OsStatus,
+    pub method: BootMethod,
+    pub samples: u32,
+    pub failures: u32,
+    pub min: Option<u64>,
+    pub median: Option<u64>,
+    pub p95: Option<u64>,
+    // failed repeatedly in recent, avoided when planning
+    pub degraded: bool,
+    // landed in another os than the graph expected
+    pub misroutes: u32,
+    // added by hand
+    pub manual: bool,
+    // never taken when booting
+    pub disabled: bool,
+}
+
+// edges and os added by hand are kept by re-discovery
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "op")]
+pub enum GraphEdit {
+    AddEdge {
+        from: OsStatus,
+        method: BootMethod,
+        to: OsStatus,
+    },
+    RemoveEdge {
+        from: OsStatus,
+        method: BootMethod,
+    },
+    // a disabled edge is never taken when booting
+    DisableEdge {
+        from: OsStatus,
+        method: BootMethod,
+        disabled: bool,
+    },
+    // an os which can't be discovered, reached by method from another os
+    AddNode {
+        display_name: String,
+        from: OsStatus,
+        method: BootMethod,
+    },
+    RemoveNode {
+        id: ID,
+    },
+}
+
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GraphNode {
+    pub os: OsStatus,
+    // name given by label, or the one reported by host
+    pub display_name: String,
+}
+
+// estimate in milliseconds, measured median or a default by boot method
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PlanHop {
+    pub method: BootMethod,
+    pub to: OsStatus,
+    pub estimate: u64,
+}
+
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct OsInfoInner<'a> {
+    // name given by label, or the one reported by host
+    pub display_name: Cow<'a, str>,
+    pub id: ID,
+    pub facts: OsFacts<'a>,
+    pub label: OsLabel<'a>,
+}
+
+// reported by host, empty for what its agent can't tell
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct OsFacts<'a> {
+    pub display_name: Cow<'a, str>,
+    pub kernel_version: Cow<'a, str>,
+    pub distro_id: Cow<'a, str>,
+    pub hostname: Cow<'a, str>,
+}
+
+// edited by user, survive re-enrollment
+#[derive(Deserialize, Serialize, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct OsLabel<'a> {
+    // override the name reported by host
+    pub display_name: Option<Cow<'a, str>>,
+    pub aliases: Vec<Cow<'a, str>>,
+    pub icon: Option<Cow<'a, str>>,
+    pub tags: Vec<Cow<'a, str>>,
+    pub notes: Cow<'a, str>,
+    // at most one os of a machine is default
+    pub default: bool,
+}
+
+// unset fields fall back to 0.0.0.0 sending to 255.255.255.255
+#[derive(Deserialize, Serialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WolSettings {
+    // outgoing interface, its address is bound and its subnet broadcast targeted
+    pub interface: Option<String>,
+    // override the address of interface
+    pub source: Option<Ipv4Addr>,
+    // directed subnet broadcast or unicast, override the broadcast of interface
+    pub target: Option<Ipv4Addr>,
+    // 7 or 9
+    pub port: u16,
+    // SecureOn password of 4 or 6 bytes, never sent back, none keeps the current one and
+    // an empty one clears it
+    pub password: Option<Vec<u8>>,
+    // mac address of an enrolled machine on the same segment, sending magic packets for
+    // this one, which are sent by the server if the relay is offline
+    pub relay: Option<[u8; 6]>,
+}
+
+// tell a machine which woke without agent from one which didn't wake
+#[derive(Deserialize, Serialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ProbeSettings {
+    pub method: ProbeMethod,
+    // seconds to wait for a woken machine, before giving up
+    pub timeout: u64,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind")]
+pub enum ProbeMethod {
+    // up once a service listening on port accept the connection
+    Tcp { address: Ipv4Addr, port: u16 },
+    // echo request, the server need a ping socket or raw socket
+    Icmp { address: Ipv4Addr },
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BmcSettings {
+    pub protocol: BmcProtocol,
+    // base url of redfish like "https://10.0.0.2", "host[:port]" of ipmi
+    pub address: String,
+    pub username: String,
+    // none keeps the current password
+    pub password: Option<String>,
+    // accept the self-signed certificate most controllers ship with
+    pub insecure: bool,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum BmcProtocol {
+    Redfish,
+    // ipmi over lan, by ipmitool installed on the server
+    Ipmi,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PlugSettings {
+    pub kind: PlugKind,
+    // base url of the local http api like "http://10.0.0.3"
+    pub address: String,
+    // outlet of a multi-outlet plug, starting at 0
+    pub channel: u8,
+    // none keeps the current password, if any
+    pub password: Option<String>,
+    // seconds without power before it's restored
+    pub off_time: u64,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum PlugKind {
+    Tasmota,
+    // gen1 http api, also served by gen2 devices
+    Shelly,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EscalationSettings {
+    pub action: EscalationAction,
+    // seconds to wait an answer of shutdown or reboot
+    pub timeout: u64,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum EscalationAction {
+    BmcHardReset,
+    BmcPowerOff,
+    PowerCycle,
+    // reset hook of the agent relaying magic packets for the machine
+    Relay,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum GracefulRequest {
+    Shutdown,
+    Reboot,
+}
+
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AuditEntry {
+    // seconds since unix epoch
+    pub time: u64,
+    pub event: AuditEvent,
+}
+
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind")]
+pub enum AuditEvent {
+    Escalated {
+        request: GracefulRequest,
+        action: EscalationAction,
+    },
+    EscalationFailed {
+        request: GracefulRequest,
+        action: EscalationAction,
+        reason: String,
+    },
+}
+
+// schema of the `kind` tags carried by `MustBe!`, which schemars cannot derive
+#[cfg(feature = "schema")]
+mod kind {
+    use schemars::gen::SchemaGenerator;
+    use schemars::schema::{InstanceType, Schema, SchemaObject};
+
+    macro_rules! impl_kind_schema {
+        ($($f:ident => $k:literal),*) => {
+            $(
+                pub(super) fn $f(_: &mut SchemaGenerator) -> Schema {
+                    Schema::Object(SchemaObject {
+                        instance_type: Some(InstanceType::String.into()),
+                        enum_values: Some(vec![$k.into()]),
+                        ..Default::default()
+                    })
+                }
+            )*
+        };
+    }
+
+    impl_kind_schema! {down => "Down", uninited => "Uninited", up => "Up", standalone => "Standalone"}
+}
@@ -0,0 +1,76 @@
+use std::{net::SocketAddr, time::Duration};
+
+use async_std::future::timeout;
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+
+use crate::constant::SERVICE_TYPE;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("mdns")]
+    Mdns(#[from] mdns_sd::Error),
+    #[error("no server announced itself in time")]
+    NotFound,
+}
+
+/// keep the server announced on every interface until dropped
+pub struct Announcement {
+    daemon: ServiceDaemon,
+    fullname: String,
+}
+
+/// `instance` tells servers apart on the same network, like the hostname
+pub fn announce(instance: &str, port: u16) -> Result<Announcement, Error> {
+    let daemon = ServiceDaemon::new()?;
+    let host_name = format!("{}.local.", instance);
+    // addresses follow the interfaces of the host
+    let info =
+        ServiceInfo::new(SERVICE_TYPE, instance, &host_name, "", port, None)?.enable_addr_auto();
+    let fullname = info.get_fullname().to_owned();
+    daemon.register(info)?;
+    Ok(Announcement { daemon, fullname })
+}
+
+impl Drop for Announcement {
+    fn drop(&mut self) {
+        self.daemon.unregister(&self.fullname).ok();
+        self.daemon.shutdown().ok();
+    }
+}
+
+/// address of the first server resolved within `wait`, ipv4 only as the agent
+pub async fn discover(wait: Duration) -> Result<SocketAddr, Error> {
+    let daemon = ServiceDaemon::new()?;
+    let receiver = daemon.browse(SERVICE_TYPE)?;
+    let found = timeout(wait, async {
+        while let Ok(event) = receiver.recv_async().await {
+            if let ServiceEvent::ServiceResolved(info) = event {
+                if let Some(ip) = info.get_addresses_v4().into_iter().next() {
+                    return Some(SocketAddr::new((*ip).into(), info.get_port()));
+                }
+            }
+        }
+        None
+    })
+    .await;
+    daemon.shutdown().ok();
+    found.ok().flatten().ok_or(Error::NotFound)
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::{announce, discover};
+
+    // mdns-sd skips loopback interfaces, this needs one with multicast
+    #[async_std::test]
+    async fn announce_discover() {
+        let port = 40000 + (std::process::id() % 20000) as u16;
+        let instance = format!("grub-wol-test-{}", std::process::id());
+        let announcement = announce(&instance, port).unwrap();
+        let server = discover(Duration::from_secs(5)).await.unwrap();
+        assert_eq!(server.port(), port);
+        drop(announcement);
+    }
+}
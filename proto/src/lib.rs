@@ -1,14 +1,20 @@
+#[cfg(feature = "transfer")]
 #[macro_use]
 extern crate lazy_static;
 
 pub mod constant;
 mod def;
+#[cfg(feature = "discovery")]
+pub mod discovery;
 pub mod mock;
+#[cfg(feature = "transfer")]
 mod transfer;
 
 pub mod prelude {
     pub use super::constant::*;
     pub use super::def::*;
+    #[cfg(feature = "transfer")]
     pub use super::transfer::Error;
+    #[cfg(feature = "transfer")]
     pub use super::transfer::*;
 }
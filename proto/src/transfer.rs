@@ -2,7 +2,6 @@ use async_std::io::{ReadExt, WriteExt};
 use serde::{Deserialize, Serialize};
 use std::marker::PhantomData;
 use std::vec;
-use thiserror;
 
 // use crate::mock::MockTcpStream;
 
@@ -1,13 +1,15 @@
 use std::borrow::Cow;
 use std::mem;
 use std::sync::Arc;
+use std::time::Duration;
 
-use super::machine::{Error, Machine, Server};
-use super::{api, bootgraph};
+use super::machine::{Error, Machine, OsLabel, Server};
+use super::{api, audit, bootgraph, packet};
 use async_trait::async_trait;
 use log::warn;
 use monostate::MustBe;
-use monostate::MustBeStr::MustBeStr;
+use monostate::MustBeStr;
+use proto::prelude::ID;
 use serde::Serialize;
 
 #[async_trait]
@@ -18,6 +20,273 @@ where
     async fn convert(self) -> Result<Vec<u8>, Error>;
 }
 
+impl From<&bootgraph::OsStatus> for api::OsStatus {
+    fn from(os: &bootgraph::OsStatus) -> Self {
+        match os {
+            bootgraph::OsStatus::Down => api::OsStatus::Down { kind: MustBeStr },
+            bootgraph::OsStatus::Up(id) => api::OsStatus::Up {
+                kind: MustBeStr,
+                id: *id,
+            },
+        }
+    }
+}
+
+impl From<&api::OsStatus> for bootgraph::OsStatus {
+    fn from(os: &api::OsStatus) -> Self {
+        match os {
+            api::OsStatus::Down { kind: _ } => bootgraph::OsStatus::Down,
+            api::OsStatus::Up { kind: _, id } => bootgraph::OsStatus::Up(*id),
+        }
+    }
+}
+
+impl From<&api::BootMethod> for bootgraph::BootMethod {
+    fn from(method: &api::BootMethod) -> Self {
+        match method {
+            api::BootMethod::WOL => bootgraph::BootMethod::Wol,
+            api::BootMethod::Grub { entry } => bootgraph::BootMethod::Grub(*entry),
+            api::BootMethod::Uefi { entry } => bootgraph::BootMethod::Uefi(*entry),
+            api::BootMethod::Shutdown => bootgraph::BootMethod::Shutdown,
+            api::BootMethod::Bmc { action } => bootgraph::BootMethod::Bmc(action.into()),
+            api::BootMethod::PowerCycle => bootgraph::BootMethod::PowerCycle,
+        }
+    }
+}
+
+impl From<&bootgraph::BootMethod> for api::BootMethod {
+    fn from(method: &bootgraph::BootMethod) -> Self {
+        match method {
+            bootgraph::BootMethod::Wol => api::BootMethod::WOL,
+            bootgraph::BootMethod::Grub(entry) => api::BootMethod::Grub { entry: *entry },
+            bootgraph::BootMethod::Uefi(entry) => api::BootMethod::Uefi { entry: *entry },
+            bootgraph::BootMethod::Shutdown => api::BootMethod::Shutdown,
+            bootgraph::BootMethod::Bmc(action) => api::BootMethod::Bmc {
+                action: (*action).into(),
+            },
+            bootgraph::BootMethod::PowerCycle => api::BootMethod::PowerCycle,
+        }
+    }
+}
+
+impl From<&api::BmcAction> for packet::BmcAction {
+    fn from(action: &api::BmcAction) -> Self {
+        match action {
+            api::BmcAction::PowerOn => Self::PowerOn,
+            api::BmcAction::PowerOff => Self::PowerOff,
+            api::BmcAction::HardReset => Self::HardReset,
+            api::BmcAction::OneTimeBoot { device } => Self::OneTimeBoot(match device {
+                api::BootDevice::Pxe => packet::BootDevice::Pxe,
+                api::BootDevice::Disk => packet::BootDevice::Disk,
+                api::BootDevice::Cdrom => packet::BootDevice::Cdrom,
+                api::BootDevice::BiosSetup => packet::BootDevice::BiosSetup,
+            }),
+        }
+    }
+}
+
+impl From<packet::BmcAction> for api::BmcAction {
+    fn from(action: packet::BmcAction) -> Self {
+        match action {
+            packet::BmcAction::PowerOn => Self::PowerOn,
+            packet::BmcAction::PowerOff => Self::PowerOff,
+            packet::BmcAction::HardReset => Self::HardReset,
+            packet::BmcAction::OneTimeBoot(device) => Self::OneTimeBoot {
+                device: match device {
+                    packet::BootDevice::Pxe => api::BootDevice::Pxe,
+                    packet::BootDevice::Disk => api::BootDevice::Disk,
+                    packet::BootDevice::Cdrom => api::BootDevice::Cdrom,
+                    packet::BootDevice::BiosSetup => api::BootDevice::BiosSetup,
+                },
+            },
+        }
+    }
+}
+
+// the password is filled by the caller, as it's never sent back
+impl From<api::BmcSettings> for packet::BmcConfig {
+    fn from(settings: api::BmcSettings) -> Self {
+        Self {
+            protocol: match settings.protocol {
+                api::BmcProtocol::Redfish => packet::BmcProtocol::Redfish,
+                api::BmcProtocol::Ipmi => packet::BmcProtocol::Ipmi,
+            },
+            address: settings.address,
+            username: settings.username,
+            password: settings.password.unwrap_or_default(),
+            insecure: settings.insecure,
+        }
+    }
+}
+
+impl From<&packet::BmcConfig> for api::BmcSettings {
+    fn from(config: &packet::BmcConfig) -> Self {
+        Self {
+            protocol: match config.protocol {
+                packet::BmcProtocol::Redfish => api::BmcProtocol::Redfish,
+                packet::BmcProtocol::Ipmi => api::BmcProtocol::Ipmi,
+            },
+            address: config.address.clone(),
+            username: config.username.clone(),
+            password: None,
+            insecure: config.insecure,
+        }
+    }
+}
+
+impl From<api::PlugSettings> for packet::PlugConfig {
+    fn from(settings: api::PlugSettings) -> Self {
+        Self {
+            kind: match settings.kind {
+                api::PlugKind::Tasmota => packet::PlugKind::Tasmota,
+                api::PlugKind::Shelly => packet::PlugKind::Shelly,
+            },
+            address: settings.address,
+            channel: settings.channel,
+            password: settings.password,
+            off_time: Duration::from_secs(settings.off_time),
+        }
+    }
+}
+
+impl From<&packet::PlugConfig> for api::PlugSettings {
+    fn from(config: &packet::PlugConfig) -> Self {
+        Self {
+            kind: match config.kind {
+                packet::PlugKind::Tasmota => api::PlugKind::Tasmota,
+                packet::PlugKind::Shelly => api::PlugKind::Shelly,
+            },
+            address: config.address.clone(),
+            channel: config.channel,
+            password: None,
+            off_time: config.off_time.as_secs(),
+        }
+    }
+}
+
+impl From<api::WolSettings> for packet::WolConfig {
+    fn from(settings: api::WolSettings) -> Self {
+        Self {
+            interface: settings.interface,
+            source: settings.source,
+            target: settings.target,
+            port: settings.port,
+            // an empty password clears it
+            password: settings.password.filter(|password| !password.is_empty()),
+            relay: settings.relay,
+        }
+    }
+}
+
+// the password is never sent back
+impl From<&packet::WolConfig> for api::WolSettings {
+    fn from(config: &packet::WolConfig) -> Self {
+        Self {
+            interface: config.interface.clone(),
+            source: config.source,
+            target: config.target,
+            port: config.port,
+            password: None,
+            relay: config.relay,
+        }
+    }
+}
+
+impl From<api::ProbeSettings> for packet::ProbeConfig {
+    fn from(settings: api::ProbeSettings) -> Self {
+        let probe = match settings.method {
+            api::ProbeMethod::Tcp { address, port } => packet::Probe::Tcp { address, port },
+            api::ProbeMethod::Icmp { address } => packet::Probe::Icmp { address },
+        };
+        Self {
+            probe,
+            timeout: Duration::from_secs(settings.timeout),
+        }
+    }
+}
+
+impl From<&packet::ProbeConfig> for api::ProbeSettings {
+    fn from(config: &packet::ProbeConfig) -> Self {
+        let method = match config.probe {
+            packet::Probe::Tcp { address, port } => api::ProbeMethod::Tcp { address, port },
+            packet::Probe::Icmp { address } => api::ProbeMethod::Icmp { address },
+        };
+        Self {
+            method,
+            timeout: config.timeout.as_secs(),
+        }
+    }
+}
+
+impl From<api::EscalationAction> for packet::EscalationAction {
+    fn from(action: api::EscalationAction) -> Self {
+        match action {
+            api::EscalationAction::BmcHardReset => Self::BmcHardReset,
+            api::EscalationAction::BmcPowerOff => Self::BmcPowerOff,
+            api::EscalationAction::PowerCycle => Self::PowerCycle,
+            api::EscalationAction::Relay => Self::Relay,
+        }
+    }
+}
+
+impl From<packet::EscalationAction> for api::EscalationAction {
+    fn from(action: packet::EscalationAction) -> Self {
+        match action {
+            packet::EscalationAction::BmcHardReset => Self::BmcHardReset,
+            packet::EscalationAction::BmcPowerOff => Self::BmcPowerOff,
+            packet::EscalationAction::PowerCycle => Self::PowerCycle,
+            packet::EscalationAction::Relay => Self::Relay,
+        }
+    }
+}
+
+impl From<api::EscalationSettings> for packet::Escalation {
+    fn from(settings: api::EscalationSettings) -> Self {
+        Self {
+            action: settings.action.into(),
+            timeout: Duration::from_secs(settings.timeout),
+        }
+    }
+}
+
+impl From<&packet::Escalation> for api::EscalationSettings {
+    fn from(escalation: &packet::Escalation) -> Self {
+        Self {
+            action: escalation.action.into(),
+            timeout: escalation.timeout.as_secs(),
+        }
+    }
+}
+
+impl From<&audit::AuditEntry> for api::AuditEntry {
+    fn from(entry: &audit::AuditEntry) -> Self {
+        let request = |request: packet::Graceful| match request {
+            packet::Graceful::Shutdown => api::GracefulRequest::Shutdown,
+            packet::Graceful::Reboot => api::GracefulRequest::Reboot,
+        };
+        Self {
+            time: entry.time,
+            event: match &entry.event {
+                audit::AuditEvent::Escalated { request: req, action } => {
+                    api::AuditEvent::Escalated {
+                        request: request(*req),
+                        action: (*action).into(),
+                    }
+                }
+                audit::AuditEvent::EscalationFailed {
+                    request: req,
+                    action,
+                    reason,
+                } => api::AuditEvent::EscalationFailed {
+                    request: request(*req),
+                    action: (*action).into(),
+                    reason: reason.clone(),
+                },
+            },
+        }
+    }
+}
+
 pub struct OsListAdaptor {
     pub(super) machine: Option<Arc<Machine>>,
 }
@@ -27,12 +296,33 @@ impl<'a> Convert<api::OsList<'a>> for OsListAdaptor {
     async fn convert(self) -> Result<Vec<u8>, Error> {
         match self.machine {
             Some(machine) => {
-                let oss = machine
-                    .boot_graph
+                let labels = machine.labels.lock().await;
+                let unlabeled = OsLabel::default();
+                let boot_graph = machine.boot_graph.read().await;
+                let oss = boot_graph
                     .list_os()
-                    .map(|(&id,info)| api::OsInfoInner {
-                        display_name: Cow::Borrowed(&info.display_name),
-                        id,
+                    .map(|(&id, info)| {
+                        let label = labels.get(&id).unwrap_or(&unlabeled);
+                        api::OsInfoInner {
+                            display_name: Cow::Borrowed(
+                                label.display_name.as_ref().unwrap_or(&info.display_name),
+                            ),
+                            id,
+                            facts: api::OsFacts {
+                                display_name: Cow::Borrowed(&info.display_name),
+                                kernel_version: Cow::Borrowed(&info.kernel_version),
+                                distro_id: Cow::Borrowed(&info.distro_id),
+                                hostname: Cow::Borrowed(&info.hostname),
+                            },
+                            label: api::OsLabel {
+                                display_name: label.display_name.as_deref().map(Cow::Borrowed),
+                                aliases: label.aliases.iter().map(|x| Cow::Borrowed(x.as_str())).collect(),
+                                icon: label.icon.as_deref().map(Cow::Borrowed),
+                                tags: label.tags.iter().map(|x| Cow::Borrowed(x.as_str())).collect(),
+                                notes: Cow::Borrowed(&label.notes),
+                                default: label.default,
+                            },
+                        }
                     })
                     .collect();
                 Ok(serde_json::to_vec(&api::OsList { oss }).unwrap())
@@ -42,6 +332,23 @@ impl<'a> Convert<api::OsList<'a>> for OsListAdaptor {
     }
 }
 
+/// a standalone machine is probed, others ask the connected agent
+async fn machine_state(machine: &Machine) -> Result<api::MachineState, Error> {
+    if machine.standalone {
+        return Ok(api::MachineState::Standalone {
+            kind: MustBeStr,
+            awake: machine.awake().await,
+        });
+    }
+    Ok(match machine.current_os().await? {
+        Some(os) => api::MachineState::Up {
+            kind: MustBe!("Up"),
+            id: os,
+        },
+        None => api::MachineState::Down { kind: MustBeStr },
+    })
+}
+
 pub struct MachineInfoAdaptor {
     pub(super) machine: Option<Arc<Machine>>,
 }
@@ -51,21 +358,12 @@ impl<'a> Convert<api::MachineInfo<'a>> for MachineInfoAdaptor {
     async fn convert(self) -> Result<Vec<u8>, Error> {
         match self.machine {
             Some(machine) => {
-                let current_os = match machine.current_os().await? {
-                    Some(os) => Some(os),
-                    None => None,
-                };
+                let state = machine_state(&machine).await?;
                 let display_name = &*machine.display_name.lock().await.to_owned();
                 Ok(serde_json::to_vec(&Some(api::MachineInfoInner {
-                    display_name: Some(Cow::Borrowed(&display_name)),
+                    display_name: Some(Cow::Borrowed(display_name)),
                     mac_address: Cow::Borrowed(&machine.mac_address),
-                    state: match current_os {
-                        Some(os) => api::MachineState::Up {
-                            kind: MustBe!("Up"),
-                            id: os,
-                        },
-                        None => api::MachineState::Down { kind: MustBeStr },
-                    },
+                    state,
                 }))
                 .unwrap())
             }
@@ -84,19 +382,27 @@ impl<'a> Convert<api::MachineList<'a>> for MachineListAdaptor<'a> {
         let mut machines = Vec::new();
         let server = self.server;
 
-        let machines_src = server.machines.lock().await;
-        for (mac_address, machine) in machines_src.iter() {
-            let current_os = machine.current_os().await?.map(|os| os);
+        let machines_src: Vec<([u8; 6], Arc<Machine>)> = server
+            .machines
+            .lock()
+            .await
+            .iter()
+            .map(|(mac_address, machine)| (*mac_address, machine.clone()))
+            .collect();
+        // a standalone machine may take a probe timeout to answer, they're all asked at once
+        let states: Vec<_> = machines_src
+            .iter()
+            .map(|(_, machine)| {
+                let machine = machine.clone();
+                async_std::task::spawn(async move { machine_state(&machine).await })
+            })
+            .collect();
+        for ((mac_address, machine), state) in machines_src.iter().zip(states) {
+            let state = state.await?;
             let display_name = machine.display_name.lock().await.to_owned();
             machines.push(api::MachineInfoInner {
                 display_name: Some(Cow::Owned(display_name)),
-                state: match current_os {
-                    Some(os) => api::MachineState::Up {
-                        kind: MustBe!("Up"),
-                        id: os,
-                    },
-                    None => api::MachineState::Down { kind: MustBeStr },
-                },
+                state,
                 mac_address: Cow::Borrowed(mac_address),
             });
         }
@@ -104,7 +410,7 @@ impl<'a> Convert<api::MachineList<'a>> for MachineListAdaptor<'a> {
         let unknown_src = server.unknown_packet.lock().await;
         let unknown_mac: Vec<[u8; 6]> = unknown_src
             .iter()
-            .map(|p| p.get_mac_address().clone())
+            .map(|p| *p.get_mac_address())
             .collect();
         unknown_mac.iter().for_each(|mac_address| {
             machines.push(api::MachineInfoInner {
@@ -118,23 +424,39 @@ impl<'a> Convert<api::MachineList<'a>> for MachineListAdaptor<'a> {
     }
 }
 
-pub struct BootAdaptor {
+pub struct BootAdaptor<'a> {
     pub(super) os: api::OsStatus,
     pub(super) machine: Option<Arc<Machine>>,
+    pub(super) relay: Option<packet::TcpRelay>,
+    pub(super) server: &'a Server,
 }
 
 #[async_trait]
-impl Convert<api::BootRes> for BootAdaptor {
+impl<'a> Convert<api::BootRes> for BootAdaptor<'a> {
     async fn convert(self) -> Result<Vec<u8>, Error> {
-        let os = match self.os {
-            api::OsStatus::Down { kind: _ } => bootgraph::OsStatus::Down,
-            api::OsStatus::Up { kind: _, id } => bootgraph::OsStatus::Up(id),
-        };
+        let os = bootgraph::OsStatus::from(&self.os);
 
         if self.machine.is_none() {
             return Ok(serde_json::to_vec(&api::BootRes::NotFound).unwrap());
         }
         let machine = self.machine.unwrap();
+        if machine.standalone {
+            // only woken, it has no agent to shutdown or to reboot
+            let res = match os {
+                bootgraph::OsStatus::Up(id) if machine.boot_graph.read().await.find_os(id).is_some() => {
+                    match machine.wake(self.relay).await {
+                        Ok(true) => api::BootRes::Success,
+                        Ok(false) => api::BootRes::NotWoken,
+                        Err(err) => {
+                            warn!("{}", err);
+                            api::BootRes::Fail
+                        }
+                    }
+                }
+                _ => api::BootRes::Fail,
+            };
+            return Ok(serde_json::to_vec(&res).unwrap());
+        }
         let mut packet = machine.packet.lock().await;
         let mut out_packet = None;
         mem::swap(&mut out_packet, &mut *packet);
@@ -142,13 +464,30 @@ impl Convert<api::BootRes> for BootAdaptor {
 
         match out_packet {
             Some(mut packet) => {
-                let raw = match machine.boot_graph.boot(os, &mut packet).await {
+                packet.set_relay(self.relay);
+                let res = bootgraph::BootGraph::boot(&machine.boot_graph, os, &mut packet).await;
+                machine.boot_graph.write().await.heal();
+                // keep timings and re-learned edges across a crash
+                if let Err(err) = self.server.save().await {
+                    warn!("{}", err);
+                }
+                let raw = match res {
                     Ok(_) => api::BootRes::Success,
+                    Err(bootgraph::Error::Packet(packet::Error::AgentMissing)) => {
+                        api::BootRes::AgentMissing
+                    }
+                    Err(bootgraph::Error::Packet(packet::Error::NotWoken)) => {
+                        api::BootRes::NotWoken
+                    }
                     Err(e) => {
                         warn!("{}", e);
                         api::BootRes::Fail
                     }
                 };
+                // hand the connection back, otherwise the machine look down until host reconnect
+                if packet.get_uid().await.is_ok() {
+                    machine.connect(packet).await;
+                }
                 Ok(serde_json::to_vec(&raw).unwrap())
             }
             None => Ok(serde_json::to_vec(&api::BootRes::NotFound).unwrap()),
@@ -187,3 +526,471 @@ impl<'a> Convert<api::NewMachineRes> for NewMachineAdaptor<'a> {
     }
 }
 // new machine
+
+pub struct RenameMachineAdaptor<'a> {
+    pub(super) display_name: String,
+    pub(super) mac_address: [u8; 6],
+    pub(super) server: &'a Server,
+}
+
+#[async_trait]
+impl<'a> Convert<api::RenameMachineRes> for RenameMachineAdaptor<'a> {
+    async fn convert(self) -> Result<Vec<u8>, Error> {
+        let res = if self
+            .server
+            .rename_machine(&self.mac_address, self.display_name)
+            .await?
+        {
+            api::RenameMachineRes::Success
+        } else {
+            api::RenameMachineRes::NotFound
+        };
+        Ok(serde_json::to_vec(&res).unwrap())
+    }
+}
+
+pub struct ForgetMachineAdaptor<'a> {
+    pub(super) mac_address: [u8; 6],
+    pub(super) server: &'a Server,
+}
+
+#[async_trait]
+impl<'a> Convert<api::ForgetMachineRes> for ForgetMachineAdaptor<'a> {
+    async fn convert(self) -> Result<Vec<u8>, Error> {
+        let res = if self.server.forget_machine(&self.mac_address).await? {
+            api::ForgetMachineRes::Success
+        } else {
+            api::ForgetMachineRes::NotFound
+        };
+        Ok(serde_json::to_vec(&res).unwrap())
+    }
+}
+
+pub struct RenewMachineAdaptor<'a> {
+    pub(super) mac_address: [u8; 6],
+    pub(super) server: &'a Server,
+}
+
+#[async_trait]
+impl<'a> Convert<api::RenewMachineRes> for RenewMachineAdaptor<'a> {
+    async fn convert(self) -> Result<Vec<u8>, Error> {
+        Ok(serde_json::to_vec(&match self.server.renew_machine(&self.mac_address).await {
+            Ok(true) => api::RenewMachineRes::Success,
+            Ok(false) => api::RenewMachineRes::NotFound,
+            Err(err) => {
+                log::warn!("{:?}", err);
+                api::RenewMachineRes::Fail
+            }
+        })
+        .unwrap())
+    }
+}
+
+pub struct EditOsAdaptor<'a> {
+    pub(super) mac_address: [u8; 6],
+    pub(super) os: ID,
+    pub(super) label: OsLabel,
+    pub(super) server: &'a Server,
+}
+
+#[async_trait]
+impl<'a> Convert<api::EditOsRes> for EditOsAdaptor<'a> {
+    async fn convert(self) -> Result<Vec<u8>, Error> {
+        let res = match self
+            .server
+            .edit_os(&self.mac_address, self.os, self.label)
+            .await?
+        {
+            Some(Ok(())) => api::EditOsRes::Success,
+            Some(Err(err)) => api::EditOsRes::Invalid {
+                reason: err.to_string(),
+            },
+            None => api::EditOsRes::NotFound,
+        };
+        Ok(serde_json::to_vec(&res).unwrap())
+    }
+}
+
+fn edge_info(
+    boot_graph: &bootgraph::BootGraph,
+    from: &bootgraph::OsStatus,
+    method: &bootgraph::BootMethod,
+    to: &bootgraph::OsStatus,
+) -> api::EdgeInfo {
+    let measure = boot_graph.measure(from, method).unwrap_or_default();
+    let millis = |x: Option<Duration>| x.map(|x| x.as_millis() as u64);
+    api::EdgeInfo {
+        from: from.into(),
+        to: to.into(),
+        method: method.into(),
+        samples: measure.samples() as u32,
+        failures: measure.failures(),
+        min: millis(measure.min()),
+        median: millis(measure.median()),
+        p95: millis(measure.p95()),
+        degraded: boot_graph.is_degraded(from, method),
+        misroutes: measure.misroutes(),
+        manual: boot_graph.is_manual(from, method),
+        disabled: boot_graph.is_disabled(from, method),
+    }
+}
+
+pub struct EdgeListAdaptor {
+    pub(super) machine: Option<Arc<Machine>>,
+}
+
+#[async_trait]
+impl Convert<api::EdgeList> for EdgeListAdaptor {
+    async fn convert(self) -> Result<Vec<u8>, Error> {
+        let edges = match self.machine {
+            Some(machine) => {
+                let boot_graph = machine.boot_graph.read().await;
+                boot_graph
+                    .list_edge()
+                    .map(|(from, method, to)| edge_info(&boot_graph, from, method, to))
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+        Ok(serde_json::to_vec(&api::EdgeList { edges }).unwrap())
+    }
+}
+
+pub struct EditGraphAdaptor<'a> {
+    pub(super) mac_address: [u8; 6],
+    pub(super) edit: api::GraphEdit,
+    pub(super) server: &'a Server,
+}
+
+#[async_trait]
+impl<'a> Convert<api::EditGraphRes> for EditGraphAdaptor<'a> {
+    async fn convert(self) -> Result<Vec<u8>, Error> {
+        let res = match self.server.edit_graph(&self.mac_address, self.edit).await? {
+            Some(Ok(Some(id))) => api::EditGraphRes::Added { id },
+            Some(Ok(None)) => api::EditGraphRes::Success,
+            Some(Err(err)) => api::EditGraphRes::Invalid {
+                reason: err.to_string(),
+            },
+            None => api::EditGraphRes::NotFound,
+        };
+        Ok(serde_json::to_vec(&res).unwrap())
+    }
+}
+
+pub struct GraphAdaptor {
+    pub(super) machine: Option<Arc<Machine>>,
+}
+
+#[async_trait]
+impl Convert<api::GraphExport> for GraphAdaptor {
+    async fn convert(self) -> Result<Vec<u8>, Error> {
+        let res = match self.machine {
+            Some(machine) => {
+                let labels = machine.labels.lock().await;
+                let boot_graph = machine.boot_graph.read().await;
+                let name = |os: &bootgraph::OsStatus| match os {
+                    bootgraph::OsStatus::Down => "Down".to_owned(),
+                    bootgraph::OsStatus::Up(id) => labels
+                        .get(id)
+                        .and_then(|label| label.display_name.clone())
+                        .or_else(|| boot_graph.find_os(*id).map(|info| info.display_name.clone()))
+                        .unwrap_or_else(|| id.to_string()),
+                };
+                api::GraphExport {
+                    nodes: boot_graph
+                        .list_node()
+                        .map(|os| api::GraphNode {
+                            os: os.into(),
+                            display_name: name(os),
+                        })
+                        .collect(),
+                    edges: boot_graph
+                        .list_edge()
+                        .map(|(from, method, to)| edge_info(&boot_graph, from, method, to))
+                        .collect(),
+                    dot: boot_graph.to_dot(name),
+                }
+            }
+            None => api::GraphExport {
+                nodes: Vec::new(),
+                edges: Vec::new(),
+                dot: bootgraph::BootGraph::default().to_dot(|_| String::new()),
+            },
+        };
+        Ok(serde_json::to_vec(&res).unwrap())
+    }
+}
+
+pub struct PlanAdaptor {
+    pub(super) os: api::OsStatus,
+    pub(super) machine: Option<Arc<Machine>>,
+}
+
+#[async_trait]
+impl Convert<api::PlanRes> for PlanAdaptor {
+    async fn convert(self) -> Result<Vec<u8>, Error> {
+        let machine = match self.machine {
+            Some(machine) => machine,
+            None => return Ok(serde_json::to_vec(&api::PlanRes::NotFound).unwrap()),
+        };
+        let from = match machine.current_os().await? {
+            Some(id) => bootgraph::OsStatus::Up(id),
+            None => bootgraph::OsStatus::Down,
+        };
+        let res = match machine.boot_graph.read().await.plan(&from, &(&self.os).into()) {
+            Ok(hops) => api::PlanRes::Success {
+                from: (&from).into(),
+                estimate: hops.iter().map(|hop| hop.estimate.as_millis() as u64).sum(),
+                hops: hops
+                    .iter()
+                    .map(|hop| api::PlanHop {
+                        method: (&hop.method).into(),
+                        to: (&hop.to).into(),
+                        estimate: hop.estimate.as_millis() as u64,
+                    })
+                    .collect(),
+            },
+            Err(bootgraph::Error::BadGraph) => api::PlanRes::BadGraph,
+            Err(err) => return Err(err.into()),
+        };
+        Ok(serde_json::to_vec(&res).unwrap())
+    }
+}
+
+pub struct WolAdaptor {
+    pub(super) machine: Option<Arc<Machine>>,
+}
+
+#[async_trait]
+impl Convert<api::WolRes> for WolAdaptor {
+    async fn convert(self) -> Result<Vec<u8>, Error> {
+        let res = match self.machine {
+            Some(machine) => api::WolRes::Success {
+                settings: (&*machine.wol.lock().await).into(),
+            },
+            None => api::WolRes::NotFound,
+        };
+        Ok(serde_json::to_vec(&res).unwrap())
+    }
+}
+
+pub struct EditWolAdaptor<'a> {
+    pub(super) mac_address: [u8; 6],
+    pub(super) settings: api::WolSettings,
+    pub(super) server: &'a Server,
+}
+
+#[async_trait]
+impl<'a> Convert<api::EditWolRes> for EditWolAdaptor<'a> {
+    async fn convert(self) -> Result<Vec<u8>, Error> {
+        let res = match self.server.edit_wol(&self.mac_address, self.settings).await? {
+            Some(Ok(())) => api::EditWolRes::Success,
+            Some(Err(err)) => api::EditWolRes::Invalid {
+                reason: err.to_string(),
+            },
+            None => api::EditWolRes::NotFound,
+        };
+        Ok(serde_json::to_vec(&res).unwrap())
+    }
+}
+
+pub struct BmcAdaptor {
+    pub(super) machine: Option<Arc<Machine>>,
+}
+
+#[async_trait]
+impl Convert<api::BmcRes> for BmcAdaptor {
+    async fn convert(self) -> Result<Vec<u8>, Error> {
+        let res = match self.machine {
+            Some(machine) => api::BmcRes::Success {
+                settings: machine.bmc.lock().await.as_ref().map(Into::into),
+            },
+            None => api::BmcRes::NotFound,
+        };
+        Ok(serde_json::to_vec(&res).unwrap())
+    }
+}
+
+pub struct EditBmcAdaptor<'a> {
+    pub(super) mac_address: [u8; 6],
+    pub(super) settings: Option<api::BmcSettings>,
+    pub(super) server: &'a Server,
+}
+
+#[async_trait]
+impl<'a> Convert<api::EditBmcRes> for EditBmcAdaptor<'a> {
+    async fn convert(self) -> Result<Vec<u8>, Error> {
+        let res = match self.server.edit_bmc(&self.mac_address, self.settings).await? {
+            Some(Ok(())) => api::EditBmcRes::Success,
+            Some(Err(err)) => api::EditBmcRes::Invalid {
+                reason: err.to_string(),
+            },
+            None => api::EditBmcRes::NotFound,
+        };
+        Ok(serde_json::to_vec(&res).unwrap())
+    }
+}
+
+pub struct PlugAdaptor {
+    pub(super) machine: Option<Arc<Machine>>,
+}
+
+#[async_trait]
+impl Convert<api::PlugRes> for PlugAdaptor {
+    async fn convert(self) -> Result<Vec<u8>, Error> {
+        let res = match self.machine {
+            Some(machine) => api::PlugRes::Success {
+                settings: machine.plug.lock().await.as_ref().map(Into::into),
+            },
+            None => api::PlugRes::NotFound,
+        };
+        Ok(serde_json::to_vec(&res).unwrap())
+    }
+}
+
+pub struct EditPlugAdaptor<'a> {
+    pub(super) mac_address: [u8; 6],
+    pub(super) settings: Option<api::PlugSettings>,
+    pub(super) server: &'a Server,
+}
+
+#[async_trait]
+impl<'a> Convert<api::EditPlugRes> for EditPlugAdaptor<'a> {
+    async fn convert(self) -> Result<Vec<u8>, Error> {
+        let res = match self.server.edit_plug(&self.mac_address, self.settings).await? {
+            Some(Ok(())) => api::EditPlugRes::Success,
+            Some(Err(err)) => api::EditPlugRes::Invalid {
+                reason: err.to_string(),
+            },
+            None => api::EditPlugRes::NotFound,
+        };
+        Ok(serde_json::to_vec(&res).unwrap())
+    }
+}
+
+pub struct EscalationAdaptor {
+    pub(super) machine: Option<Arc<Machine>>,
+}
+
+#[async_trait]
+impl Convert<api::EscalationRes> for EscalationAdaptor {
+    async fn convert(self) -> Result<Vec<u8>, Error> {
+        let res = match self.machine {
+            Some(machine) => api::EscalationRes::Success {
+                escalation: machine.escalation.lock().await.as_ref().map(Into::into),
+            },
+            None => api::EscalationRes::NotFound,
+        };
+        Ok(serde_json::to_vec(&res).unwrap())
+    }
+}
+
+pub struct EditEscalationAdaptor<'a> {
+    pub(super) mac_address: [u8; 6],
+    pub(super) escalation: Option<api::EscalationSettings>,
+    pub(super) server: &'a Server,
+}
+
+#[async_trait]
+impl<'a> Convert<api::EditEscalationRes> for EditEscalationAdaptor<'a> {
+    async fn convert(self) -> Result<Vec<u8>, Error> {
+        let escalation = self.escalation.map(Into::into);
+        let res = match self.server.edit_escalation(&self.mac_address, escalation).await? {
+            Some(Ok(())) => api::EditEscalationRes::Success,
+            Some(Err(err)) => api::EditEscalationRes::Invalid {
+                reason: err.to_string(),
+            },
+            None => api::EditEscalationRes::NotFound,
+        };
+        Ok(serde_json::to_vec(&res).unwrap())
+    }
+}
+
+pub struct AuditAdaptor {
+    pub(super) machine: Option<Arc<Machine>>,
+}
+
+#[async_trait]
+impl Convert<api::AuditRes> for AuditAdaptor {
+    async fn convert(self) -> Result<Vec<u8>, Error> {
+        let res = match self.machine {
+            Some(machine) => api::AuditRes::Success {
+                entries: machine.audit.entries().iter().map(Into::into).collect(),
+            },
+            None => api::AuditRes::NotFound,
+        };
+        Ok(serde_json::to_vec(&res).unwrap())
+    }
+}
+
+pub struct NewStandaloneAdaptor<'a> {
+    pub(super) mac_address: [u8; 6],
+    pub(super) display_name: String,
+    pub(super) wol: api::WolSettings,
+    pub(super) probe: Option<api::ProbeSettings>,
+    pub(super) server: &'a Server,
+}
+
+#[async_trait]
+impl<'a> Convert<api::NewStandaloneRes> for NewStandaloneAdaptor<'a> {
+    async fn convert(self) -> Result<Vec<u8>, Error> {
+        let wol: packet::WolConfig = self.wol.into();
+        let res = match wol.validate() {
+            Err(err) => api::NewStandaloneRes::Invalid {
+                reason: err.to_string(),
+            },
+            Ok(()) => match self
+                .server
+                .new_standalone(
+                    self.mac_address,
+                    self.display_name,
+                    wol,
+                    self.probe.map(Into::into),
+                )
+                .await?
+            {
+                true => api::NewStandaloneRes::Success,
+                false => api::NewStandaloneRes::Exists,
+            },
+        };
+        Ok(serde_json::to_vec(&res).unwrap())
+    }
+}
+
+pub struct ProbeAdaptor {
+    pub(super) machine: Option<Arc<Machine>>,
+}
+
+#[async_trait]
+impl Convert<api::ProbeRes> for ProbeAdaptor {
+    async fn convert(self) -> Result<Vec<u8>, Error> {
+        let res = match self.machine {
+            Some(machine) => api::ProbeRes::Success {
+                probe: machine.probe.lock().await.as_ref().map(Into::into),
+            },
+            None => api::ProbeRes::NotFound,
+        };
+        Ok(serde_json::to_vec(&res).unwrap())
+    }
+}
+
+pub struct EditProbeAdaptor<'a> {
+    pub(super) mac_address: [u8; 6],
+    pub(super) probe: Option<api::ProbeSettings>,
+    pub(super) server: &'a Server,
+}
+
+#[async_trait]
+impl<'a> Convert<api::EditProbeRes> for EditProbeAdaptor<'a> {
+    async fn convert(self) -> Result<Vec<u8>, Error> {
+        let res = match self
+            .server
+            .edit_probe(&self.mac_address, self.probe.map(Into::into))
+            .await?
+        {
+            true => api::EditProbeRes::Success,
+            false => api::EditProbeRes::NotFound,
+        };
+        Ok(serde_json::to_vec(&res).unwrap())
+    }
+}
@@ -0,0 +1,59 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::packet::{EscalationAction, Graceful};
+
+// entries kept per machine, the oldest are dropped
+const AUDIT_SIZE: usize = 64;
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct AuditEntry {
+    // seconds since unix epoch
+    pub time: u64,
+    pub event: AuditEvent,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum AuditEvent {
+    // the agent didn't answer a graceful request, an out of band action took over
+    Escalated {
+        request: Graceful,
+        action: EscalationAction,
+    },
+    EscalationFailed {
+        request: Graceful,
+        action: EscalationAction,
+        reason: String,
+    },
+}
+
+/// actions taken on a machine behind its agent, shared by the machine and its connection
+#[derive(Clone, Default)]
+pub struct Audit(Arc<Mutex<VecDeque<AuditEntry>>>);
+
+impl Audit {
+    pub fn new(entries: Vec<AuditEntry>) -> Self {
+        Self(Arc::new(Mutex::new(entries.into())))
+    }
+    pub fn record(&self, event: AuditEvent) {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|x| x.as_secs())
+            .unwrap_or_default();
+        log::info!("audit: {:?}", event);
+        let mut entries = self.0.lock().unwrap();
+        if entries.len() == AUDIT_SIZE {
+            entries.pop_front();
+        }
+        entries.push_back(AuditEntry { time, event });
+    }
+    /// oldest first
+    pub fn entries(&self) -> Vec<AuditEntry> {
+        self.0.lock().unwrap().iter().cloned().collect()
+    }
+}
@@ -1,24 +1,42 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
 
+use async_std::sync::RwLock;
 use indexmap::IndexMap;
-use proto::prelude::{GrubId, ID};
+use proto::prelude::{GrubId, UefiId, ID};
 use serde::{Deserialize, Serialize};
 
-use crate::grub::packet::{self, TcpPacket};
+use crate::grub::packet::{self, Graceful, TcpPacket};
 
-use super::graph::{Graph, Node};
+use super::graph::{Dijkstra, Graph, Node};
+use super::stats::{EdgeStats, Measure};
+
+// give up a boot after these many failed hops
+const MAX_RETRY: u32 = 3;
+// doubled after every failure
+const RETRY_BACKOFF: Duration = Duration::from_secs(2);
+// bound a boot that keep landing somewhere else
+const MAX_HOP: usize = 16;
 
 #[derive(Hash, Eq, PartialEq, Clone, Deserialize, Serialize, Debug)]
 pub enum BootMethod {
-    WOL,
+    Wol,
     Grub(GrubId),
+    // firmware boot entry, booted once by BootNext
+    Uefi(UefiId),
     Shutdown,
+    Bmc(packet::BmcAction),
+    PowerCycle,
 }
 
 impl BootMethod {
     pub async fn execute(&self, packet: &mut TcpPacket) -> Result<(), packet::Error> {
         match self {
-            BootMethod::WOL => {
+            BootMethod::Wol => {
                 log::trace!("waiting host {:x?} to boot", packet.get_mac_address());
                 packet.wol_reconnect().await?;
             }
@@ -28,17 +46,53 @@ impl BootMethod {
                     packet.get_mac_address()
                 );
                 packet.write_reboot(*x).await?;
-                packet.read_reboot().await?;
+                packet
+                    .graceful(Graceful::Reboot, packet.read_reboot())
+                    .await?;
+                packet.wait_reconnect().await?;
+            }
+            BootMethod::Uefi(x) => {
+                log::trace!(
+                    "executing host {:x?} to boot firmware entry {:04X}",
+                    packet.get_mac_address(),
+                    x
+                );
+                packet.write_boot_next(*x).await?;
+                let answer = async { packet.read_boot_next().await?.map_err(packet::Error::BootNext) };
+                packet.graceful(Graceful::Reboot, answer).await?;
                 packet.wait_reconnect().await?;
             }
             BootMethod::Shutdown => {
                 log::trace!("shuting down host {:x?}", packet.get_mac_address());
                 packet.write_shutdown().await?;
-                packet.read_shutdown().await?;
+                packet
+                    .graceful(Graceful::Shutdown, packet.read_shutdown())
+                    .await?;
+                packet.disconnect().await;
+            }
+            BootMethod::Bmc(action) => {
+                log::trace!("{:?} host {:x?} by bmc", action, packet.get_mac_address());
+                packet.bmc_reconnect(*action).await?;
+            }
+            BootMethod::PowerCycle => {
+                log::trace!("power cycling host {:x?} by plug", packet.get_mac_address());
+                packet.plug_reconnect().await?;
             }
         };
         Ok(())
     }
+    /// rough duration before any boot is measured
+    pub fn default_duration(&self) -> Duration {
+        match self {
+            BootMethod::Wol => Duration::from_secs(60),
+            BootMethod::Grub(_) => Duration::from_secs(60),
+            BootMethod::Uefi(_) => Duration::from_secs(60),
+            BootMethod::Shutdown => Duration::from_secs(15),
+            BootMethod::Bmc(packet::BmcAction::PowerOff) => Duration::from_secs(15),
+            BootMethod::Bmc(_) => Duration::from_secs(60),
+            BootMethod::PowerCycle => Duration::from_secs(70),
+        }
+    }
 }
 
 #[derive(Hash, Eq, PartialEq, Clone, Deserialize, Serialize, Debug)]
@@ -47,11 +101,62 @@ pub enum OsStatus {
     Up(ID),
 }
 
+/// a step of a planned boot
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hop {
+    pub method: BootMethod,
+    pub to: OsStatus,
+    pub estimate: Duration,
+}
+
+/// progress of an enrollment, enough to resume it
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Checkpoint {
+    graph: BootGraph,
+    unknowns: HashMap<ID, Vec<BootMethod>>,
+    issued: HashSet<ID>,
+}
+
+impl Checkpoint {
+    /// none if there is no checkpoint, or it can't be read
+    pub async fn load(path: &Path) -> Option<Self> {
+        let buf = async_std::fs::read(path).await.ok()?;
+        match bincode::deserialize(&buf) {
+            Ok(checkpoint) => Some(checkpoint),
+            Err(err) => {
+                log::warn!("discarding broken checkpoint {}: {}", path.display(), err);
+                None
+            }
+        }
+    }
+    async fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            async_std::fs::create_dir_all(dir).await?;
+        }
+        // a crash while writing must not corrupt the last checkpoint
+        let tmp = path.with_extension("tmp");
+        async_std::fs::write(&tmp, bincode::serialize(self).unwrap()).await?;
+        async_std::fs::rename(&tmp, path).await
+    }
+}
+
+/// exploration stopped by an error, with the connection it was using
+///
+/// returned boxed, the connection is large
+pub struct Interrupted {
+    pub error: Error,
+    pub packet: TcpPacket,
+}
+
 struct Helper {
     packet: TcpPacket,
     unknowns: HashMap<ID, Vec<BootMethod>>,
     offline: Node,
     graph: BootGraph,
+    // os id issued by previous exploration, kept by host
+    issued: HashSet<ID>,
+    // where progress is saved, if resumable
+    checkpoint: Option<PathBuf>,
 }
 
 impl Helper {
@@ -64,15 +169,69 @@ impl Helper {
             unknowns: HashMap::new(),
             offline,
             graph,
+            issued: HashSet::new(),
+            checkpoint: None,
+        }
+    }
+    fn renew(packet: TcpPacket, origin: &BootGraph) -> Self {
+        let mut helper = Self::new(packet);
+        helper.graph.id_counter = origin.id_counter;
+        helper.issued = origin.os.keys().copied().collect();
+        // os keep their id, so do measured edges
+        helper.graph.stats = origin.stats.clone();
+        helper
+    }
+    fn partial(packet: TcpPacket, origin: &BootGraph) -> Self {
+        let mut graph = origin.clone();
+        let offline = graph.graph.add_node(OsStatus::Down);
+        Self {
+            packet,
+            unknowns: HashMap::new(),
+            offline,
+            issued: origin.os.keys().copied().collect(),
+            graph,
+            checkpoint: None,
+        }
+    }
+    fn resume(packet: TcpPacket, checkpoint: Checkpoint) -> Self {
+        let Checkpoint { mut graph, unknowns, mut issued } = checkpoint;
+        let offline = graph.graph.add_node(OsStatus::Down);
+        // host may still run an os inited before the interruption
+        issued.extend(graph.os.keys().copied());
+        Self {
+            packet,
+            unknowns,
+            offline,
+            graph,
+            issued,
+            checkpoint: None,
+        }
+    }
+    async fn save_checkpoint(&self) {
+        if let Some(path) = &self.checkpoint {
+            let checkpoint = Checkpoint {
+                graph: self.graph.clone(),
+                unknowns: self.unknowns.clone(),
+                issued: self.issued.clone(),
+            };
+            // losing a checkpoint only cost some progress
+            if let Err(err) = checkpoint.save(path).await {
+                log::warn!("fail to save checkpoint {}: {}", path.display(), err);
+            }
         }
     }
     async fn set_uid(&mut self) -> Result<ID, Error> {
+        let uid = self.packet.get_uid().await?;
+        if self.issued.contains(&uid) {
+            log::trace!("os id of {} was kept by the client", uid);
+            return Ok(uid);
+        }
         let uid = self.graph.id_counter;
         self.graph.id_counter += 1;
         self.packet.set_uid(uid)?;
         self.packet.write_init_id(uid).await?;
         self.packet.read_init_id().await?;
-        log::trace!("os id of ${} was issue for the client",uid);
+        log::trace!("os id of {} was issue for the client",uid);
         Ok(uid)
     }
     async fn init_os(&mut self) -> Result<(), Error> {
@@ -80,11 +239,19 @@ impl Helper {
         // perform grub query
         self.packet.write_grub_query().await?;
         let grub_list = self.packet.read_grub_query().await?;
-        if !grub_list.is_empty(){
-            self.unknowns.insert(
-                uid,
-                grub_list.into_iter().map(|info| BootMethod::Grub(info.grub_sec)).collect(),
-            );
+        // firmware entries are explored like grub ones
+        self.packet.write_uefi_query().await?;
+        let uefi_list = self.packet.read_uefi_query().await?;
+        let entries: Vec<BootMethod> = grub_list
+            .into_iter()
+            .map(|info| BootMethod::Grub(info.grub_sec))
+            .chain(uefi_list.into_iter().map(|info| {
+                log::debug!("firmware entry {:04X}: {}", info.boot_num, info.description);
+                BootMethod::Uefi(info.boot_num)
+            }))
+            .collect();
+        if !entries.is_empty(){
+            self.unknowns.insert(uid, entries);
         }
         // perform os query
         self.packet.write_os_query().await?;
@@ -105,12 +272,13 @@ impl Helper {
     }
     async fn trace_unknown(&mut self) -> Result<BootMethod, Error> {
         let uid = self.packet.get_uid().await?;
-        let list=self.unknowns.get_mut(&uid).ok_or(Error::BadGraph).unwrap();
-        let path=list.pop().ok_or(Error::BadGraph).unwrap();
-        if list.is_empty(){
-            self.unknowns.remove(&uid).unwrap();
+        let from = OsStatus::Up(uid);
+        let list = self.unknowns.get_mut(&uid).ok_or(Error::BadGraph)?;
+        let path = list.pop().ok_or(Error::BadGraph)?;
+        if list.is_empty() {
+            self.unknowns.remove(&uid);
         }
-        path.execute(&mut self.packet).await?;
+        self.graph.execute(&from, &path, &mut self.packet).await?;
         log::trace!("randomly picked a unknown edge to discover");
         Ok(path)
     }
@@ -120,94 +288,254 @@ impl Helper {
         self.packet.wol_reconnect().await?;
         Ok(())
     }
-    async fn trace_closest_with_unknown(&mut self) -> Result<(), Error> {
+    /// return false if no os with unknown edge can be booted into
+    async fn trace_closest_with_unknown(&mut self) -> Result<bool, Error> {
         let from_node = self.get_node().await?;
-        let dijkstra = self.graph.graph.dijkstra(&from_node);
+        let dijkstra = self.graph.dijkstra(&from_node);
 
-        let unsafe_node = unsafe { Node::new(0) };
         let closest_node = self
             .unknowns
-            .iter()
-            .map(|(uid, _)| {
-                let node = self.graph.graph.find_node(&OsStatus::Up(*uid)).unwrap();
-                let distance = dijkstra.to(&node).unwrap_or(usize::MAX);
-                (node, distance)
-            })
-            .fold(
-                (unsafe_node, usize::MAX),
-                |(acc_node, acc_distance), (node, distance)| {
-                    if acc_distance > distance {
-                        (node, distance)
-                    } else {
-                        (acc_node, acc_distance)
-                    }
-                },
-            )
-            .0;
+            .keys()
+            .filter_map(|uid| self.graph.graph.find_node(&OsStatus::Up(*uid)))
+            .filter_map(|node| dijkstra.to(&node).map(|distance| (node, distance)))
+            .min_by_key(|(_, distance)| *distance)
+            .map(|(node, _)| node);
+        let Some(closest_node) = closest_node else {
+            return Ok(false);
+        };
         let trace = dijkstra
             .trace(&closest_node)
             .unwrap();
         for pat in trace{
-            pat.execute(&mut self.packet).await?;
+            let from = BootGraph::current_os(&self.packet).await?;
+            self.graph.execute(&from, pat, &mut self.packet).await?;
         }
         log::trace!("booted to closest node with unknown edge");
-        Ok(())
+        Ok(true)
     }
     async fn construct_wol_edge(&mut self)-> Result<(), Error> {
         let offline = self.offline;
         let node = self.get_node().await?;
-        self.graph.graph.connect(offline, node, BootMethod::WOL);
+        self.graph.graph.connect(offline, node, BootMethod::Wol);
         Ok(())
     }
     async fn is_os_inited(&self) -> Result<bool, Error> {
-        Ok(self.packet.get_uid().await? != 0)
+        let uid = self.packet.get_uid().await?;
+        Ok(self.graph.os.contains_key(&uid))
     }
     fn is_finish(&self) -> bool {
         self.unknowns.is_empty()
     }
-    fn finialize(self)->(BootGraph,TcpPacket){
-        (self.graph,self.packet)
+    // the connection is given back either way, the host may still answer after an error
+    fn finialize(self, res: Result<(), Error>) -> Result<(BootGraph, TcpPacket), Box<Interrupted>> {
+        match res {
+            Ok(()) => Ok((self.graph, self.packet)),
+            Err(error) => Err(Box::new(Interrupted {
+                error,
+                packet: self.packet,
+            })),
+        }
+    }
+    async fn explore(&mut self) -> Result<(), Error> {
+        self.reset().await?;
+        self.init_os().await?;
+        self.construct_wol_edge().await?;
+        self.save_checkpoint().await;
+        self.discover().await
+    }
+    /// go on with an interrupted exploration, its wol edge was already learned
+    async fn proceed(&mut self) -> Result<(), Error> {
+        if !self.is_os_inited().await? {
+            self.init_os().await?;
+        }
+        self.discover().await
+    }
+    /// explore only the given entries of current os, or every entry of an os never seen
+    async fn explore_entries(&mut self, entries: Vec<BootMethod>) -> Result<(), Error> {
+        if self.is_os_inited().await? {
+            let uid = self.packet.get_uid().await?;
+            if !entries.is_empty() {
+                self.unknowns.insert(uid, entries);
+            }
+        } else {
+            self.init_os().await?;
+            self.verify_wol_edge().await?;
+        }
+        self.discover().await
+    }
+    /// point the wol edge to the os waking actually lead to
+    ///
+    /// an os never seen may have been booted by hand, so it's only trusted once woken into
+    async fn verify_wol_edge(&mut self) -> Result<(), Error> {
+        let offline = self.offline;
+        let wol = self.graph.graph.follow(&offline, &BootMethod::Wol);
+        if wol.is_none() {
+            return self.construct_wol_edge().await;
+        }
+        if self.graph.is_manual(&OsStatus::Down, &BootMethod::Wol) {
+            return Ok(());
+        }
+        self.reset().await?;
+        if !self.is_os_inited().await? {
+            self.init_os().await?;
+        }
+        let node = self.get_node().await?;
+        if wol != Some(node) {
+            log::info!("waking now lead to os {}", self.packet.get_uid().await?);
+            self.graph.graph.disconnect(offline, &BootMethod::Wol);
+            self.construct_wol_edge().await?;
+            // timings were measured toward the former os
+            self.graph.stats.forget(&OsStatus::Down, &BootMethod::Wol);
+        }
+        Ok(())
+    }
+    // follow unknown edges until none left
+    async fn discover(&mut self) -> Result<(), Error> {
+        while !self.is_finish() {
+            if !self.is_os_inited().await? {
+                self.init_os().await?;
+            }
+            // boot to a node with unknown edge
+            if !self.trace_closest_with_unknown().await? {
+                // an os only booted by hand, no edge lead back to it
+                let oss: Vec<&ID> = self.unknowns.keys().collect();
+                log::warn!("os {:?} can't be booted into, their entries are left unexplored", oss);
+                self.unknowns.clear();
+                break;
+            }
+            let from_node=self.get_node().await?;
+            // boot to any unknown edge
+            let unknown_edge = self.trace_unknown().await?;
+            if !self.is_os_inited().await? {
+                self.init_os().await?;
+            }
+            let to_node=self.get_node().await?;
+            self.graph.graph.connect(from_node, to_node, unknown_edge);
+            self.save_checkpoint().await;
+        }
+        Ok(())
     }
 }
 
 #[derive(Clone, Deserialize, Serialize)]
 pub struct BootGraph {
-    graph: Graph<OsStatus, BootMethod>,
-    os: IndexMap<ID, OsInfo>,
-    id_counter: ID,
+    pub(super) graph: Graph<OsStatus, BootMethod>,
+    pub(super) os: IndexMap<ID, OsInfo>,
+    pub(super) id_counter: ID,
+    stats: EdgeStats,
+    // edges added by admin, kept by re-discovery
+    pub(super) manual: HashSet<(OsStatus, BootMethod)>,
+    // edges never taken when planning
+    pub(super) disabled: HashSet<(OsStatus, BootMethod)>,
 }
 
 impl Default for BootGraph {
     fn default() -> Self {
-        Self { graph: Default::default(), os: Default::default(), id_counter: 1 }
+        Self {
+            graph: Default::default(),
+            os: Default::default(),
+            id_counter: 1,
+            stats: Default::default(),
+            manual: Default::default(),
+            disabled: Default::default(),
+        }
     }
 }
 
 impl BootGraph {
     pub async fn new(packet: TcpPacket) -> Result<(Self,TcpPacket), Error> {
         let mut helper = Helper::new(packet);
-        helper.reset().await?;
-        helper.init_os().await?;
-        helper.construct_wol_edge().await?;
-
-        while !helper.is_finish() {
-            if !helper.is_os_inited().await? {
-                helper.init_os().await?;
+        let res = helper.explore().await;
+        helper.finialize(res).map_err(|interrupted| interrupted.error)
+    }
+    /// explore a new machine, saving progress to `checkpoint` after every edge
+    ///
+    /// resume from the checkpoint if a previous enrollment was interrupted, it's removed once done
+    pub async fn enroll(packet: TcpPacket, checkpoint: &Path) -> Result<(Self,TcpPacket), Error> {
+        let (mut helper, resumed) = match Checkpoint::load(checkpoint).await {
+            Some(saved) => {
+                log::info!("resuming enrollment from {}", checkpoint.display());
+                (Helper::resume(packet, saved), true)
             }
-            // boot to a node with unknown edge
-            helper.trace_closest_with_unknown().await?;
-            let from_node=helper.get_node().await?;
-            // boot to any unknown edge
-            let unknown_edge=helper.trace_unknown().await.unwrap();
-            if !helper.is_os_inited().await? {
-                helper.init_os().await?;
+            None => (Helper::new(packet), false),
+        };
+        helper.checkpoint = Some(checkpoint.to_owned());
+        let res = if resumed {
+            helper.proceed().await
+        } else {
+            helper.explore().await
+        };
+        if res.is_ok() {
+            async_std::fs::remove_file(checkpoint).await.ok();
+        }
+        helper.finialize(res).map_err(|interrupted| interrupted.error)
+    }
+    /// graph of a machine without agent, which is only woken into a single os
+    pub fn standalone(display_name: String) -> Self {
+        let mut graph = Self::default();
+        let id = graph.id_counter;
+        graph.id_counter += 1;
+        graph.os.insert(
+            id,
+            OsInfo {
+                display_name,
+                ..Default::default()
+            },
+        );
+        let down = graph.graph.add_node(OsStatus::Down);
+        let up = graph.graph.add_node(OsStatus::Up(id));
+        graph.graph.connect(down, up, BootMethod::Wol);
+        // kept like an edge added by hand, nothing can discover it
+        graph.manual.insert((OsStatus::Down, BootMethod::Wol));
+        graph
+    }
+    /// explore again, os keep the id issued by this graph
+    pub async fn renew(&self, packet: TcpPacket) -> Result<(Self,TcpPacket), Box<Interrupted>> {
+        let mut helper = Helper::renew(packet, self);
+        let res = helper.explore().await;
+        let (mut graph, packet) = helper.finialize(res)?;
+        graph.keep_manual(self);
+        Ok((graph, packet))
+    }
+    /// explore new grub entries of current os, the rest of the graph is kept
+    ///
+    /// an os never seen is explored with all its entries
+    pub async fn rediscover(&self, packet: TcpPacket, entries: Vec<BootMethod>) -> Result<(Self,TcpPacket), Box<Interrupted>> {
+        let mut helper = Helper::partial(packet, self);
+        let res = helper.explore_entries(entries).await;
+        helper.finialize(res)
+    }
+    /// grub and firmware entries reported by an os, compared with its edges
+    ///
+    /// return entries the graph doesn't know and edges no longer reported, none if the os is unknown
+    pub fn diff_entries(&self, os: ID, reported: &[BootMethod]) -> Option<(Vec<BootMethod>, Vec<BootMethod>)> {
+        self.graph.find_node(&OsStatus::Up(os))?;
+        let known: Vec<BootMethod> = self
+            .graph
+            .list_edge()
+            .filter(|(from, method, _)| {
+                **from == OsStatus::Up(os) && matches!(method, BootMethod::Grub(_) | BootMethod::Uefi(_))
+            })
+            .map(|(_, method, _)| method.clone())
+            .collect();
+        let added = reported.iter().filter(|x| !known.contains(x)).cloned().collect();
+        // manual edges aren't reported by grub nor firmware
+        let vanished = known
+            .into_iter()
+            .filter(|x| !reported.contains(x) && !self.is_manual(&OsStatus::Up(os), x))
+            .collect();
+        Some((added, vanished))
+    }
+    /// remove edges of an os
+    pub fn forget_edges(&mut self, os: ID, methods: &[BootMethod]) {
+        if let Some(node) = self.graph.find_node(&OsStatus::Up(os)) {
+            for method in methods {
+                log::info!("{:?} of os {} vanished", method, os);
+                self.graph.disconnect(node, method);
             }
-            let to_node=helper.get_node().await?;
-            helper.graph.graph.connect(from_node, to_node, unknown_edge);
         }
-        Ok(helper.finialize())
     }
-    pub async fn current_os(&self, packet: &TcpPacket) -> Result<OsStatus, Error> {
+    pub async fn current_os(packet: &TcpPacket) -> Result<OsStatus, Error> {
         match packet.get_uid().await {
             Ok(x) => {
                 Ok(OsStatus::Up(x))
@@ -226,35 +554,200 @@ impl BootGraph {
     pub fn find_os(&self, os: ID) -> Option<&OsInfo> {
         self.os.get(&os)
     }
-    pub async fn boot(&self,os:OsStatus,packet:&mut TcpPacket)->Result<(),Error>{
-        let from_os=match packet.get_uid().await{
-            Ok(x) => Ok(OsStatus::Up(x)),
-            Err(err) => match err{
-                packet::Error::ClientOffline => Ok(OsStatus::Down),
-                packet::Error::Timeout |  packet::Error::Conn(_) => Err(err).into(),
-            },
-        }?;
-        let from_node=self.graph.find_node(&from_os).ok_or(Error::BadGraph)?;
-        let to_node=self.graph.find_node(&os).ok_or(Error::BadGraph)?;
+    /// every edge as (from, method, to)
+    pub fn list_edge(&self) -> impl Iterator<Item = (&OsStatus, &BootMethod, &OsStatus)> {
+        self.graph.list_edge()
+    }
+    pub fn list_node(&self) -> impl Iterator<Item = &OsStatus> {
+        self.graph.list_node()
+    }
+    /// graphviz dot of the graph, edges are labeled with boot method and median duration
+    pub fn to_dot<F: Fn(&OsStatus) -> String>(&self, name: F) -> String {
+        self.graph.to_dot(name, |from, method| {
+            let mut label = format!("{:?}", method);
+            if let Some(median) = self.measure(from, method).and_then(|x| x.median()) {
+                label += &format!("\n{:.1}s", median.as_secs_f64());
+            }
+            if self.is_degraded(from, method) {
+                label += "\n(degraded)";
+            }
+            if self.is_manual(from, method) {
+                label += "\n(manual)";
+            }
+            if self.is_disabled(from, method) {
+                label += "\n(disabled)";
+            }
+            label
+        })
+    }
+    /// timing of an edge, none if it never ran
+    pub fn measure(&self, from: &OsStatus, method: &BootMethod) -> Option<Measure> {
+        self.stats.get(from, method)
+    }
+    pub fn is_degraded(&self, from: &OsStatus, method: &BootMethod) -> bool {
+        self.stats.is_degraded(from, method)
+    }
+    /// fastest path by measured duration of each edge, avoiding degraded ones
+    fn dijkstra(&self, from: &Node) -> Dijkstra<'_, BootMethod> {
+        self.graph.dijkstra(from, |from, method| {
+            if self.is_disabled(from, method) {
+                // never relaxed, saturating add keep it at max
+                return usize::MAX;
+            }
+            self.stats.cost(from, method).as_millis() as usize
+        })
+    }
+    /// hops `boot` would take from `from` to `os`, without running anything
+    ///
+    /// the path is planned again after every hop while booting, so it may differ
+    pub fn plan(&self, from: &OsStatus, os: &OsStatus) -> Result<Vec<Hop>, Error> {
+        let from_node = self.graph.find_node(from).ok_or(Error::BadGraph)?;
+        let to_node = self.graph.find_node(os).ok_or(Error::BadGraph)?;
+        let dijkstra = self.dijkstra(&from_node);
+        let trace = dijkstra.trace(&to_node).ok_or(Error::BadGraph)?;
 
-        let dijkstra=self.graph.dijkstra(&from_node);
-        let trace=dijkstra.trace(&to_node).ok_or(Error::BadGraph)?;
-        for pat in trace{
-            pat.execute(packet).await?;
+        let (mut node, mut from) = (from_node, from.clone());
+        trace
+            .into_iter()
+            .map(|method| {
+                let next = self.graph.follow(&node, method).ok_or(Error::BadGraph)?;
+                let to = self.graph.value(&next).ok_or(Error::BadGraph)?.clone();
+                let hop = Hop {
+                    method: method.clone(),
+                    to: to.clone(),
+                    estimate: self.stats.estimate(&from, method),
+                };
+                (node, from) = (next, to);
+                Ok(hop)
+            })
+            .collect()
+    }
+    /// first edge of the fastest path from `from` to `os`
+    fn next_hop(&self, from: &OsStatus, os: &OsStatus) -> Result<BootMethod, Error> {
+        let from_node = self.graph.find_node(from).ok_or(Error::BadGraph)?;
+        let to_node = self.graph.find_node(os).ok_or(Error::BadGraph)?;
+        self.dijkstra(&from_node)
+            .trace(&to_node)
+            .and_then(|trace| trace.first().map(|method| (*method).clone()))
+            .ok_or(Error::BadGraph)
+    }
+    /// execute an edge, recording how long it took
+    async fn execute(&self, from: &OsStatus, method: &BootMethod, packet: &mut TcpPacket) -> Result<(), Error> {
+        let start = Instant::now();
+        let res = method.execute(packet).await;
+        self.record(from, method, res, start.elapsed())
+    }
+    fn record(&self, from: &OsStatus, method: &BootMethod, res: Result<(), packet::Error>, elapsed: Duration) -> Result<(), Error> {
+        match res {
+            Ok(()) => {
+                self.stats.record(from, method, elapsed);
+                Ok(())
+            }
+            Err(err) => {
+                self.stats.fail(from, method);
+                Err(err.into())
+            }
+        }
+    }
+    /// boot into `os`, planning again from the actual os after every hop
+    ///
+    /// a failing hop is retried with backoff, from wherever the machine land.
+    /// the graph is only locked to plan or to record a hop, never while one run
+    pub async fn boot(graph: &RwLock<Self>, os: OsStatus, packet: &mut TcpPacket) -> Result<(), Error> {
+        let mut failures = 0;
+        for _ in 0..MAX_HOP {
+            let from_os = Self::current_os(packet).await?;
+            if from_os == os {
+                return Ok(());
+            }
+            let pat = graph.read().await.next_hop(&from_os, &os)?;
+            let start = Instant::now();
+            let res = pat.execute(packet).await;
+            let res = graph.read().await.record(&from_os, &pat, res, start.elapsed());
+            if let Err(err) = res {
+                // the probe already waited for its own timeout, retrying wouldn't tell more
+                if matches!(err, Error::Packet(packet::Error::AgentMissing | packet::Error::NotWoken)) {
+                    return Err(err);
+                }
+                // the host was reset or powered off out of band, plan again from there
+                if let Error::Packet(packet::Error::Escalated) = err {
+                    log::warn!("{:?} from {:?} escalated, planning again", pat, from_os);
+                    continue;
+                }
+                if let Error::Packet(packet::Error::Conn(_)) = err {
+                    // host is likely restarting, plan from down and wait for it
+                    packet.disconnect().await;
+                }
+                failures += 1;
+                if failures > MAX_RETRY {
+                    return Err(err);
+                }
+                let backoff = RETRY_BACKOFF * 2u32.pow(failures - 1);
+                log::warn!("{:?} from {:?} failed: {:?}, retry in {:?}", pat, from_os, err, backoff);
+                async_std::task::sleep(backoff).await;
+            } else {
+                Self::check_landing(graph, &from_os, &pat, packet).await?;
+            }
+        }
+        Err(Error::TooManyHop)
+    }
+    // compare where an edge led with the graph, mismatches are counted toward re-learning
+    async fn check_landing(graph: &RwLock<Self>, from: &OsStatus, method: &BootMethod, packet: &TcpPacket) -> Result<(), Error> {
+        let landed = Self::current_os(packet).await?;
+        let graph = graph.read().await;
+        // the graph may have been edited while the edge ran
+        let expected = graph
+            .graph
+            .find_node(from)
+            .and_then(|node| graph.graph.follow(&node, method))
+            .and_then(|node| graph.graph.value(&node).cloned());
+        if expected.as_ref() == Some(&landed) {
+            graph.stats.route(from, method);
+        } else {
+            log::warn!("graph inconsistency: {:?} from {:?} was expected to reach {:?}, but landed in {:?}", method, from, expected, landed);
+            graph.stats.misroute(from, method, &landed);
         }
         Ok(())
     }
+    /// point edges that keep landing in the same unexpected os to that os
+    ///
+    /// return whether the graph changed
+    pub fn heal(&mut self) -> bool {
+        let mut healed = false;
+        for (from, method, landed) in self.stats.take_relearn() {
+            if self.is_manual(&from, &method) {
+                log::warn!("{:?} from {:?} landed in {:?}, kept as added by hand", method, from, landed);
+                continue;
+            }
+            let (Some(from_node), Some(to_node)) = (self.graph.find_node(&from), self.graph.find_node(&landed)) else {
+                log::warn!("{:?} from {:?} landed in {:?}, which is unknown to the graph", method, from, landed);
+                continue;
+            };
+            if self.graph.disconnect(from_node, &method).is_some() {
+                log::info!("re-learned {:?} from {:?}, now leading to {:?}", method, from, landed);
+                self.graph.connect(from_node, to_node, method);
+                healed = true;
+            }
+        }
+        healed
+    }
 }
 
-#[derive(Clone, Deserialize, Serialize)]
+#[derive(Clone, Default, Deserialize, Serialize)]
 pub struct OsInfo {
     pub display_name: String,
+    pub kernel_version: String,
+    pub distro_id: String,
+    pub hostname: String,
 }
 
 impl OsInfo {
     fn from_query(query: proto::prelude::host::OsQuery) -> Self {
         Self {
             display_name: query.display_name,
+            kernel_version: query.kernel_version,
+            distro_id: query.distro_id,
+            hostname: query.hostname,
         }
     }
 }
@@ -265,6 +758,436 @@ pub enum Error {
     UndefinedClientBehavior,
     #[error("maybe graph is badly created")]
     BadGraph,
+    #[error("os not reached after too many hops")]
+    TooManyHop,
     #[error("Packet Error")]
-    PacketError(#[from] packet::Error),
+    Packet(#[from] packet::Error),
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::{HashMap, HashSet};
+    use std::marker::PhantomData;
+    use std::net::{Ipv4Addr, SocketAddr};
+    use std::time::Duration;
+
+    use async_std::channel::{self, Receiver};
+    use async_std::future::timeout;
+    use async_std::net::{TcpListener, TcpStream};
+    use async_std::task::{sleep, spawn};
+    use proto::prelude::{
+        host, packets::host::Packet as HostP, packets::server::Packet as ServerP, GrubId,
+        ReadConn, WriteConn, APIVERSION, ID, PROTO_IDENT,
+    };
+
+    use super::{BootGraph, BootMethod, Checkpoint, Error, Hop, OsInfo, OsStatus};
+    use crate::grub::packet::{TcpPacket, TcpPackets, WolConfig};
+
+    const MAC: [u8; 6] = [0x00, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e];
+    // a real host take seconds to reboot, the server must be waiting for it by then
+    const REBOOT_DELAY: Duration = Duration::from_millis(100);
+
+    /// host chain loading its os by grub, woken into the first one
+    struct MockHost {
+        // grub entries of every os, with the os they boot
+        oss: Vec<Vec<(GrubId, usize)>>,
+        uids: Vec<ID>,
+        current: usize,
+        // rebooting by this entry crash the host once, which come back in the first os
+        crash: Option<GrubId>,
+    }
+
+    impl MockHost {
+        fn new(oss: Vec<Vec<(GrubId, usize)>>) -> Self {
+            Self {
+                uids: vec![0; oss.len()],
+                oss,
+                current: 0,
+                crash: None,
+            }
+        }
+        async fn run(mut self, server: SocketAddr) {
+            loop {
+                let stream = TcpStream::connect(server).await.unwrap();
+                let mut reader = ReadConn::<_, ServerP> {
+                    data_type: PhantomData,
+                    stream: stream.clone(),
+                };
+                let mut writer = WriteConn::<_, HostP> {
+                    data_type: PhantomData,
+                    stream,
+                };
+                let handshake = host::Handshake {
+                    ident: PROTO_IDENT,
+                    mac_address: MAC,
+                    uid: self.uids[self.current],
+                    version: APIVERSION,
+                };
+                writer.write(HostP::Handshake(handshake)).await.unwrap();
+                loop {
+                    let res = match reader.read().await {
+                        Ok(ServerP::Handshake(_)) => continue,
+                        Ok(ServerP::InitId(uid)) => {
+                            self.uids[self.current] = uid;
+                            HostP::InitId
+                        }
+                        Ok(ServerP::GrubQuery) => HostP::GrubQuery(
+                            self.oss[self.current]
+                                .iter()
+                                .map(|(grub_sec, _)| host::GrubInfo { grub_sec: *grub_sec })
+                                .collect(),
+                        ),
+                        Ok(ServerP::UefiQuery) => HostP::UefiQuery(Vec::new()),
+                        Ok(ServerP::OsQuery) => HostP::OsQuery(host::OsQuery {
+                            display_name: format!("os {}", self.current),
+                            kernel_version: String::new(),
+                            distro_id: String::new(),
+                            hostname: String::new(),
+                        }),
+                        Ok(ServerP::Reboot(grub_sec)) => {
+                            if self.crash == Some(grub_sec) {
+                                self.crash = None;
+                                self.current = 0;
+                                break;
+                            }
+                            writer.write(HostP::Reboot).await.unwrap();
+                            self.current = self.oss[self.current]
+                                .iter()
+                                .find(|(entry, _)| *entry == grub_sec)
+                                .unwrap()
+                                .1;
+                            break;
+                        }
+                        Ok(ServerP::Shutdown) => {
+                            // woken as soon as it's down
+                            writer.write(HostP::Shutdown).await.unwrap();
+                            self.current = 0;
+                            break;
+                        }
+                        Ok(packet) => panic!("unexpected {:?}", packet),
+                        // server is done with the host
+                        Err(_) => return,
+                    };
+                    writer.write(res).await.unwrap();
+                }
+                drop((reader, writer));
+                sleep(REBOOT_DELAY).await;
+            }
+        }
+    }
+
+    /// connections of the host which aren't awaited by a reconnection
+    async fn serve(host: MockHost) -> Receiver<TcpPacket> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = channel::unbounded();
+        spawn(async move {
+            let packets = TcpPackets::default();
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                if let Some(packet) = packets.connect(stream).await.unwrap() {
+                    tx.send(packet).await.unwrap();
+                }
+            }
+        });
+        spawn(host.run(addr));
+        rx
+    }
+
+    async fn accept(rx: &Receiver<TcpPacket>) -> TcpPacket {
+        let mut packet = rx.recv().await.unwrap();
+        // the mock host doesn't listen for magic packets
+        packet.set_wol(WolConfig {
+            target: Some(Ipv4Addr::LOCALHOST),
+            ..Default::default()
+        });
+        packet
+    }
+
+    fn edges(graph: &BootGraph) -> HashSet<(OsStatus, BootMethod, OsStatus)> {
+        graph
+            .list_edge()
+            .map(|(from, method, to)| (from.clone(), method.clone(), to.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn heal() {
+        let mut graph = BootGraph::default();
+        let down = graph.graph.add_node(OsStatus::Down);
+        let ubuntu = graph.graph.add_node(OsStatus::Up(1));
+        let debian = graph.graph.add_node(OsStatus::Up(2));
+        graph.graph.connect(down, ubuntu, BootMethod::Wol);
+        graph.graph.connect(ubuntu, debian, BootMethod::Grub(7));
+
+        // grub.cfg was reordered, entry 7 now boot ubuntu itself
+        let from = OsStatus::Up(1);
+        graph.stats.misroute(&from, &BootMethod::Grub(7), &OsStatus::Up(1));
+        assert!(!graph.heal());
+        graph.stats.misroute(&from, &BootMethod::Grub(7), &OsStatus::Up(1));
+        assert!(graph.heal());
+        assert_eq!(graph.graph.follow(&ubuntu, &BootMethod::Grub(7)), Some(ubuntu));
+        assert_eq!(graph.graph.follow(&down, &BootMethod::Wol), Some(ubuntu));
+
+        // an os the graph doesn't know need a re-discovery
+        graph.stats.misroute(&from, &BootMethod::Grub(7), &OsStatus::Up(9));
+        graph.stats.misroute(&from, &BootMethod::Grub(7), &OsStatus::Up(9));
+        assert!(!graph.heal());
+        assert_eq!(graph.graph.follow(&ubuntu, &BootMethod::Grub(7)), Some(ubuntu));
+    }
+
+    #[test]
+    fn diff_entries() {
+        let mut graph = BootGraph::default();
+        let down = graph.graph.add_node(OsStatus::Down);
+        let ubuntu = graph.graph.add_node(OsStatus::Up(1));
+        let debian = graph.graph.add_node(OsStatus::Up(2));
+        graph.graph.connect(ubuntu, down, BootMethod::Shutdown);
+        graph.graph.connect(ubuntu, debian, BootMethod::Grub(7));
+        graph.graph.connect(ubuntu, ubuntu, BootMethod::Grub(8));
+        graph.graph.connect(ubuntu, debian, BootMethod::Uefi(3));
+        graph.graph.connect(debian, ubuntu, BootMethod::Grub(9));
+
+        let reported = [BootMethod::Grub(8), BootMethod::Uefi(3), BootMethod::Grub(7)];
+        assert_eq!(graph.diff_entries(1, &reported), Some((vec![], vec![])));
+        assert_eq!(
+            graph.diff_entries(1, &[BootMethod::Grub(8), BootMethod::Grub(10), BootMethod::Uefi(4)]),
+            Some((
+                vec![BootMethod::Grub(10), BootMethod::Uefi(4)],
+                vec![BootMethod::Grub(7), BootMethod::Uefi(3)]
+            ))
+        );
+        assert_eq!(graph.diff_entries(3, &[BootMethod::Grub(8)]), None);
+
+        graph.forget_edges(1, &[BootMethod::Grub(7), BootMethod::Uefi(3)]);
+        assert_eq!(graph.graph.follow(&ubuntu, &BootMethod::Grub(7)), None);
+        assert_eq!(graph.graph.follow(&ubuntu, &BootMethod::Shutdown), Some(down));
+        assert_eq!(graph.graph.follow(&debian, &BootMethod::Grub(9)), Some(ubuntu));
+        assert_eq!(graph.diff_entries(1, &[BootMethod::Grub(8)]), Some((vec![], vec![])));
+    }
+
+    #[async_std::test]
+    async fn checkpoint() {
+        let dir = std::env::temp_dir().join(format!("grub-wol-checkpoint-{}", std::process::id()));
+        let path = dir.join("enroll.sav");
+        assert!(Checkpoint::load(&path).await.is_none());
+
+        let mut graph = BootGraph::default();
+        let down = graph.graph.add_node(OsStatus::Down);
+        let ubuntu = graph.graph.add_node(OsStatus::Up(1));
+        graph.graph.connect(down, ubuntu, BootMethod::Wol);
+        graph.graph.connect(ubuntu, down, BootMethod::Shutdown);
+        graph.id_counter = 2;
+        let checkpoint = Checkpoint {
+            graph,
+            unknowns: HashMap::from([(1, vec![BootMethod::Grub(3), BootMethod::Grub(4)])]),
+            issued: HashSet::from([7]),
+        };
+        checkpoint.save(&path).await.unwrap();
+
+        let loaded = Checkpoint::load(&path).await.unwrap();
+        assert_eq!(loaded.unknowns, checkpoint.unknowns);
+        assert_eq!(loaded.issued, checkpoint.issued);
+        assert_eq!(loaded.graph.id_counter, 2);
+        assert_eq!(
+            loaded.graph.graph.follow(&down, &BootMethod::Wol),
+            Some(ubuntu)
+        );
+        assert!(!path.with_extension("tmp").exists());
+
+        // a truncated checkpoint start over instead of failing the enrollment
+        std::fs::write(&path, [1, 2, 3]).unwrap();
+        assert!(Checkpoint::load(&path).await.is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn plan() {
+        let mut graph = BootGraph::default();
+        let down = graph.graph.add_node(OsStatus::Down);
+        let ubuntu = graph.graph.add_node(OsStatus::Up(1));
+        let debian = graph.graph.add_node(OsStatus::Up(2));
+        let windows = graph.graph.add_node(OsStatus::Up(3));
+        graph.graph.connect(down, ubuntu, BootMethod::Wol);
+        graph.graph.connect(ubuntu, down, BootMethod::Shutdown);
+        graph.graph.connect(ubuntu, debian, BootMethod::Grub(7));
+        graph.graph.connect(debian, down, BootMethod::Shutdown);
+        graph.graph.connect(windows, down, BootMethod::Shutdown);
+        graph.stats.record(&OsStatus::Down, &BootMethod::Wol, Duration::from_secs(40));
+
+        let hops = graph.plan(&OsStatus::Down, &OsStatus::Up(2)).unwrap();
+        assert_eq!(
+            hops,
+            vec![
+                Hop {
+                    method: BootMethod::Wol,
+                    to: OsStatus::Up(1),
+                    estimate: Duration::from_secs(40),
+                },
+                Hop {
+                    method: BootMethod::Grub(7),
+                    to: OsStatus::Up(2),
+                    estimate: BootMethod::Grub(7).default_duration(),
+                },
+            ]
+        );
+        assert!(graph.plan(&OsStatus::Up(2), &OsStatus::Up(2)).unwrap().is_empty());
+        // nothing lead to windows
+        assert!(matches!(graph.plan(&OsStatus::Down, &OsStatus::Up(3)), Err(Error::BadGraph)));
+        assert!(matches!(graph.plan(&OsStatus::Down, &OsStatus::Up(9)), Err(Error::BadGraph)));
+    }
+
+    #[test]
+    fn standalone() {
+        let graph = BootGraph::standalone("awake".to_owned());
+        assert_eq!(graph.find_os(1).unwrap().display_name, "awake");
+        let hops = graph.plan(&OsStatus::Down, &OsStatus::Up(1)).unwrap();
+        assert_eq!(hops.len(), 1);
+        assert_eq!(hops[0].method, BootMethod::Wol);
+        // never shutdown, there is no agent
+        assert!(matches!(graph.plan(&OsStatus::Up(1), &OsStatus::Down), Err(Error::BadGraph)));
+        assert!(graph.is_manual(&OsStatus::Down, &BootMethod::Wol));
+    }
+
+    #[async_std::test]
+    async fn resume() {
+        let dir = std::env::temp_dir().join(format!("grub-wol-resume-{}", std::process::id()));
+        let checkpoint = dir.join("enroll.sav");
+        let mut host = MockHost::new(vec![vec![(10, 0), (11, 1)], vec![(20, 0), (21, 1)]]);
+        host.crash = Some(11);
+        let rx = serve(host).await;
+
+        timeout(Duration::from_secs(30), async {
+            // the host crash while booting the second os
+            let packet = accept(&rx).await;
+            assert!(BootGraph::enroll(packet, &checkpoint).await.is_err());
+            assert!(checkpoint.exists());
+
+            let packet = accept(&rx).await;
+            let (graph, _packet) = BootGraph::enroll(packet, &checkpoint).await.unwrap();
+            assert!(!checkpoint.exists());
+            let (first, second) = (OsStatus::Up(1), OsStatus::Up(2));
+            assert_eq!(
+                edges(&graph),
+                HashSet::from([
+                    (OsStatus::Down, BootMethod::Wol, first.clone()),
+                    (first.clone(), BootMethod::Shutdown, OsStatus::Down),
+                    (first.clone(), BootMethod::Grub(10), first.clone()),
+                    (first.clone(), BootMethod::Grub(11), second.clone()),
+                    (second.clone(), BootMethod::Shutdown, OsStatus::Down),
+                    (second.clone(), BootMethod::Grub(20), first.clone()),
+                    (second.clone(), BootMethod::Grub(21), second.clone()),
+                ])
+            );
+        })
+        .await
+        .unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[async_std::test]
+    async fn unseen_os() {
+        // a new os took over grub, the known one is only chain loaded by it
+        let mut host = MockHost::new(vec![vec![(31, 0), (30, 1)], vec![(10, 1)]]);
+        host.uids[1] = 1;
+        let rx = serve(host).await;
+
+        let mut graph = BootGraph::default();
+        let down = graph.graph.add_node(OsStatus::Down);
+        let known = graph.graph.add_node(OsStatus::Up(1));
+        graph.graph.connect(down, known, BootMethod::Wol);
+        graph.graph.connect(known, down, BootMethod::Shutdown);
+        graph.graph.connect(known, known, BootMethod::Grub(10));
+        graph.os.insert(1, OsInfo::default());
+        graph.id_counter = 2;
+        graph.stats.record(&OsStatus::Down, &BootMethod::Wol, Duration::from_secs(40));
+
+        timeout(Duration::from_secs(30), async {
+            let packet = accept(&rx).await;
+            let (graph, _packet) = graph.rediscover(packet, Vec::new()).await.ok().unwrap();
+            // only timings of the rewired edge are left
+            let measure = graph.measure(&OsStatus::Down, &BootMethod::Wol).unwrap();
+            assert!(measure.p95().unwrap() < Duration::from_secs(40));
+            let (known, unseen) = (OsStatus::Up(1), OsStatus::Up(2));
+            assert_eq!(
+                edges(&graph),
+                HashSet::from([
+                    (OsStatus::Down, BootMethod::Wol, unseen.clone()),
+                    (known.clone(), BootMethod::Shutdown, OsStatus::Down),
+                    (known.clone(), BootMethod::Grub(10), known.clone()),
+                    (unseen.clone(), BootMethod::Shutdown, OsStatus::Down),
+                    (unseen.clone(), BootMethod::Grub(30), known.clone()),
+                    (unseen.clone(), BootMethod::Grub(31), unseen.clone()),
+                ])
+            );
+        })
+        .await
+        .unwrap();
+    }
+
+    #[async_std::test]
+    async fn unseen_os_booted_by_hand() {
+        // the new os was booted by hand, waking still lead to the known one
+        let mut host = MockHost::new(vec![vec![(10, 0), (11, 1)], vec![(20, 0)]]);
+        host.uids[0] = 1;
+        host.current = 1;
+        let rx = serve(host).await;
+
+        let mut graph = BootGraph::default();
+        let down = graph.graph.add_node(OsStatus::Down);
+        let known = graph.graph.add_node(OsStatus::Up(1));
+        graph.graph.connect(down, known, BootMethod::Wol);
+        graph.graph.connect(known, down, BootMethod::Shutdown);
+        graph.graph.connect(known, known, BootMethod::Grub(10));
+        graph.os.insert(1, OsInfo::default());
+        graph.id_counter = 2;
+        graph.stats.record(&OsStatus::Down, &BootMethod::Wol, Duration::from_secs(40));
+
+        timeout(Duration::from_secs(30), async {
+            let packet = accept(&rx).await;
+            let (graph, _packet) = graph.rediscover(packet, Vec::new()).await.ok().unwrap();
+            assert!(graph.measure(&OsStatus::Down, &BootMethod::Wol).is_some());
+            let (known, unseen) = (OsStatus::Up(1), OsStatus::Up(2));
+            assert_eq!(
+                edges(&graph),
+                HashSet::from([
+                    (OsStatus::Down, BootMethod::Wol, known.clone()),
+                    (known.clone(), BootMethod::Shutdown, OsStatus::Down),
+                    (known.clone(), BootMethod::Grub(10), known.clone()),
+                    // nothing known lead back to it, its entries are left unexplored
+                    (unseen.clone(), BootMethod::Shutdown, OsStatus::Down),
+                ])
+            );
+        })
+        .await
+        .unwrap();
+    }
+
+    #[async_std::test]
+    async fn unseen_os_manual_wol() {
+        // same host as unseen_os, but the wol edge was added by hand
+        let mut host = MockHost::new(vec![vec![(31, 0), (30, 1)], vec![(10, 1)]]);
+        host.uids[1] = 1;
+        let rx = serve(host).await;
+
+        let mut graph = BootGraph::default();
+        let down = graph.graph.add_node(OsStatus::Down);
+        let known = graph.graph.add_node(OsStatus::Up(1));
+        graph.graph.connect(down, known, BootMethod::Wol);
+        graph.graph.connect(known, down, BootMethod::Shutdown);
+        graph.graph.connect(known, known, BootMethod::Grub(10));
+        graph.os.insert(1, OsInfo::default());
+        graph.id_counter = 2;
+        graph.manual.insert((OsStatus::Down, BootMethod::Wol));
+
+        timeout(Duration::from_secs(30), async {
+            let packet = accept(&rx).await;
+            let (graph, _packet) = graph.rediscover(packet, Vec::new()).await.ok().unwrap();
+            let (known, unseen) = (OsStatus::Up(1), OsStatus::Up(2));
+            assert!(edges(&graph).contains(&(OsStatus::Down, BootMethod::Wol, known)));
+            assert!(!edges(&graph).contains(&(OsStatus::Down, BootMethod::Wol, unseen)));
+        })
+        .await
+        .unwrap();
+    }
 }
@@ -0,0 +1,465 @@
+use std::collections::HashSet;
+
+use proto::prelude::ID;
+
+use crate::grub::packet::BmcAction;
+
+use super::boot::{BootGraph, BootMethod, OsInfo, OsStatus};
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum EditError {
+    #[error("os is not in the graph")]
+    UnknownOs,
+    #[error("edge is not in the graph")]
+    UnknownEdge,
+    #[error("edge already exist, remove it first")]
+    Conflict,
+    #[error("{0:?} can't lead from {1:?} to {2:?}")]
+    BadMethod(BootMethod, OsStatus, OsStatus),
+    #[error("{0:?} would become unreachable")]
+    Unreachable(OsStatus),
+    #[error("{0:?} need its controller, configure it first")]
+    NoController(BootMethod),
+}
+
+impl BootGraph {
+    pub fn is_manual(&self, from: &OsStatus, method: &BootMethod) -> bool {
+        self.manual.contains(&(from.clone(), method.clone()))
+    }
+    pub fn is_disabled(&self, from: &OsStatus, method: &BootMethod) -> bool {
+        self.disabled.contains(&(from.clone(), method.clone()))
+    }
+    /// add an edge by hand, it's kept by re-discovery
+    pub fn add_edge(
+        &mut self,
+        from: &OsStatus,
+        method: BootMethod,
+        to: &OsStatus,
+    ) -> Result<(), EditError> {
+        let from_node = self.find_known(from)?;
+        let to_node = self.find_known(to)?;
+        let valid = matches!(
+            (&method, from, to),
+            (BootMethod::Wol, OsStatus::Down, OsStatus::Up(_))
+                | (BootMethod::Grub(_), OsStatus::Up(_), OsStatus::Up(_))
+                | (BootMethod::Uefi(_), OsStatus::Up(_), OsStatus::Up(_))
+                | (BootMethod::Shutdown, OsStatus::Up(_), OsStatus::Down)
+                | (BootMethod::Bmc(BmcAction::PowerOn), OsStatus::Down, OsStatus::Up(_))
+                | (BootMethod::Bmc(BmcAction::PowerOff), OsStatus::Up(_), OsStatus::Down)
+                | (BootMethod::Bmc(BmcAction::HardReset), OsStatus::Up(_), OsStatus::Up(_))
+                | (BootMethod::Bmc(BmcAction::OneTimeBoot(_)), _, OsStatus::Up(_))
+                | (BootMethod::PowerCycle, _, OsStatus::Up(_))
+        );
+        if !valid {
+            return Err(EditError::BadMethod(method, from.clone(), to.clone()));
+        }
+        if self.graph.follow(&from_node, &method).is_some() {
+            return Err(EditError::Conflict);
+        }
+        log::info!("added {:?} from {:?} to {:?} by hand", method, from, to);
+        self.manual.insert((from.clone(), method.clone()));
+        self.graph.connect(from_node, to_node, method);
+        Ok(())
+    }
+    /// remove an edge, refused if any os would become unreachable
+    pub fn remove_edge(&mut self, from: &OsStatus, method: &BootMethod) -> Result<(), EditError> {
+        let mut edited = self.clone();
+        edited.unlink(from, method)?;
+        self.check_reachable(&edited, None)?;
+        log::info!("removed {:?} from {:?}", method, from);
+        *self = edited;
+        Ok(())
+    }
+    /// a smart plug power the host on into the os waking lead to
+    ///
+    /// added like by hand, so re-discovery keep it
+    pub fn add_power_cycle(&mut self) {
+        let Some(down) = self.graph.find_node(&OsStatus::Down) else {
+            return;
+        };
+        if self.graph.follow(&down, &BootMethod::PowerCycle).is_some() {
+            return;
+        }
+        let woken = self
+            .graph
+            .follow(&down, &BootMethod::Wol)
+            .and_then(|node| self.graph.value(&node).cloned());
+        match woken {
+            Some(to) => {
+                self.add_edge(&OsStatus::Down, BootMethod::PowerCycle, &to).ok();
+            }
+            None => log::warn!("waking lead to no os, power cycle has to be added by hand"),
+        }
+    }
+    /// remove every edge of methods driven by a controller which was cleared
+    ///
+    /// never refused unlike remove_edge, these edges can't run anymore
+    pub fn remove_controlled<F: Fn(&BootMethod) -> bool>(&mut self, controlled: F) {
+        let edges: Vec<(OsStatus, BootMethod)> = self
+            .graph
+            .list_edge()
+            .filter(|(_, method, _)| controlled(method))
+            .map(|(from, method, _)| (from.clone(), method.clone()))
+            .collect();
+        for (from, method) in edges {
+            log::info!("removed {:?} from {:?}, its controller was cleared", method, from);
+            self.unlink(&from, &method).ok();
+        }
+    }
+    /// a disabled edge is kept but never planned, refused if any os would become unreachable
+    pub fn set_disabled(
+        &mut self,
+        from: &OsStatus,
+        method: &BootMethod,
+        disabled: bool,
+    ) -> Result<(), EditError> {
+        let from_node = self.find_known(from)?;
+        self.graph
+            .follow(&from_node, method)
+            .ok_or(EditError::UnknownEdge)?;
+        let key = (from.clone(), method.clone());
+        if !disabled {
+            self.disabled.remove(&key);
+            return Ok(());
+        }
+        let mut edited = self.clone();
+        edited.disabled.insert(key);
+        self.check_reachable(&edited, None)?;
+        log::info!("disabled {:?} from {:?}", method, from);
+        *self = edited;
+        Ok(())
+    }
+    /// add an os which can't be discovered, reached by `method` from `from`
+    ///
+    /// it's shutdown like any os, return the id issued to it
+    pub fn add_node(
+        &mut self,
+        display_name: String,
+        from: &OsStatus,
+        method: BootMethod,
+    ) -> Result<ID, EditError> {
+        let id = self.id_counter;
+        let mut edited = self.clone();
+        edited.id_counter += 1;
+        edited.os.insert(
+            id,
+            OsInfo {
+                display_name,
+                kernel_version: String::new(),
+                distro_id: String::new(),
+                hostname: String::new(),
+            },
+        );
+        let node = edited.graph.add_node(OsStatus::Up(id));
+        let down = edited.graph.add_node(OsStatus::Down);
+        edited.add_edge(from, method, &OsStatus::Up(id))?;
+        edited
+            .manual
+            .insert((OsStatus::Up(id), BootMethod::Shutdown));
+        edited.graph.connect(node, down, BootMethod::Shutdown);
+        log::info!("added os {} by hand", id);
+        *self = edited;
+        Ok(id)
+    }
+    /// remove an os with every edge from and to it, refused if another os would become unreachable
+    pub fn remove_node(&mut self, os: ID) -> Result<(), EditError> {
+        let status = OsStatus::Up(os);
+        let node = self.find_known(&status)?;
+        let mut edited = self.clone();
+        let edges: Vec<(OsStatus, BootMethod)> = edited
+            .graph
+            .list_edge()
+            .filter(|(from, _, to)| **from == status || **to == status)
+            .map(|(from, method, _)| (from.clone(), method.clone()))
+            .collect();
+        // unlinked one by one, so manual and disabled flags go with them
+        for (from, method) in edges {
+            edited.unlink(&from, &method)?;
+        }
+        edited.graph.remove_node(node);
+        edited.os.shift_remove(&os);
+        self.check_reachable(&edited, Some(&status))?;
+        log::info!("removed os {}", os);
+        *self = edited;
+        Ok(())
+    }
+    /// carry edges and os added by hand to a graph explored again
+    pub(super) fn keep_manual(&mut self, origin: &BootGraph) {
+        for (id, info) in &origin.os {
+            let manual_node = origin.manual.iter().any(|(from, method)| {
+                *from == OsStatus::Up(*id) && *method == BootMethod::Shutdown
+            });
+            if manual_node && !self.os.contains_key(id) {
+                self.os.insert(*id, info.clone());
+                self.graph.add_node(OsStatus::Up(*id));
+            }
+        }
+        self.id_counter = self.id_counter.max(origin.id_counter);
+        for (from, method, to) in origin.graph.list_edge() {
+            if !origin.is_manual(from, method) {
+                continue;
+            }
+            let (Some(from_node), Some(to_node)) =
+                (self.graph.find_node(from), self.graph.find_node(to))
+            else {
+                log::warn!(
+                    "{:?} from {:?} added by hand lead to an os which vanished",
+                    method,
+                    from
+                );
+                continue;
+            };
+            // the hand-made edge win over the discovered one
+            self.graph.disconnect(from_node, method);
+            self.graph.connect(from_node, to_node, method.clone());
+            self.manual.insert((from.clone(), method.clone()));
+        }
+        self.disabled = origin.disabled.clone();
+    }
+    fn find_known(&self, os: &OsStatus) -> Result<super::graph::Node, EditError> {
+        if let OsStatus::Up(id) = os {
+            if !self.os.contains_key(id) {
+                return Err(EditError::UnknownOs);
+            }
+        }
+        self.graph.find_node(os).ok_or(EditError::UnknownOs)
+    }
+    fn unlink(&mut self, from: &OsStatus, method: &BootMethod) -> Result<(), EditError> {
+        let from_node = self.find_known(from)?;
+        self.graph
+            .disconnect(from_node, method)
+            .ok_or(EditError::UnknownEdge)?;
+        let key = (from.clone(), method.clone());
+        self.manual.remove(&key);
+        self.disabled.remove(&key);
+        Ok(())
+    }
+    // os reachable from down through enabled edges
+    fn reachable(&self) -> HashSet<OsStatus> {
+        let Some(down) = self.graph.find_node(&OsStatus::Down) else {
+            return HashSet::new();
+        };
+        let dijkstra = self.graph.dijkstra(&down, |from, method| {
+            if self.is_disabled(from, method) {
+                usize::MAX
+            } else {
+                1
+            }
+        });
+        self.list_node()
+            .filter(|os| {
+                self.graph
+                    .find_node(os)
+                    .is_some_and(|node| dijkstra.to(&node).is_some())
+            })
+            .cloned()
+            .collect()
+    }
+    // an edit must not cut off any os reachable before, except the one removed
+    fn check_reachable(
+        &self,
+        edited: &BootGraph,
+        removed: Option<&OsStatus>,
+    ) -> Result<(), EditError> {
+        let after = edited.reachable();
+        match self
+            .reachable()
+            .into_iter()
+            .find(|os| Some(os) != removed && !after.contains(os))
+        {
+            Some(os) => Err(EditError::Unreachable(os)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::EditError;
+    use crate::grub::bootgraph::{BootGraph, BootMethod, OsStatus};
+    use crate::grub::packet::BmcAction;
+
+    // down -> ubuntu <-> debian, both shutdown
+    fn graph() -> BootGraph {
+        let mut graph = BootGraph::default();
+        let down = graph.graph.add_node(OsStatus::Down);
+        for id in [1, 2] {
+            let node = graph.graph.add_node(OsStatus::Up(id));
+            graph.os.insert(id, Default::default());
+            graph.graph.connect(node, down, BootMethod::Shutdown);
+        }
+        let (ubuntu, debian) = (
+            graph.graph.find_node(&OsStatus::Up(1)).unwrap(),
+            graph.graph.find_node(&OsStatus::Up(2)).unwrap(),
+        );
+        graph.graph.connect(down, ubuntu, BootMethod::Wol);
+        graph.graph.connect(ubuntu, debian, BootMethod::Grub(7));
+        graph.graph.connect(debian, ubuntu, BootMethod::Grub(8));
+        graph.id_counter = 3;
+        graph
+    }
+
+    #[test]
+    fn controlled() {
+        let mut graph = graph();
+        let (down, ubuntu, debian) = (OsStatus::Down, OsStatus::Up(1), OsStatus::Up(2));
+        let (power_on, reset) = (BootMethod::Bmc(BmcAction::PowerOn), BootMethod::Bmc(BmcAction::HardReset));
+        graph.add_edge(&down, power_on.clone(), &debian).unwrap();
+        graph.add_edge(&ubuntu, reset.clone(), &ubuntu).unwrap();
+        graph.set_disabled(&ubuntu, &reset, true).unwrap();
+
+        graph.remove_controlled(|method| matches!(method, BootMethod::Bmc(_)));
+        assert!(graph.list_edge().all(|(_, method, _)| !matches!(method, BootMethod::Bmc(_))));
+        assert!(!graph.is_manual(&down, &power_on));
+        assert!(!graph.is_disabled(&ubuntu, &reset));
+        assert_eq!(graph.list_edge().count(), 5);
+    }
+
+    #[test]
+    fn power_cycle() {
+        let mut graph = graph();
+        let (down, ubuntu) = (OsStatus::Down, OsStatus::Up(1));
+        graph.add_power_cycle();
+        graph.add_power_cycle();
+        let power_cycles: Vec<_> = graph
+            .list_edge()
+            .filter(|(_, method, _)| **method == BootMethod::PowerCycle)
+            .map(|(from, _, to)| (from.clone(), to.clone()))
+            .collect();
+        assert_eq!(power_cycles, vec![(down.clone(), ubuntu)]);
+        assert!(graph.is_manual(&down, &BootMethod::PowerCycle));
+
+        graph.remove_controlled(|method| *method == BootMethod::PowerCycle);
+        assert_eq!(graph.list_edge().count(), 5);
+    }
+
+    #[test]
+    fn edge() {
+        let mut graph = graph();
+        let (down, ubuntu, debian) = (OsStatus::Down, OsStatus::Up(1), OsStatus::Up(2));
+
+        assert_eq!(
+            graph.add_edge(&ubuntu, BootMethod::Grub(7), &ubuntu),
+            Err(EditError::Conflict)
+        );
+        assert!(matches!(
+            graph.add_edge(&down, BootMethod::Grub(9), &debian),
+            Err(EditError::BadMethod(..))
+        ));
+        assert!(matches!(
+            graph.add_edge(&debian, BootMethod::Shutdown, &ubuntu),
+            Err(EditError::BadMethod(..))
+        ));
+        assert_eq!(
+            graph.add_edge(&ubuntu, BootMethod::Grub(9), &OsStatus::Up(5)),
+            Err(EditError::UnknownOs)
+        );
+
+        // debian is only reached through grub 7
+        assert_eq!(
+            graph.remove_edge(&ubuntu, &BootMethod::Grub(7)),
+            Err(EditError::Unreachable(debian.clone()))
+        );
+        assert_eq!(
+            graph.set_disabled(&ubuntu, &BootMethod::Grub(7), true),
+            Err(EditError::Unreachable(debian.clone()))
+        );
+        assert!(!graph.is_disabled(&ubuntu, &BootMethod::Grub(7)));
+
+        graph
+            .add_edge(&ubuntu, BootMethod::Grub(9), &debian)
+            .unwrap();
+        assert!(graph.is_manual(&ubuntu, &BootMethod::Grub(9)));
+        graph
+            .set_disabled(&ubuntu, &BootMethod::Grub(7), true)
+            .unwrap();
+        assert_eq!(
+            graph.plan(&ubuntu, &debian).unwrap()[0].method,
+            BootMethod::Grub(9)
+        );
+        assert_eq!(
+            graph.set_disabled(&ubuntu, &BootMethod::Grub(9), true),
+            Err(EditError::Unreachable(debian.clone()))
+        );
+        graph.remove_edge(&ubuntu, &BootMethod::Grub(7)).unwrap();
+        assert!(!graph.is_disabled(&ubuntu, &BootMethod::Grub(7)));
+        assert_eq!(
+            graph.remove_edge(&ubuntu, &BootMethod::Grub(7)),
+            Err(EditError::UnknownEdge)
+        );
+
+        // grub doesn't report manual edges, they aren't vanished
+        assert_eq!(graph.diff_entries(1, &[]), Some((vec![], vec![])));
+    }
+
+    #[test]
+    fn node() {
+        let mut graph = graph();
+        let (ubuntu, debian) = (OsStatus::Up(1), OsStatus::Up(2));
+
+        let windows = graph
+            .add_node("Windows".to_owned(), &debian, BootMethod::Grub(9))
+            .unwrap();
+        assert_eq!(windows, 3);
+        let windows = OsStatus::Up(windows);
+        assert_eq!(graph.plan(&OsStatus::Down, &windows).unwrap().len(), 3);
+        assert_eq!(
+            graph.plan(&windows, &OsStatus::Down).unwrap()[0].method,
+            BootMethod::Shutdown
+        );
+        // a failed add doesn't issue an id
+        assert!(graph
+            .add_node("Windows".to_owned(), &debian, BootMethod::Grub(9))
+            .is_err());
+        assert_eq!(graph.id_counter, 4);
+
+        // windows is only reached through debian
+        assert_eq!(
+            graph.remove_node(2),
+            Err(EditError::Unreachable(windows.clone()))
+        );
+        graph.remove_node(3).unwrap();
+        assert_eq!(graph.remove_node(3), Err(EditError::UnknownOs));
+        assert!(!graph.list_node().any(|os| *os == windows));
+        assert!(graph
+            .list_edge()
+            .all(|(from, _, to)| *from != windows && *to != windows));
+        graph.remove_node(2).unwrap();
+        assert_eq!(graph.list_node().count(), 2);
+        assert!(graph.plan(&ubuntu, &OsStatus::Down).is_ok());
+    }
+
+    #[test]
+    fn keep_manual() {
+        let mut origin = graph();
+        let (ubuntu, debian) = (OsStatus::Up(1), OsStatus::Up(2));
+        let windows = origin
+            .add_node("Windows".to_owned(), &debian, BootMethod::Grub(9))
+            .unwrap();
+        origin
+            .add_edge(&ubuntu, BootMethod::Grub(10), &OsStatus::Up(windows))
+            .unwrap();
+
+        // explored again, grub 9 now discovered leading to ubuntu
+        let mut renewed = graph();
+        let (from, to) = (
+            renewed.graph.find_node(&debian).unwrap(),
+            renewed.graph.find_node(&ubuntu).unwrap(),
+        );
+        renewed.graph.connect(from, to, BootMethod::Grub(9));
+        renewed.keep_manual(&origin);
+
+        let windows = OsStatus::Up(windows);
+        assert!(renewed.find_os(3).is_some());
+        assert_eq!(
+            renewed.plan(&debian, &windows).unwrap()[0].method,
+            BootMethod::Grub(9)
+        );
+        assert_eq!(
+            renewed.plan(&ubuntu, &windows).unwrap()[0].method,
+            BootMethod::Grub(10)
+        );
+        assert!(renewed.is_manual(&debian, &BootMethod::Grub(9)));
+        assert_eq!(renewed.id_counter, 4);
+    }
+}
@@ -1,13 +1,19 @@
 use indexmap::IndexMap;
 use serde;
-use std::{collections::VecDeque, fmt::Debug, hash::Hash};
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, VecDeque},
+    fmt::Debug,
+    hash::Hash,
+};
 
 #[derive(Clone, serde::Deserialize, serde::Serialize, Debug)]
 pub struct Graph<V, E>
 where
     V: Hash + Eq,
 {
-    edges: Vec<Vec<Edge<E>>>,
+    // indexed by node id, none once the node is removed so ids stay stable
+    edges: Vec<Option<Vec<Edge<E>>>>,
     values: IndexMap<V, usize>,
 }
 
@@ -30,92 +36,201 @@ where
             values: Default::default(),
         }
     }
-    pub fn update_node(&mut self, origin: &V, value: V) -> Option<V> {
-        let node = self.values.remove(origin);
-        if let Some(id) = node {
-            self.values.insert(value, id);
-            None
-        } else {
-            Some(value)
+    /// change the value of a node, `value` is given back if `origin` isn't a node
+    /// or if another node already has it
+    pub fn update_node(&mut self, origin: &V, value: V) -> Result<Node, V> {
+        if value != *origin && self.values.contains_key(&value) {
+            return Err(value);
+        }
+        match self.values.swap_remove(origin) {
+            Some(id) => {
+                self.values.insert(value, id);
+                Ok(Node(id))
+            }
+            None => Err(value),
         }
     }
     pub fn list_node(&self) -> impl Iterator<Item = &V> {
         self.values.iter().map(|(node, _)| node)
     }
+    pub fn list_edge(&self) -> impl Iterator<Item = (&V, &E, &V)> {
+        let values = self.values_by_id();
+        self.slots()
+            .flat_map(|(from, edges)| edges.iter().map(move |edge| (from, edge)))
+            .map(|(from, edge)| (values[from].unwrap(), &edge.value, values[edge.to].unwrap()))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+    /// edges leaving a node as (value, to), empty if the node was removed
+    pub fn edges(&self, from: &Node) -> impl Iterator<Item = (&E, Node)> {
+        self.slot(from)
+            .into_iter()
+            .flatten()
+            .map(|edge| (&edge.value, Node(edge.to)))
+    }
+    /// graphviz dot of the graph, labels are given by `node_label` and `edge_label`
+    pub fn to_dot<N, L>(&self, node_label: N, edge_label: L) -> String
+    where
+        N: Fn(&V) -> String,
+        L: Fn(&V, &E) -> String,
+    {
+        let values = self.values_by_id();
+        let mut dot = "digraph {\n".to_owned();
+        values.iter().enumerate().for_each(|(id, value)| {
+            if let Some(value) = value {
+                dot += &format!(
+                    "    n{} [label=\"{}\"];\n",
+                    id,
+                    escape_dot(&node_label(value))
+                );
+            }
+        });
+        self.slots().for_each(|(from, edges)| {
+            edges.iter().for_each(|edge| {
+                dot += &format!(
+                    "    n{} -> n{} [label=\"{}\"];\n",
+                    from,
+                    edge.to,
+                    escape_dot(&edge_label(values[from].unwrap(), &edge.value))
+                );
+            })
+        });
+        dot + "}\n"
+    }
+    // value of each node, indexed by node id, none if removed
+    fn values_by_id(&self) -> Vec<Option<&V>> {
+        let mut values: Vec<Option<&V>> = vec![None; self.edges.len()];
+        self.values
+            .iter()
+            .for_each(|(value, id)| values[*id] = Some(value));
+        values
+    }
+    // edges of every node not removed, with its id
+    fn slots(&self) -> impl Iterator<Item = (usize, &Vec<Edge<E>>)> {
+        self.edges
+            .iter()
+            .enumerate()
+            .filter_map(|(id, edges)| edges.as_ref().map(|edges| (id, edges)))
+    }
+    fn slot(&self, node: &Node) -> Option<&Vec<Edge<E>>> {
+        self.edges.get(node.0)?.as_ref()
+    }
+    fn slot_mut(&mut self, node: &Node) -> Option<&mut Vec<Edge<E>>> {
+        self.edges.get_mut(node.0)?.as_mut()
+    }
     pub fn find_node(&self, value: &V) -> Option<Node> {
-        let id = self.values.get(value)?.clone();
-        Some({
-            let id = id;
-            Node(id)
-        })
+        self.values.get(value).map(|id| Node(*id))
     }
     pub fn add_node(&mut self, value: V) -> Node {
         if let Some(node) = self.find_node(&value) {
             node
         } else {
-            let value = value;
-            let id = self.values.len();
+            // ids of removed nodes aren't reused, a stale node never point to another one
+            let id = self.edges.len();
             self.values.insert(value, id);
-            self.edges.push(vec![]);
-
-            let id = id;
+            self.edges.push(Some(vec![]));
             Node(id)
         }
     }
-    pub fn connect(&mut self, from: Node, to: Node, value: E) {
-        let parent = from.0;
-        let child = to.0;
-        let edge = Edge::new(child, value);
-        self.edges[parent].push(edge);
-    }
-    pub fn has_direct_edge(&mut self, from: Node, to: Node) -> bool {
-        let mut contain = false;
-        self.edges[from.0].iter().for_each(|edge| {
-            if edge.to == to.0 {
-                contain = true;
+    /// remove a node with every edge from and to it, returning its value
+    pub fn remove_node(&mut self, node: Node) -> Option<V> {
+        self.slot(&node)?;
+        let value = self
+            .values
+            .iter()
+            .position(|(_, id)| *id == node.0)
+            .and_then(|index| self.values.shift_remove_index(index))
+            .map(|(value, _)| value);
+        self.edges[node.0] = None;
+        self.edges
+            .iter_mut()
+            .flatten()
+            .for_each(|edges| edges.retain(|edge| edge.to != node.0));
+        value
+    }
+    /// false if either node was removed
+    pub fn connect(&mut self, from: Node, to: Node, value: E) -> bool {
+        if self.slot(&to).is_none() {
+            return false;
+        }
+        match self.slot_mut(&from) {
+            Some(edges) => {
+                edges.push(Edge::new(to.0, value));
+                true
             }
-        });
-        contain
+            None => false,
+        }
     }
-    pub fn bfs(&self, root: &Node) -> BFS<'_, V, E> {
-        let mut queue = VecDeque::new();
-        self.edges[root.0].iter().for_each(|edge| {
-            queue.push_back(edge);
-        });
-        BFS { graph: self, queue }
+    /// remove the first edge of `from` with the value, returning where it led
+    pub fn disconnect(&mut self, from: Node, value: &E) -> Option<Node>
+    where
+        E: PartialEq,
+    {
+        let edges = self.slot_mut(&from)?;
+        let index = edges.iter().position(|edge| edge.value == *value)?;
+        Some(Node(edges.remove(index).to))
     }
-    pub fn dfs(&self, root: &Node) -> DFS<'_, V, E> {
-        let mut stack = VecDeque::new();
-        self.edges[root.0].iter().for_each(|edge| {
-            stack.push_back(edge);
-        });
-        DFS { graph: self, stack }
+    /// where the edge of `from` with the value lead
+    pub fn follow(&self, from: &Node, value: &E) -> Option<Node>
+    where
+        E: PartialEq,
+    {
+        self.edges(from)
+            .find(|(edge, _)| **edge == *value)
+            .map(|(_, to)| to)
+    }
+    pub fn value(&self, node: &Node) -> Option<&V> {
+        self.values
+            .iter()
+            .find(|(_, id)| **id == node.0)
+            .map(|(value, _)| value)
     }
-    pub fn dijkstra<'a>(&'a self, from: &Node) -> Dijkstra<'a, E> {
+    pub fn has_direct_edge(&self, from: Node, to: Node) -> bool {
+        self.edges(&from).any(|(_, node)| node == to)
+    }
+    pub fn bfs(&self, root: &Node) -> Bfs<'_, V, E> {
+        let queue = self.slot(root).into_iter().flatten().collect();
+        Bfs { graph: self, queue }
+    }
+    pub fn dfs(&self, root: &Node) -> Dfs<'_, V, E> {
+        let stack = self.slot(root).into_iter().flatten().collect();
+        Dfs { graph: self, stack }
+    }
+    /// shortest path from a node, the cost of each edge is given by `cost`
+    pub fn dijkstra<'a, F>(&'a self, from: &Node, mut cost: F) -> Dijkstra<'a, E>
+    where
+        F: FnMut(&V, &E) -> usize,
+    {
         type NodeId = usize;
         type Distance = usize;
 
+        let values = self.values_by_id();
+
         let mut distance: Vec<Option<Distance>> = vec![None; self.edges.len()];
         let mut last_node: Vec<NodeId> = vec![from.0; self.edges.len()];
         let mut last_edge: Vec<Option<&E>> = vec![None; self.edges.len()];
-        let mut queue = VecDeque::new();
+        let mut queue = BinaryHeap::new();
 
-        distance[from.0] = Some(0);
-        queue.push_back(from.0);
+        if self.slot(from).is_some() {
+            distance[from.0] = Some(0);
+            queue.push(Reverse((0, from.0)));
+        }
 
-        while !queue.is_empty() {
-            let from_l = queue.pop_front().unwrap();
-            let from_d = distance[from_l].unwrap();
-            const COST: usize = 1;
+        while let Some(Reverse((from_d, from_l))) = queue.pop() {
+            if distance[from_l].is_some_and(|d| d < from_d) {
+                // stale entry, the node was reached by a cheaper path
+                continue;
+            }
+            let from_v = values[from_l].unwrap();
 
-            self.edges[from_l].iter().for_each(|edge| {
+            self.edges[from_l].iter().flatten().for_each(|edge| {
                 let edge_dist = edge.to;
-                let dist_d = distance[edge_dist].unwrap_or(usize::MAX);
-                if from_d < dist_d {
-                    distance[edge_dist] = Some(from_d + COST);
+                let new_d = from_d.saturating_add(cost(from_v, &edge.value));
+                if new_d < distance[edge_dist].unwrap_or(usize::MAX) {
+                    distance[edge_dist] = Some(new_d);
                     last_node[edge_dist] = from_l;
                     last_edge[edge_dist] = Some(&edge.value);
-                    queue.push_back(edge_dist);
+                    queue.push(Reverse((new_d, edge_dist)));
                 }
             })
         }
@@ -126,19 +241,22 @@ where
             last_edge,
         }
     }
-    pub fn transform_node<F, T>(self, mut f: F) -> Graph<T, E>
+    /// map the value of every node by `f`, none if `f` gives two nodes the same value
+    pub fn transform_node<F, T>(self, mut f: F) -> Option<Graph<T, E>>
     where
         F: FnMut(V) -> T,
         T: Hash + Eq,
     {
         let mut values = IndexMap::default();
-        self.values.into_iter().for_each(|(k, v)| {
-            values.insert(f(k), v);
-        });
-        Graph {
+        for (k, v) in self.values {
+            if values.insert(f(k), v).is_some() {
+                return None;
+            }
+        }
+        Some(Graph {
             edges: self.edges,
             values,
-        }
+        })
     }
 }
 
@@ -151,10 +269,10 @@ pub struct Dijkstra<'a, E> {
 
 impl<'a, E> Dijkstra<'a, E> {
     pub fn to(&self, dist: &Node) -> Option<usize> {
-        self.distance[dist.0]
+        *self.distance.get(dist.0)?
     }
     pub fn trace(&self, dist: &Node) -> Option<Vec<&E>> {
-        match self.distance[dist.0] {
+        match self.to(dist) {
             Some(_) => {
                 let mut trace: Vec<&E> = vec![];
                 let mut last = dist.0;
@@ -173,7 +291,7 @@ impl<'a, E> Dijkstra<'a, E> {
     }
 }
 
-pub struct BFS<'a, V, E>
+pub struct Bfs<'a, V, E>
 where
     V: Hash + Eq,
 {
@@ -181,7 +299,7 @@ where
     queue: VecDeque<&'a Edge<E>>,
 }
 
-impl<'a, V, E> Iterator for BFS<'a, V, E>
+impl<'a, V, E> Iterator for Bfs<'a, V, E>
 where
     V: Hash + Eq,
 {
@@ -190,9 +308,10 @@ where
     fn next(&mut self) -> Option<Self::Item> {
         if !self.queue.is_empty() {
             let current_edge = self.queue.pop_front().unwrap();
-            self.graph.edges[current_edge.to].iter().for_each(|edge| {
-                self.queue.push_back(edge);
-            });
+            self.graph.edges[current_edge.to]
+                .iter()
+                .flatten()
+                .for_each(|edge| self.queue.push_back(edge));
             Some((&current_edge.value, Node(current_edge.to)))
         } else {
             None
@@ -200,7 +319,7 @@ where
     }
 }
 
-pub struct DFS<'a, V, E>
+pub struct Dfs<'a, V, E>
 where
     V: Hash + Eq,
 {
@@ -208,7 +327,7 @@ where
     stack: VecDeque<&'a Edge<E>>,
 }
 
-impl<'a, V, E> Iterator for DFS<'a, V, E>
+impl<'a, V, E> Iterator for Dfs<'a, V, E>
 where
     V: Hash + Eq,
 {
@@ -217,9 +336,10 @@ where
     fn next(&mut self) -> Option<Self::Item> {
         if !self.stack.is_empty() {
             let current_edge = self.stack.pop_back().unwrap();
-            self.graph.edges[current_edge.to].iter().for_each(|edge| {
-                self.stack.push_back(edge);
-            });
+            self.graph.edges[current_edge.to]
+                .iter()
+                .flatten()
+                .for_each(|edge| self.stack.push_back(edge));
             Some((&current_edge.value, Node(current_edge.to)))
         } else {
             None
@@ -239,18 +359,25 @@ impl<E> Edge<E> {
     }
 }
 
+// quoted string of dot, line break is kept as a centered newline
+fn escape_dot(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// handle of a node, only made by its graph
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Node(usize);
 
-impl Node {
-    pub unsafe fn new(index: usize) -> Self {
-        Self(index)
-    }
-}
-
 #[cfg(test)]
 mod test {
-    use super::Graph;
+    use std::collections::{HashMap, HashSet};
+
+    use proptest::prelude::*;
+
+    use super::{Graph, Node};
 
     #[test]
     fn graph() {
@@ -261,6 +388,52 @@ mod test {
 
         assert_eq!(g.find_node(&"node a".to_string()), Some(a));
         assert_eq!(g.find_node(&"node b".to_string()), Some(b));
+        assert_eq!(
+            g.list_edge().collect::<Vec<_>>(),
+            vec![(
+                &"node a".to_string(),
+                &"edge a to b".to_string(),
+                &"node b".to_string()
+            )]
+        );
+    }
+    #[test]
+    fn dot() {
+        let mut g = Graph::new();
+        let a = g.add_node("a");
+        let b = g.add_node("say \"b\"");
+        g.connect(a, b, 1);
+        g.connect(b, b, 2);
+
+        let dot = g.to_dot(|v| v.to_string(), |from, e| format!("{}\n{}", from, e));
+        assert_eq!(
+            dot,
+            "digraph {\n\
+             \x20   n0 [label=\"a\"];\n\
+             \x20   n1 [label=\"say \\\"b\\\"\"];\n\
+             \x20   n0 -> n1 [label=\"a\\n1\"];\n\
+             \x20   n1 -> n1 [label=\"say \\\"b\\\"\\n2\"];\n\
+             }\n"
+        );
+    }
+    #[test]
+    fn reconnect() {
+        let mut g = Graph::new();
+        let a = g.add_node("node a");
+        let b = g.add_node("node b");
+        let c = g.add_node("node c");
+        g.connect(a, b, "edge 1");
+        g.connect(a, c, "edge 2");
+
+        assert_eq!(g.follow(&a, &"edge 1"), Some(b));
+        assert_eq!(g.value(&b), Some(&"node b"));
+
+        assert_eq!(g.disconnect(a, &"edge 1"), Some(b));
+        assert_eq!(g.disconnect(a, &"edge 1"), None);
+        assert_eq!(g.follow(&a, &"edge 1"), None);
+        g.connect(a, c, "edge 1");
+        assert_eq!(g.follow(&a, &"edge 1"), Some(c));
+        assert_eq!(g.dijkstra(&a, |_, _| 1).to(&b), None);
     }
     #[test]
     fn dijkstra() {
@@ -278,6 +451,245 @@ mod test {
         g.connect(c, a, "edge c to a");
         g.connect(c, c, "edge c to c");
 
-        assert_eq!(g.dijkstra(&c).trace(&a), Some(vec![&"edge c to a"]));
+        assert_eq!(
+            g.dijkstra(&c, |_, _| 1).trace(&a),
+            Some(vec![&"edge c to a"])
+        );
+    }
+    #[test]
+    fn dijkstra_weighted() {
+        // down -> grub -> windows is faster than a slow direct edge
+        let mut g = Graph::new();
+        let down = g.add_node("down");
+        let linux = g.add_node("linux");
+        let windows = g.add_node("windows");
+
+        g.connect(down, windows, ("wol", 120));
+        g.connect(down, linux, ("wol", 30));
+        g.connect(linux, windows, ("grub", 40));
+
+        let dijkstra = g.dijkstra(&down, |_, (_, cost)| *cost);
+        assert_eq!(dijkstra.to(&windows), Some(70));
+        assert_eq!(
+            dijkstra.trace(&windows),
+            Some(vec![&("wol", 30), &("grub", 40)])
+        );
+
+        // fewest hops win when every edge cost the same
+        let dijkstra = g.dijkstra(&down, |_, _| 1);
+        assert_eq!(dijkstra.trace(&windows), Some(vec![&("wol", 120)]));
+    }
+    #[test]
+    fn dijkstra_relax() {
+        // the first reached path to d is long, a later one must replace it
+        let mut g = Graph::new();
+        let a = g.add_node('a');
+        let b = g.add_node('b');
+        let c = g.add_node('c');
+        let d = g.add_node('d');
+        let e = g.add_node('e');
+
+        g.connect(a, d, 50);
+        g.connect(a, b, 10);
+        g.connect(b, c, 10);
+        g.connect(c, d, 10);
+        g.connect(d, e, 5);
+        g.connect(e, a, 1);
+
+        let dijkstra = g.dijkstra(&a, |_, cost| *cost);
+        assert_eq!(dijkstra.to(&d), Some(30));
+        assert_eq!(dijkstra.to(&e), Some(35));
+        assert_eq!(dijkstra.trace(&e), Some(vec![&10, &10, &10, &5]));
+        assert_eq!(dijkstra.trace(&a), Some(vec![]));
+    }
+    #[test]
+    fn dijkstra_cost_by_node() {
+        // cost may depend on the node an edge leave from
+        let mut g = Graph::new();
+        let a = g.add_node(1);
+        let b = g.add_node(100);
+        let c = g.add_node(2);
+        let d = g.add_node(3);
+
+        g.connect(a, b, ());
+        g.connect(b, d, ());
+        g.connect(a, c, ());
+        g.connect(c, d, ());
+
+        let dijkstra = g.dijkstra(&a, |from, _| *from);
+        assert_eq!(dijkstra.to(&d), Some(3));
+        assert_eq!(dijkstra.to(&b), Some(1));
+        assert_eq!(dijkstra.to(&c), Some(1));
+    }
+    #[test]
+    fn remove() {
+        let mut g = Graph::new();
+        let a = g.add_node("a");
+        let b = g.add_node("b");
+        let c = g.add_node("c");
+        g.connect(a, b, 1);
+        g.connect(b, c, 2);
+        g.connect(a, c, 3);
+
+        assert_eq!(g.remove_node(b), Some("b"));
+        assert_eq!(g.remove_node(b), None);
+        assert_eq!(g.find_node(&"b"), None);
+        assert_eq!(g.edges(&a).collect::<Vec<_>>(), vec![(&3, c)]);
+        assert_eq!(g.list_edge().collect::<Vec<_>>(), vec![(&"a", &3, &"c")]);
+        // ids are stable, a stale node point nowhere
+        assert_eq!(g.find_node(&"c"), Some(c));
+        assert_eq!(g.edges(&b).count(), 0);
+        assert_eq!(g.value(&b), None);
+        assert!(!g.connect(a, b, 4));
+        assert_eq!(g.dijkstra(&a, |_, _| 1).to(&b), None);
+        assert_eq!(g.dijkstra(&b, |_, _| 1).to(&c), None);
+        let b2 = g.add_node("b");
+        assert_ne!(b2, b);
+        assert_eq!(g.dijkstra(&a, |_, _| 1).to(&b2), None);
+        assert!(!g
+            .to_dot(|v| v.to_string(), |_, e| e.to_string())
+            .contains("n1"));
+    }
+
+    #[derive(Debug, Clone)]
+    enum Op {
+        Add(u8),
+        Remove(u8),
+        Connect(u8, u8, u8),
+        Disconnect(u8, u8),
+        Update(u8, u8),
+        // map every node by (v + k) % 6, or by v / 2 if none, which may collide
+        Transform(Option<u8>),
+    }
+
+    fn op() -> impl Strategy<Value = Op> {
+        // few values, so ops often hit existing nodes
+        prop_oneof![
+            (0..6u8).prop_map(Op::Add),
+            (0..6u8).prop_map(Op::Remove),
+            (0..6u8, 0..6u8, 0..3u8).prop_map(|(a, b, e)| Op::Connect(a, b, e)),
+            (0..6u8, 0..3u8).prop_map(|(a, e)| Op::Disconnect(a, e)),
+            (0..6u8, 0..6u8).prop_map(|(a, b)| Op::Update(a, b)),
+            proptest::option::of(0..6u8).prop_map(Op::Transform),
+        ]
+    }
+
+    proptest! {
+        // checked against a naive model after every op
+        #[test]
+        fn invariant(ops in proptest::collection::vec(op(), 0..64)) {
+            let mut g: Graph<u8, u8> = Graph::new();
+            let mut nodes: HashMap<u8, Node> = HashMap::new();
+            let mut removed: Vec<Node> = Vec::new();
+            let mut edges: Vec<(u8, u8, u8)> = Vec::new();
+
+            for op in ops {
+                match op {
+                    Op::Add(v) => {
+                        let node = g.add_node(v);
+                        prop_assert_eq!(*nodes.entry(v).or_insert(node), node);
+                        prop_assert!(!removed.contains(&node));
+                    }
+                    Op::Remove(v) => {
+                        let res = nodes.get(&v).and_then(|node| g.remove_node(*node));
+                        prop_assert_eq!(res, nodes.get(&v).map(|_| v));
+                        if let Some(node) = nodes.remove(&v) {
+                            removed.push(node);
+                            edges.retain(|(from, _, to)| *from != v && *to != v);
+                        }
+                    }
+                    Op::Connect(a, b, e) => {
+                        let connected = match (nodes.get(&a), nodes.get(&b)) {
+                            (Some(from), Some(to)) => g.connect(*from, *to, e),
+                            _ => false,
+                        };
+                        prop_assert_eq!(connected, nodes.contains_key(&a) && nodes.contains_key(&b));
+                        if connected {
+                            edges.push((a, e, b));
+                        }
+                    }
+                    Op::Disconnect(a, e) => {
+                        let to = nodes.get(&a).and_then(|node| g.disconnect(*node, &e));
+                        let index = edges.iter().position(|(from, x, _)| *from == a && *x == e);
+                        prop_assert_eq!(to.and_then(|to| g.value(&to).copied()), index.map(|i| edges[i].2));
+                        if let Some(index) = index {
+                            edges.remove(index);
+                        }
+                    }
+                    Op::Update(a, b) => {
+                        let res = g.update_node(&a, b);
+                        let valid = nodes.contains_key(&a) && (a == b || !nodes.contains_key(&b));
+                        prop_assert_eq!(res.ok(), valid.then(|| nodes[&a]));
+                        if valid {
+                            let node = nodes.remove(&a).unwrap();
+                            nodes.insert(b, node);
+                            edges.iter_mut().for_each(|(from, _, to)| {
+                                if *from == a {
+                                    *from = b;
+                                }
+                                if *to == a {
+                                    *to = b;
+                                }
+                            });
+                        }
+                    }
+                    Op::Transform(k) => {
+                        let f = |v: u8| match k {
+                            Some(k) => (v + k) % 6,
+                            None => v / 2,
+                        };
+                        let mapped: HashSet<u8> = nodes.keys().map(|v| f(*v)).collect();
+                        let injective = mapped.len() == nodes.len();
+                        let res = g.clone().transform_node(f);
+                        prop_assert_eq!(res.is_some(), injective);
+                        if let Some(res) = res {
+                            g = res;
+                            nodes = nodes.into_iter().map(|(v, node)| (f(v), node)).collect();
+                            edges.iter_mut().for_each(|(from, _, to)| (*from, *to) = (f(*from), f(*to)));
+                        }
+                    }
+                }
+
+                let mut listed: Vec<(u8, u8, u8)> = g.list_edge().map(|(a, e, b)| (*a, *e, *b)).collect();
+                let mut expected = edges.clone();
+                listed.sort();
+                expected.sort();
+                prop_assert_eq!(listed, expected);
+                prop_assert_eq!(g.list_node().copied().collect::<HashSet<_>>(), nodes.keys().copied().collect::<HashSet<_>>());
+                for (v, node) in &nodes {
+                    prop_assert_eq!(g.find_node(v), Some(*node));
+                    prop_assert_eq!(g.value(node), Some(v));
+                    let mut out: Vec<(u8, u8)> = g.edges(node).map(|(e, to)| (*e, *g.value(&to).unwrap())).collect();
+                    let mut expected: Vec<(u8, u8)> = edges.iter().filter(|(from, _, _)| from == v).map(|(_, e, to)| (*e, *to)).collect();
+                    out.sort();
+                    expected.sort();
+                    prop_assert_eq!(out, expected);
+                }
+                for node in &removed {
+                    prop_assert_eq!(g.value(node), None);
+                    prop_assert_eq!(g.edges(node).count(), 0);
+                }
+            }
+
+            // reachability by dijkstra match the model
+            for (v, node) in &nodes {
+                let dijkstra = g.dijkstra(node, |_, _| 1);
+                let mut reached = HashSet::from([*v]);
+                let mut queue = vec![*v];
+                while let Some(from) = queue.pop() {
+                    for (_, _, to) in edges.iter().filter(|(x, _, _)| *x == from) {
+                        if reached.insert(*to) {
+                            queue.push(*to);
+                        }
+                    }
+                }
+                for (w, other) in &nodes {
+                    prop_assert_eq!(dijkstra.to(other).is_some(), reached.contains(w));
+                }
+                for node in &removed {
+                    prop_assert_eq!(dijkstra.to(node), None);
+                }
+            }
+        }
     }
 }
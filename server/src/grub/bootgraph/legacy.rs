@@ -0,0 +1,84 @@
+// boot graph as saved before the save file was versioned, only read to migrate it
+
+use indexmap::IndexMap;
+use proto::prelude::{GrubId, ID};
+use serde::Deserialize;
+
+use super::{BootGraph, BootMethod, OsInfo, OsStatus};
+
+// variants were reordered since, so they can't be read as the current BootMethod
+#[derive(Deserialize)]
+enum BootMethodV0 {
+    Wol,
+    Grub(GrubId),
+    Shutdown,
+}
+
+impl From<BootMethodV0> for BootMethod {
+    fn from(method: BootMethodV0) -> Self {
+        match method {
+            BootMethodV0::Wol => BootMethod::Wol,
+            BootMethodV0::Grub(id) => BootMethod::Grub(id),
+            BootMethodV0::Shutdown => BootMethod::Shutdown,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct EdgeV0 {
+    to: usize,
+    value: BootMethodV0,
+}
+
+// maps are read as the vecs of pairs bincode lays them out as, which don't trust
+// the length read for their capacity
+#[derive(Deserialize)]
+struct GraphV0 {
+    edges: Vec<Vec<EdgeV0>>,
+    values: Vec<(OsStatus, usize)>,
+}
+
+#[derive(Deserialize)]
+struct OsInfoV0 {
+    display_name: String,
+}
+
+#[derive(Deserialize)]
+pub struct BootGraphV0 {
+    graph: GraphV0,
+    os: Vec<(ID, OsInfoV0)>,
+    id_counter: ID,
+}
+
+impl From<BootGraphV0> for BootGraph {
+    fn from(old: BootGraphV0) -> Self {
+        let mut boot_graph = BootGraph::default();
+        boot_graph.id_counter = old.id_counter;
+        let mut values = old.graph.values;
+        values.sort_by_key(|(_, id)| *id);
+        let nodes: IndexMap<usize, _> = values
+            .into_iter()
+            .map(|(value, id)| (id, boot_graph.graph.add_node(value)))
+            .collect();
+        for (from, edges) in old.graph.edges.into_iter().enumerate() {
+            for edge in edges {
+                // an edge of a node without value was unreachable anyway
+                if let (Some(from), Some(to)) = (nodes.get(&from), nodes.get(&edge.to)) {
+                    boot_graph.graph.connect(*from, *to, edge.value.into());
+                }
+            }
+        }
+        boot_graph.os = old
+            .os
+            .into_iter()
+            .map(|(id, info)| {
+                let info = OsInfo {
+                    display_name: info.display_name,
+                    ..Default::default()
+                };
+                (id, info)
+            })
+            .collect();
+        boot_graph
+    }
+}
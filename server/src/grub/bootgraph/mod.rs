@@ -1,4 +1,9 @@
 mod boot;
+mod edit;
 mod graph;
+mod legacy;
+mod stats;
 
 pub use boot::*;
+pub use edit::EditError;
+pub use legacy::BootGraphV0;
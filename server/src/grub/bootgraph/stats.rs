@@ -0,0 +1,350 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::boot::{BootMethod, OsStatus};
+
+// an edge is identified by the os it leave from and its boot method
+type EdgeKey = (OsStatus, BootMethod);
+
+// only recent boots are kept, firmware update may change the timing
+const WINDOW: usize = 64;
+
+#[derive(Clone, Default, Deserialize, Serialize, Debug, PartialEq)]
+pub struct Measure {
+    samples: VecDeque<Duration>,
+    failures: u32,
+    // landed in another os than the graph expected
+    misroutes: u32,
+}
+
+impl Measure {
+    pub fn record(&mut self, elapsed: Duration) {
+        if self.samples.len() == WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(elapsed);
+    }
+    pub fn fail(&mut self) {
+        self.failures += 1;
+    }
+    pub fn misroute(&mut self) {
+        self.misroutes += 1;
+    }
+    pub fn misroutes(&self) -> u32 {
+        self.misroutes
+    }
+    pub fn samples(&self) -> usize {
+        self.samples.len()
+    }
+    pub fn failures(&self) -> u32 {
+        self.failures
+    }
+    pub fn min(&self) -> Option<Duration> {
+        self.samples.iter().min().copied()
+    }
+    pub fn median(&self) -> Option<Duration> {
+        self.percentile(50)
+    }
+    pub fn p95(&self) -> Option<Duration> {
+        self.percentile(95)
+    }
+    // nearest-rank percentile
+    fn percentile(&self, p: usize) -> Option<Duration> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<Duration> = self.samples.iter().copied().collect();
+        sorted.sort();
+        let rank = (p * sorted.len()).div_ceil(100).max(1);
+        Some(sorted[rank - 1])
+    }
+}
+
+// an edge failing this many times in a row is avoided for a while
+const DEGRADE_AFTER: u32 = 2;
+const DEGRADE_FOR: Duration = Duration::from_secs(15 * 60);
+// added to the cost of a degraded edge, so it's only taken if there is no other way
+const DEGRADE_PENALTY: Duration = Duration::from_secs(60 * 60);
+// an edge landing in the same unexpected os this many times in a row is re-learned
+const RELEARN_AFTER: u32 = 2;
+
+#[derive(Clone, Default, Debug)]
+struct Inner {
+    measures: HashMap<EdgeKey, Measure>,
+    // consecutive failures and the last one, not persisted
+    streaks: HashMap<EdgeKey, (u32, Instant)>,
+    // where a misrouted edge landed and how many times in a row, not persisted
+    landings: HashMap<EdgeKey, (OsStatus, u32)>,
+}
+
+/// measured duration and reliability of every edge of a boot graph
+///
+/// recorded while booting, which only borrow the graph, so it lock by itself
+#[derive(Default, Debug)]
+pub struct EdgeStats {
+    inner: Mutex<Inner>,
+}
+
+impl EdgeStats {
+    pub fn record(&self, from: &OsStatus, method: &BootMethod, elapsed: Duration) {
+        let key = (from.clone(), method.clone());
+        let mut inner = self.inner.lock().unwrap();
+        inner.streaks.remove(&key);
+        inner.measures.entry(key).or_default().record(elapsed);
+    }
+    pub fn fail(&self, from: &OsStatus, method: &BootMethod) {
+        let key = (from.clone(), method.clone());
+        let mut inner = self.inner.lock().unwrap();
+        let streak = inner
+            .streaks
+            .entry(key.clone())
+            .or_insert((0, Instant::now()));
+        *streak = (streak.0 + 1, Instant::now());
+        if streak.0 == DEGRADE_AFTER {
+            log::warn!("edge {:?} from {:?} is degraded", method, from);
+        }
+        inner.measures.entry(key).or_default().fail();
+    }
+    /// the edge led to `landed`, which the graph didn't expect
+    pub fn misroute(&self, from: &OsStatus, method: &BootMethod, landed: &OsStatus) {
+        let key = (from.clone(), method.clone());
+        let mut inner = self.inner.lock().unwrap();
+        let landing = inner
+            .landings
+            .entry(key.clone())
+            .or_insert((landed.clone(), 0));
+        if landing.0 != *landed {
+            *landing = (landed.clone(), 0);
+        }
+        landing.1 += 1;
+        inner.measures.entry(key).or_default().misroute();
+    }
+    /// the edge led where the graph expected
+    pub fn route(&self, from: &OsStatus, method: &BootMethod) {
+        self.inner
+            .lock()
+            .unwrap()
+            .landings
+            .remove(&(from.clone(), method.clone()));
+    }
+    /// edges keep landing in the same unexpected os, as (from, method, landed)
+    pub fn take_relearn(&self) -> Vec<(OsStatus, BootMethod, OsStatus)> {
+        let mut inner = self.inner.lock().unwrap();
+        let keys: Vec<EdgeKey> = inner
+            .landings
+            .iter()
+            .filter(|(_, (_, count))| *count >= RELEARN_AFTER)
+            .map(|(key, _)| key.clone())
+            .collect();
+        keys.into_iter()
+            .map(|key| {
+                let (landed, _) = inner.landings.remove(&key).unwrap();
+                (key.0, key.1, landed)
+            })
+            .collect()
+    }
+    /// drop everything recorded about an edge, which now lead elsewhere
+    pub fn forget(&self, from: &OsStatus, method: &BootMethod) {
+        let key = (from.clone(), method.clone());
+        let mut inner = self.inner.lock().unwrap();
+        inner.measures.remove(&key);
+        inner.streaks.remove(&key);
+        inner.landings.remove(&key);
+    }
+    pub fn get(&self, from: &OsStatus, method: &BootMethod) -> Option<Measure> {
+        self.inner
+            .lock()
+            .unwrap()
+            .measures
+            .get(&(from.clone(), method.clone()))
+            .cloned()
+    }
+    /// whether an edge failed repeatedly in recent
+    pub fn is_degraded(&self, from: &OsStatus, method: &BootMethod) -> bool {
+        self.inner
+            .lock()
+            .unwrap()
+            .streaks
+            .get(&(from.clone(), method.clone()))
+            .is_some_and(|(streak, last)| *streak >= DEGRADE_AFTER && last.elapsed() < DEGRADE_FOR)
+    }
+    /// expected duration of an edge, a default by boot method if never measured
+    pub fn estimate(&self, from: &OsStatus, method: &BootMethod) -> Duration {
+        self.get(from, method)
+            .and_then(|measure| measure.median())
+            .unwrap_or_else(|| method.default_duration())
+    }
+    /// weight of an edge when planning a path
+    pub fn cost(&self, from: &OsStatus, method: &BootMethod) -> Duration {
+        if self.is_degraded(from, method) {
+            self.estimate(from, method) + DEGRADE_PENALTY
+        } else {
+            self.estimate(from, method)
+        }
+    }
+}
+
+impl Clone for EdgeStats {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Mutex::new(self.inner.lock().unwrap().clone()),
+        }
+    }
+}
+
+impl Serialize for EdgeStats {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.inner.lock().unwrap().measures.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for EdgeStats {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self {
+            inner: Mutex::new(Inner {
+                measures: HashMap::deserialize(deserializer)?,
+                streaks: HashMap::new(),
+                landings: HashMap::new(),
+            }),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::{EdgeStats, Measure, DEGRADE_PENALTY, WINDOW};
+    use crate::grub::bootgraph::{BootMethod, OsStatus};
+
+    fn secs(x: u64) -> Duration {
+        Duration::from_secs(x)
+    }
+
+    #[test]
+    fn percentile() {
+        let mut measure = Measure::default();
+        assert_eq!(measure.min(), None);
+        assert_eq!(measure.median(), None);
+        assert_eq!(measure.p95(), None);
+
+        measure.record(secs(10));
+        assert_eq!(measure.median(), Some(secs(10)));
+        assert_eq!(measure.p95(), Some(secs(10)));
+
+        (1..=20).rev().for_each(|x| measure.record(secs(x)));
+        // 1..=20 plus another 10
+        assert_eq!(measure.samples(), 21);
+        assert_eq!(measure.min(), Some(secs(1)));
+        assert_eq!(measure.median(), Some(secs(10)));
+        assert_eq!(measure.p95(), Some(secs(19)));
+
+        measure.fail();
+        assert_eq!(measure.failures(), 1);
+        assert_eq!(measure.samples(), 21);
+    }
+
+    #[test]
+    fn window() {
+        let mut measure = Measure::default();
+        (0..WINDOW as u64).for_each(|_| measure.record(secs(100)));
+        (0..WINDOW as u64).for_each(|_| measure.record(secs(30)));
+        assert_eq!(measure.samples(), WINDOW);
+        assert_eq!(measure.p95(), Some(secs(30)));
+    }
+
+    #[test]
+    fn estimate() {
+        let stats = EdgeStats::default();
+        let from = OsStatus::Up(1);
+
+        assert_eq!(
+            stats.estimate(&from, &BootMethod::Grub(2)),
+            BootMethod::Grub(2).default_duration()
+        );
+
+        stats.record(&from, &BootMethod::Grub(2), secs(20));
+        stats.record(&from, &BootMethod::Grub(2), secs(90));
+        stats.record(&from, &BootMethod::Grub(2), secs(30));
+        stats.fail(&from, &BootMethod::Grub(2));
+        assert_eq!(stats.estimate(&from, &BootMethod::Grub(2)), secs(30));
+        // measured per edge, not per method
+        assert_eq!(
+            stats.estimate(&OsStatus::Up(3), &BootMethod::Grub(2)),
+            BootMethod::Grub(2).default_duration()
+        );
+        // failure alone doesn't change the estimate
+        stats.fail(&from, &BootMethod::Shutdown);
+        assert_eq!(
+            stats.estimate(&from, &BootMethod::Shutdown),
+            BootMethod::Shutdown.default_duration()
+        );
+
+        let stats: EdgeStats = bincode::deserialize(&bincode::serialize(&stats).unwrap()).unwrap();
+        let measure = stats.get(&from, &BootMethod::Grub(2)).unwrap();
+        assert_eq!(measure.median(), Some(secs(30)));
+        assert_eq!(measure.failures(), 1);
+    }
+
+    #[test]
+    fn degrade() {
+        let stats = EdgeStats::default();
+        let from = OsStatus::Up(1);
+        let grub = BootMethod::Grub(2);
+        stats.record(&from, &grub, secs(20));
+
+        stats.fail(&from, &grub);
+        assert!(!stats.is_degraded(&from, &grub));
+        assert_eq!(stats.cost(&from, &grub), secs(20));
+
+        stats.fail(&from, &grub);
+        assert!(stats.is_degraded(&from, &grub));
+        assert_eq!(stats.cost(&from, &grub), secs(20) + DEGRADE_PENALTY);
+        // the estimate is still the real duration
+        assert_eq!(stats.estimate(&from, &grub), secs(20));
+        assert!(!stats.is_degraded(&OsStatus::Up(3), &grub));
+
+        // a success clear the streak
+        stats.record(&from, &grub, secs(20));
+        assert!(!stats.is_degraded(&from, &grub));
+        stats.fail(&from, &grub);
+        assert!(!stats.is_degraded(&from, &grub));
+        assert_eq!(stats.get(&from, &grub).unwrap().failures(), 3);
+
+        // degradation isn't persisted
+        stats.fail(&from, &grub);
+        assert!(stats.is_degraded(&from, &grub));
+        let stats: EdgeStats = bincode::deserialize(&bincode::serialize(&stats).unwrap()).unwrap();
+        assert!(!stats.is_degraded(&from, &grub));
+    }
+
+    #[test]
+    fn relearn() {
+        let stats = EdgeStats::default();
+        let from = OsStatus::Up(1);
+        let grub = BootMethod::Grub(2);
+
+        stats.misroute(&from, &grub, &OsStatus::Up(3));
+        assert!(stats.take_relearn().is_empty());
+        // landing where expected start over
+        stats.route(&from, &grub);
+        stats.misroute(&from, &grub, &OsStatus::Up(3));
+        assert!(stats.take_relearn().is_empty());
+        // so does landing in yet another os
+        stats.misroute(&from, &grub, &OsStatus::Up(4));
+        assert!(stats.take_relearn().is_empty());
+
+        stats.misroute(&from, &grub, &OsStatus::Up(4));
+        assert_eq!(
+            stats.take_relearn(),
+            vec![(from.clone(), grub.clone(), OsStatus::Up(4))]
+        );
+        assert!(stats.take_relearn().is_empty());
+        assert_eq!(stats.get(&from, &grub).unwrap().misroutes(), 4);
+    }
+}
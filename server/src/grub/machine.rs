@@ -1,14 +1,16 @@
-use super::packet::{self, TcpPacket, TcpPackets};
+use super::audit::Audit;
+use super::packet::{self, BmcConfig, BmcError, Escalation, EscalationAction, PlugConfig, PlugError, ProbeConfig, TcpPacket, TcpPackets, TcpRelay, TcpWaker, WolConfig, WolError};
 use super::{adaptor, api};
 use async_std::future::timeout;
 use async_std::{net, process};
-use async_std::sync::Mutex;
+use async_std::sync::{Mutex, RwLock};
 use async_std::task::spawn;
 
 use super::bootgraph::{self, *};
-use super::serde::{AsyncState, ServerSave};
+use super::serde::{AsyncState, SaveError, ServerSave};
 
 use indexmap::IndexMap;
+use proto::discovery;
 use proto::prelude as protocal;
 use core::time;
 use std::net::SocketAddr;
@@ -17,11 +19,45 @@ use std::sync::Arc;
 use std::{collections::*, io};
 
 lazy_static! {
-    static ref SAVE_PATH: &'static Path = Path::new("./");
+    pub(crate) static ref SAVE_PATH: &'static Path = Path::new("./grub-wol.sav");
+    // progress of unfinished enrollments, one file per machine
+    pub(crate) static ref CHECKPOINT_DIR: &'static Path = Path::new("./grub-wol.enroll");
 }
 
 type MacAddress = [u8; 6];
 
+// magic packets sent to a standalone machine without probe, which can't tell whether it woke
+const BLIND_WAKE: usize = 3;
+
+fn hex_mac(mac: &MacAddress) -> String {
+    mac.iter().map(|x| format!("{:02x}", x)).collect()
+}
+
+// mdns instance of the server, servers on one network are told apart by hostname
+fn instance_name() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_owned())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "grub-wol".to_owned())
+}
+
+// os id of the connected os with its grub and firmware entries
+async fn query_entries(packet: &TcpPacket) -> Result<(protocal::ID, Vec<BootMethod>), packet::Error> {
+    let uid = packet.get_uid().await?;
+    packet.write_grub_query().await?;
+    let grub = packet.read_grub_query().await?;
+    packet.write_uefi_query().await?;
+    let uefi = packet.read_uefi_query().await?;
+    let entries = grub
+        .into_iter()
+        .map(|info| BootMethod::Grub(info.grub_sec))
+        .chain(uefi.into_iter().map(|info| BootMethod::Uefi(info.boot_num)))
+        .collect();
+    Ok((uid, entries))
+}
+
 pub(super) struct RingBuffer<T, const SIZE: usize>
 where
     T: Sized,
@@ -92,11 +128,11 @@ impl Server {
     }
     pub async fn save(&self) -> Result<(), Error> {
         log::info!("Backing up Grub server");
-        ServerSave::save(&self, &SAVE_PATH).await;
+        ServerSave::save(self, &SAVE_PATH).await?;
         Ok(())
     }
     pub async fn load(path: &Path) -> Result<Server, Error> {
-        Ok(ServerSave::load(path).await)
+        Ok(ServerSave::load(path).await?)
     }
     pub async fn start(self_: Arc<Self>) {
         log::info!("Creating autosave thread");
@@ -106,7 +142,8 @@ impl Server {
             let self_=self_c.clone();
             spawn(async move{
                 match timeout(time::Duration::from_secs(16), self_.save()).await{
-                    Ok(x) => x.unwrap(),
+                    Ok(Ok(())) => {}
+                    Ok(Err(err)) => log::error!("Fail saving server file {}", err),
                     Err(err) => log::error!("Timeout saving server file {:?}",err),
                 };
                 process::exit(1);
@@ -114,6 +151,14 @@ impl Server {
         }).expect("cannot recieve sigterm");
         log::info!("Starting Grub server");
         let listener = net::TcpListener::bind(self_.socket).await.unwrap();
+        // agents find the server by itself, kept announced as long as the server runs
+        let _announcement = match discovery::announce(&instance_name(), self_.socket.port()) {
+            Ok(announcement) => Some(announcement),
+            Err(err) => {
+                log::warn!("fail to announce server by mdns: {}", err);
+                None
+            }
+        };
         loop {
             let (stream, socket) = listener.accept().await.unwrap();
             log::debug!(
@@ -128,18 +173,53 @@ impl Server {
             };
         }
     }
-    async fn connect_tcp(&self, stream: net::TcpStream) -> Result<(), Error> {
-        match self.packets.connect(stream).await? {
-            Some(packet) => {
-                self.connect_packet(packet).await?;
-            },
-            None => {
+    async fn connect_tcp(self: &Arc<Self>, stream: net::TcpStream) -> Result<(), Error> {
+        if let Some(packet) = self.packets.connect(stream).await? {
+            self.accept_packet(packet).await?;
+        }
+        Ok(())
+    }
+    // a host connected by itself, its grub entries are checked before the machine get the connection
+    async fn accept_packet(self: &Arc<Self>, mut packet: TcpPacket) -> Result<(), Error> {
+        match self.get_machine(packet.get_mac_address()).await {
+            Some(machine) if machine.standalone => {
+                log::warn!(
+                    "agent connected as standalone machine of mac address({:x?}), ignored",
+                    packet.get_mac_address()
+                );
+                Ok(())
             }
-        };
-        return Ok(());
+            Some(machine) => {
+                // refreshing may wake or reset the host, as the machine would
+                machine.configure(&mut packet).await;
+                packet.set_relay(self.relay_of(&machine).await);
+                let server = self.clone();
+                spawn(async move {
+                    match machine.refresh(packet).await {
+                        Ok((packet, changed)) => {
+                            machine.connect(packet).await;
+                            if changed {
+                                if let Err(err) = server.save().await {
+                                    log::error!("Fail saving server file {}", err);
+                                }
+                            }
+                        }
+                        Err(interrupted) => {
+                            log::warn!("{:?}", interrupted.error);
+                            // the graph is kept as it was, reconnecting would only refresh again
+                            if interrupted.packet.get_uid().await.is_ok() {
+                                machine.connect(interrupted.packet).await;
+                            }
+                        }
+                    }
+                });
+                Ok(())
+            }
+            None => self.connect_packet(packet).await,
+        }
     }
     async fn connect_packet(&self, packet: TcpPacket) -> Result<(), Error> {
-        let mac_address = packet.get_mac_address().clone();
+        let mac_address = *packet.get_mac_address();
         if let Some(machine) = self.machines.lock().await.get_mut(&mac_address) {
             machine.connect(packet).await;
         } else {
@@ -167,12 +247,287 @@ impl Server {
             Ok(false)
         }
     }
+    /// false if the mac address is taken by a machine or an unknown host
+    pub(super) async fn new_standalone(
+        &self,
+        mac: MacAddress,
+        display_name: String,
+        wol: WolConfig,
+        probe: Option<ProbeConfig>,
+    ) -> Result<bool, Error> {
+        let unknown = self
+            .unknown_packet
+            .lock()
+            .await
+            .iter()
+            .any(|packet| *packet.get_mac_address() == mac);
+        let mut machines = self.machines.lock().await;
+        if unknown || machines.contains_key(&mac) {
+            return Ok(false);
+        }
+        log::info!("registered standalone machine {} of mac address({:x?})", display_name, mac);
+        machines.insert(mac, Arc::new(Machine::standalone(mac, display_name, wol, probe)));
+        drop(machines);
+        self.save().await?;
+        Ok(true)
+    }
+    pub(super) async fn edit_probe(&self, mac: &MacAddress, probe: Option<ProbeConfig>) -> Result<bool, Error> {
+        match self.get_machine(mac).await {
+            Some(machine) => {
+                machine.set_probe(probe).await;
+                log::info!("edited probe of mac address({:x?})", mac);
+                self.save().await?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+    pub(super) async fn rename_machine(
+        &self,
+        mac: &MacAddress,
+        display_name: String,
+    ) -> Result<bool, Error> {
+        match self.get_machine(mac).await {
+            Some(machine) => {
+                log::info!("renaming machine of mac address({:x?}) to {}", mac, display_name);
+                *machine.display_name.lock().await = display_name;
+                self.save().await?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+    pub(super) async fn forget_machine(&self, mac: &MacAddress) -> Result<bool, Error> {
+        let machine = self.machines.lock().await.shift_remove(mac);
+        match machine {
+            Some(machine) => {
+                log::info!("forgetting machine of mac address({:x?})", mac);
+                // let the host show up as unknown again
+                if let Some(packet) = machine.packet.lock().await.take() {
+                    self.unknown_packet.lock().await.push(packet);
+                }
+                self.save().await?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+    pub(super) async fn renew_machine(&self, mac: &MacAddress) -> Result<bool, Error> {
+        let machine = match self.get_machine(mac).await {
+            Some(machine) => machine,
+            None => return Ok(false),
+        };
+        let mut packet = match machine.packet.lock().await.take() {
+            Some(packet) => packet,
+            None => return Ok(false),
+        };
+        packet.set_relay(self.relay_of(&machine).await);
+        log::debug!("rediscovering machine of mac address({:x?})", mac);
+
+        let (renewed, packet) = match machine.renew(packet).await {
+            Ok(renewed) => renewed,
+            Err(interrupted) => {
+                // the old graph is kept, so is the connection if the host still answer
+                if interrupted.packet.get_uid().await.is_ok() {
+                    machine.connect(interrupted.packet).await;
+                }
+                return Err(interrupted.error.into());
+            }
+        };
+        self.machines.lock().await.insert(*mac, Arc::new(renewed));
+        self.connect_packet(packet).await?;
+        self.save().await?;
+        Ok(true)
+    }
+    /// none if the machine or the os is unknown
+    pub(super) async fn edit_os(
+        &self,
+        mac: &MacAddress,
+        os: protocal::ID,
+        label: OsLabel,
+    ) -> Result<Option<Result<(), LabelError>>, Error> {
+        let machine = match self.get_machine(mac).await {
+            Some(machine) => machine,
+            None => return Ok(None),
+        };
+        if let Err(err) = label.validate() {
+            return Ok(Some(Err(err)));
+        }
+        if !machine.edit_os(os, label).await {
+            return Ok(None);
+        }
+        log::info!("edited label of os {} on mac address({:x?})", os, mac);
+        self.save().await?;
+        Ok(Some(Ok(())))
+    }
+    /// none if the machine is unknown, the id of an added os if any
+    pub(super) async fn edit_graph(
+        &self,
+        mac: &MacAddress,
+        edit: api::GraphEdit,
+    ) -> Result<Option<Result<Option<protocal::ID>, EditError>>, Error> {
+        let machine = match self.get_machine(mac).await {
+            Some(machine) => machine,
+            None => return Ok(None),
+        };
+        let res = machine.edit_graph(edit).await;
+        if res.is_ok() {
+            log::info!("edited boot graph of mac address({:x?})", mac);
+            self.save().await?;
+        }
+        Ok(Some(res))
+    }
+    /// none if the machine is unknown
+    ///
+    /// a password left out keeps the current one, the relay can't be cleared if the escalation use it
+    pub(super) async fn edit_wol(
+        &self,
+        mac: &MacAddress,
+        settings: api::WolSettings,
+    ) -> Result<Option<Result<(), WolError>>, Error> {
+        let machine = match self.get_machine(mac).await {
+            Some(machine) => machine,
+            None => return Ok(None),
+        };
+        let keep = settings.password.is_none();
+        let mut wol: WolConfig = settings.into();
+        if keep {
+            wol.password = machine.wol.lock().await.password.clone();
+        }
+        if let Err(err) = wol.validate() {
+            return Ok(Some(Err(err)));
+        }
+        if let Some(relay) = &wol.relay {
+            if relay == mac || self.get_machine(relay).await.is_none() {
+                return Ok(Some(Err(WolError::Relay(*relay))));
+            }
+        }
+        let escalation = machine.escalation.lock().await.as_ref().map(|escalation| escalation.action);
+        if wol.relay.is_none() && escalation == Some(EscalationAction::Relay) {
+            return Ok(Some(Err(WolError::Escalation)));
+        }
+        machine.set_wol(wol).await;
+        log::info!("edited wake on lan of mac address({:x?})", mac);
+        self.save().await?;
+        Ok(Some(Ok(())))
+    }
+    /// a password left out keeps the current one, refused to clear if the escalation use it
+    pub(super) async fn edit_bmc(
+        &self,
+        mac: &MacAddress,
+        settings: Option<api::BmcSettings>,
+    ) -> Result<Option<Result<(), BmcError>>, Error> {
+        let machine = match self.get_machine(mac).await {
+            Some(machine) => machine,
+            None => return Ok(None),
+        };
+        let bmc = match settings {
+            Some(settings) => {
+                let current = machine.bmc.lock().await.as_ref().map(|bmc| bmc.password.clone());
+                let password = match settings.password.clone().or(current) {
+                    Some(password) => password,
+                    None => return Ok(Some(Err(BmcError::Password))),
+                };
+                let bmc = BmcConfig {
+                    password,
+                    ..settings.into()
+                };
+                if let Err(err) = bmc.validate() {
+                    return Ok(Some(Err(err)));
+                }
+                Some(bmc)
+            }
+            None => {
+                let escalation = machine.escalation.lock().await.as_ref().map(|escalation| escalation.action);
+                if let Some(EscalationAction::BmcHardReset | EscalationAction::BmcPowerOff) = escalation {
+                    return Ok(Some(Err(BmcError::Escalation)));
+                }
+                None
+            }
+        };
+        machine.set_bmc(bmc).await;
+        log::info!("edited bmc of mac address({:x?})", mac);
+        self.save().await?;
+        Ok(Some(Ok(())))
+    }
+    /// a password left out keeps the current one, refused to clear if the escalation use it
+    pub(super) async fn edit_plug(
+        &self,
+        mac: &MacAddress,
+        settings: Option<api::PlugSettings>,
+    ) -> Result<Option<Result<(), PlugError>>, Error> {
+        let machine = match self.get_machine(mac).await {
+            Some(machine) => machine,
+            None => return Ok(None),
+        };
+        let plug = match settings {
+            Some(settings) => {
+                let current = machine.plug.lock().await.as_ref().and_then(|plug| plug.password.clone());
+                let password = settings.password.clone().or(current);
+                let plug = PlugConfig {
+                    password,
+                    ..settings.into()
+                };
+                if let Err(err) = plug.validate() {
+                    return Ok(Some(Err(err)));
+                }
+                Some(plug)
+            }
+            None => {
+                let escalation = machine.escalation.lock().await.as_ref().map(|escalation| escalation.action);
+                if let Some(EscalationAction::PowerCycle) = escalation {
+                    return Ok(Some(Err(PlugError::Escalation)));
+                }
+                None
+            }
+        };
+        machine.set_plug(plug).await;
+        log::info!("edited smart plug of mac address({:x?})", mac);
+        self.save().await?;
+        Ok(Some(Ok(())))
+    }
+    /// the bmc, the smart plug or the relay doing the escalation must be set first
+    pub(super) async fn edit_escalation(
+        &self,
+        mac: &MacAddress,
+        escalation: Option<Escalation>,
+    ) -> Result<Option<Result<(), packet::Error>>, Error> {
+        let machine = match self.get_machine(mac).await {
+            Some(machine) => machine,
+            None => return Ok(None),
+        };
+        match escalation.as_ref().map(|escalation| escalation.action) {
+            Some(EscalationAction::BmcHardReset | EscalationAction::BmcPowerOff)
+                if machine.bmc.lock().await.is_none() =>
+            {
+                return Ok(Some(Err(packet::Error::NoBmc)));
+            }
+            Some(EscalationAction::PowerCycle) if machine.plug.lock().await.is_none() => {
+                return Ok(Some(Err(packet::Error::NoPlug)));
+            }
+            Some(EscalationAction::Relay) if machine.wol.lock().await.relay.is_none() => {
+                return Ok(Some(Err(packet::Error::NoRelay)));
+            }
+            _ => {}
+        }
+        machine.set_escalation(escalation).await;
+        log::info!("edited escalation of mac address({:x?})", mac);
+        self.save().await?;
+        Ok(Some(Ok(())))
+    }
+    /// connection slot of the relay named by wol settings of machine
+    async fn relay_of(&self, machine: &Machine) -> Option<TcpRelay> {
+        let relay = machine.wol.lock().await.relay?;
+        self.get_machine(&relay)
+            .await
+            .map(|relay| relay.packet.clone())
+    }
     async fn get_machine(&self, mac_address: &[u8; 6]) -> Option<Arc<Machine>> {
         self.machines
             .lock()
             .await
             .get(mac_address)
-            .map(|a| a.clone())
+            .cloned()
     }
     pub async fn list_os(&self, mac_address: &[u8; 6]) -> adaptor::OsListAdaptor {
         adaptor::OsListAdaptor {
@@ -184,13 +539,183 @@ impl Server {
             machine: self.get_machine(mac_address).await,
         }
     }
-    pub fn list_machine(&self) -> adaptor::MachineListAdaptor {
+    pub async fn list_edge(&self, mac_address: &[u8; 6]) -> adaptor::EdgeListAdaptor {
+        adaptor::EdgeListAdaptor {
+            machine: self.get_machine(mac_address).await,
+        }
+    }
+    pub async fn edit_boot_graph(
+        &self,
+        mac_address: [u8; 6],
+        edit: api::GraphEdit,
+    ) -> adaptor::EditGraphAdaptor<'_> {
+        adaptor::EditGraphAdaptor {
+            mac_address,
+            edit,
+            server: self,
+        }
+    }
+    pub async fn export_graph(&self, mac_address: &[u8; 6]) -> adaptor::GraphAdaptor {
+        adaptor::GraphAdaptor {
+            machine: self.get_machine(mac_address).await,
+        }
+    }
+    pub async fn new_standalone_machine(
+        &self,
+        mac_address: [u8; 6],
+        display_name: String,
+        wol: api::WolSettings,
+        probe: Option<api::ProbeSettings>,
+    ) -> adaptor::NewStandaloneAdaptor<'_> {
+        adaptor::NewStandaloneAdaptor {
+            mac_address,
+            display_name,
+            wol,
+            probe,
+            server: self,
+        }
+    }
+    pub async fn probe(&self, mac_address: &[u8; 6]) -> adaptor::ProbeAdaptor {
+        adaptor::ProbeAdaptor {
+            machine: self.get_machine(mac_address).await,
+        }
+    }
+    pub async fn edit_probe_settings(
+        &self,
+        mac_address: [u8; 6],
+        probe: Option<api::ProbeSettings>,
+    ) -> adaptor::EditProbeAdaptor<'_> {
+        adaptor::EditProbeAdaptor {
+            mac_address,
+            probe,
+            server: self,
+        }
+    }
+    pub async fn wol(&self, mac_address: &[u8; 6]) -> adaptor::WolAdaptor {
+        adaptor::WolAdaptor {
+            machine: self.get_machine(mac_address).await,
+        }
+    }
+    pub async fn edit_wol_settings(
+        &self,
+        mac_address: [u8; 6],
+        settings: api::WolSettings,
+    ) -> adaptor::EditWolAdaptor<'_> {
+        adaptor::EditWolAdaptor {
+            mac_address,
+            settings,
+            server: self,
+        }
+    }
+    pub async fn bmc(&self, mac_address: &[u8; 6]) -> adaptor::BmcAdaptor {
+        adaptor::BmcAdaptor {
+            machine: self.get_machine(mac_address).await,
+        }
+    }
+    pub async fn edit_bmc_settings(
+        &self,
+        mac_address: [u8; 6],
+        settings: Option<api::BmcSettings>,
+    ) -> adaptor::EditBmcAdaptor<'_> {
+        adaptor::EditBmcAdaptor {
+            mac_address,
+            settings,
+            server: self,
+        }
+    }
+    pub async fn plug(&self, mac_address: &[u8; 6]) -> adaptor::PlugAdaptor {
+        adaptor::PlugAdaptor {
+            machine: self.get_machine(mac_address).await,
+        }
+    }
+    pub async fn edit_plug_settings(
+        &self,
+        mac_address: [u8; 6],
+        settings: Option<api::PlugSettings>,
+    ) -> adaptor::EditPlugAdaptor<'_> {
+        adaptor::EditPlugAdaptor {
+            mac_address,
+            settings,
+            server: self,
+        }
+    }
+    pub async fn escalation(&self, mac_address: &[u8; 6]) -> adaptor::EscalationAdaptor {
+        adaptor::EscalationAdaptor {
+            machine: self.get_machine(mac_address).await,
+        }
+    }
+    pub async fn edit_escalation_settings(
+        &self,
+        mac_address: [u8; 6],
+        escalation: Option<api::EscalationSettings>,
+    ) -> adaptor::EditEscalationAdaptor<'_> {
+        adaptor::EditEscalationAdaptor {
+            mac_address,
+            escalation,
+            server: self,
+        }
+    }
+    pub async fn audit(&self, mac_address: &[u8; 6]) -> adaptor::AuditAdaptor {
+        adaptor::AuditAdaptor {
+            machine: self.get_machine(mac_address).await,
+        }
+    }
+    pub async fn plan(&self, os: api::OsStatus, mac_address: &[u8; 6]) -> adaptor::PlanAdaptor {
+        adaptor::PlanAdaptor {
+            os,
+            machine: self.get_machine(mac_address).await,
+        }
+    }
+    pub fn list_machine(&self) -> adaptor::MachineListAdaptor<'_> {
         adaptor::MachineListAdaptor { server: self }
     }
-    pub async fn boot(&self, os: api::OsStatus, mac_address: &[u8; 6]) -> adaptor::BootAdaptor {
+    pub async fn boot(&self, os: api::OsStatus, mac_address: &[u8; 6]) -> adaptor::BootAdaptor<'_> {
+        let machine = self.get_machine(mac_address).await;
+        let relay = match &machine {
+            Some(machine) => self.relay_of(machine).await,
+            None => None,
+        };
         adaptor::BootAdaptor {
             os,
-            machine: self.get_machine(mac_address).await,
+            machine,
+            relay,
+            server: self,
+        }
+    }
+    pub async fn rename(
+        &self,
+        mac_address: [u8; 6],
+        display_name: String,
+    ) -> adaptor::RenameMachineAdaptor<'_> {
+        adaptor::RenameMachineAdaptor {
+            display_name,
+            mac_address,
+            server: self,
+        }
+    }
+    pub async fn forget(&self, mac_address: [u8; 6]) -> adaptor::ForgetMachineAdaptor<'_> {
+        adaptor::ForgetMachineAdaptor {
+            mac_address,
+            server: self,
+        }
+    }
+    pub async fn renew(&self, mac_address: [u8; 6]) -> adaptor::RenewMachineAdaptor<'_> {
+        adaptor::RenewMachineAdaptor {
+            mac_address,
+            server: self,
+        }
+    }
+    pub async fn label_os(
+        &self,
+        mac_address: [u8; 6],
+        os: protocal::ID,
+        label: OsLabel,
+    ) -> adaptor::EditOsAdaptor<'_> {
+        adaptor::EditOsAdaptor {
+            mac_address,
+            os,
+            label,
+            server: self,
         }
     }
     pub async fn init_machine<'a>(
@@ -206,17 +731,96 @@ impl Server {
     }
 }
 
+/// os info edited by user, keyed by os id
+#[derive(Clone, Default, ::serde::Deserialize, ::serde::Serialize)]
+pub struct OsLabel {
+    pub display_name: Option<String>,
+    pub aliases: Vec<String>,
+    pub icon: Option<String>,
+    pub tags: Vec<String>,
+    pub notes: String,
+    pub default: bool,
+}
+
+// bytes of a display name, an icon, an alias or a tag
+const LABEL_NAME_LIMIT: usize = 128;
+// aliases or tags of an os
+const LABEL_LIST_LIMIT: usize = 16;
+const LABEL_NOTES_LIMIT: usize = 4096;
+
+#[derive(thiserror::Error, Debug)]
+pub enum LabelError {
+    #[error("{0} is longer than {LABEL_NAME_LIMIT} bytes")]
+    Name(&'static str),
+    #[error("more than {LABEL_LIST_LIMIT} {0}")]
+    List(&'static str),
+    #[error("notes are longer than {LABEL_NOTES_LIMIT} bytes")]
+    Notes,
+}
+
+impl OsLabel {
+    pub fn validate(&self) -> Result<(), LabelError> {
+        let name = |field, name: &Option<String>| match name {
+            Some(name) if name.len() > LABEL_NAME_LIMIT => Err(LabelError::Name(field)),
+            _ => Ok(()),
+        };
+        let list = |field, item, names: &Vec<String>| {
+            if names.len() > LABEL_LIST_LIMIT {
+                return Err(LabelError::List(field));
+            }
+            match names.iter().any(|name| name.len() > LABEL_NAME_LIMIT) {
+                true => Err(LabelError::Name(item)),
+                false => Ok(()),
+            }
+        };
+        name("display name", &self.display_name)?;
+        name("icon", &self.icon)?;
+        list("aliases", "an alias", &self.aliases)?;
+        list("tags", "a tag", &self.tags)?;
+        if self.notes.len() > LABEL_NOTES_LIMIT {
+            return Err(LabelError::Notes);
+        }
+        Ok(())
+    }
+}
+
+impl From<api::OsLabel<'_>> for OsLabel {
+    fn from(label: api::OsLabel<'_>) -> Self {
+        Self {
+            display_name: label.display_name.map(|x| x.into_owned()),
+            aliases: label.aliases.into_iter().map(|x| x.into_owned()).collect(),
+            icon: label.icon.map(|x| x.into_owned()),
+            tags: label.tags.into_iter().map(|x| x.into_owned()).collect(),
+            notes: label.notes.into_owned(),
+            default: label.default,
+        }
+    }
+}
+
 pub struct Machine {
     pub(super) display_name: Mutex<String>,
     pub(super) mac_address: MacAddress,
-    pub(super) boot_graph: BootGraph,
-    pub(super) packet: Mutex<Option<TcpPacket>>,
+    pub(super) boot_graph: RwLock<BootGraph>,
+    pub(super) labels: Mutex<IndexMap<protocal::ID, OsLabel>>,
+    pub(super) packet: TcpRelay,
+    pub(super) wol: Mutex<WolConfig>,
+    // registered by mac address without agent, it's only woken
+    pub(super) standalone: bool,
+    pub(super) probe: Mutex<Option<ProbeConfig>>,
+    // powers the machine on and off without its agent
+    pub(super) bmc: Mutex<Option<BmcConfig>>,
+    // power cycles the machine without bmc
+    pub(super) plug: Mutex<Option<PlugConfig>>,
+    // taken over by the bmc or the plug when the agent hangs
+    pub(super) escalation: Mutex<Option<Escalation>>,
+    pub(super) audit: Audit,
 }
 
 impl Machine {
-    pub(super) async fn connect(&self, packet: TcpPacket) -> Option<TcpPacket> {
+    pub(super) async fn connect(&self, mut packet: TcpPacket) -> Option<TcpPacket> {
         let display_name=self.display_name.lock().await;
         log::trace!("machine {} connected",display_name);
+        self.configure(&mut packet).await;
 
         let mut current_packet = self.packet.lock().await;
         match &*current_packet {
@@ -227,28 +831,255 @@ impl Machine {
             }
         }
     }
+    /// apply the settings of the machine to a connection of its host
+    async fn configure(&self, packet: &mut TcpPacket) {
+        packet.set_wol(self.wol.lock().await.clone());
+        packet.set_probe(self.probe.lock().await.clone());
+        packet.set_bmc(self.bmc.lock().await.clone());
+        packet.set_plug(self.plug.lock().await.clone());
+        packet.set_escalation(self.escalation.lock().await.clone());
+        packet.set_audit(self.audit.clone());
+    }
     pub(super) async fn new(
         packet: TcpPacket,
         display_name: String,
     ) -> Result<(Machine, TcpPacket), Error> {
-        let mac_address = packet.get_mac_address().clone();
-        let (boot_graph,packet) = BootGraph::new(packet).await?;
+        let mac_address = *packet.get_mac_address();
+        let checkpoint = CHECKPOINT_DIR.join(format!("{}.sav", hex_mac(&mac_address)));
+        let (boot_graph,packet) = BootGraph::enroll(packet, &checkpoint).await?;
 
         log::info!("finish machine with name {}", display_name);
         let machine = Machine {
             display_name: Mutex::new(display_name),
             mac_address,
-            boot_graph,
-            packet: Mutex::new(None),
+            boot_graph: RwLock::new(boot_graph),
+            labels: Default::default(),
+            packet: Default::default(),
+            wol: Default::default(),
+            standalone: false,
+            probe: Default::default(),
+            bmc: Default::default(),
+            plug: Default::default(),
+            escalation: Default::default(),
+            audit: Default::default(),
         };
 
         Ok((machine, packet))
     }
+    pub(super) fn standalone(
+        mac_address: MacAddress,
+        display_name: String,
+        wol: WolConfig,
+        probe: Option<ProbeConfig>,
+    ) -> Machine {
+        Machine {
+            boot_graph: RwLock::new(BootGraph::standalone("awake".to_owned())),
+            display_name: Mutex::new(display_name),
+            mac_address,
+            labels: Default::default(),
+            packet: Default::default(),
+            wol: Mutex::new(wol),
+            standalone: true,
+            probe: Mutex::new(probe),
+            bmc: Default::default(),
+            plug: Default::default(),
+            escalation: Default::default(),
+            audit: Default::default(),
+        }
+    }
+    /// rebuild the boot graph, keeping display name and labels
+    pub(super) async fn renew(&self, packet: TcpPacket) -> Result<(Machine, TcpPacket), Box<bootgraph::Interrupted>> {
+        let (boot_graph, packet) = self.boot_graph.read().await.renew(packet).await?;
+
+        let display_name = self.display_name.lock().await.clone();
+        log::info!("finish rediscovering machine with name {}", display_name);
+        let machine = Machine {
+            display_name: Mutex::new(display_name),
+            mac_address: self.mac_address,
+            boot_graph: RwLock::new(boot_graph),
+            labels: Mutex::new(self.labels.lock().await.clone()),
+            packet: Default::default(),
+            wol: Mutex::new(self.wol.lock().await.clone()),
+            standalone: self.standalone,
+            probe: Mutex::new(self.probe.lock().await.clone()),
+            bmc: Mutex::new(self.bmc.lock().await.clone()),
+            plug: Mutex::new(self.plug.lock().await.clone()),
+            escalation: Mutex::new(self.escalation.lock().await.clone()),
+            audit: self.audit.clone(),
+        };
+
+        Ok((machine, packet))
+    }
+    /// explore grub and firmware entries of the connected os which changed since the graph was built
+    ///
+    /// the connection is given back on error, the host may still answer,
+    /// and along with it on success whether the graph changed
+    pub(super) async fn refresh(&self, packet: TcpPacket) -> Result<(TcpPacket, bool), Box<bootgraph::Interrupted>> {
+        let (uid, entries) = match query_entries(&packet).await {
+            Ok(query) => query,
+            Err(err) => {
+                return Err(Box::new(bootgraph::Interrupted {
+                    error: err.into(),
+                    packet,
+                }))
+            }
+        };
+
+        let diff = self.boot_graph.read().await.diff_entries(uid, &entries);
+        let added = match diff {
+            Some((added, vanished)) => {
+                if !vanished.is_empty() {
+                    self.boot_graph.write().await.forget_edges(uid, &vanished);
+                }
+                if added.is_empty() {
+                    return Ok((packet, !vanished.is_empty()));
+                }
+                added
+            }
+            // an os never seen, every entry is new
+            None => Vec::new(),
+        };
+
+        let display_name = self.display_name.lock().await.clone();
+        log::info!("boot entries of machine {} changed, rediscovering", display_name);
+        let origin = self.boot_graph.read().await.clone();
+        let (boot_graph, packet) = origin.rediscover(packet, added).await?;
+        *self.boot_graph.write().await = boot_graph;
+        Ok((packet, true))
+    }
+    pub(super) async fn edit_graph(&self, edit: api::GraphEdit) -> Result<Option<protocal::ID>, EditError> {
+        if let api::GraphEdit::AddEdge { method, .. } | api::GraphEdit::AddNode { method, .. } = &edit {
+            self.check_controller(&method.into()).await?;
+        }
+        let mut boot_graph = self.boot_graph.write().await;
+        match edit {
+            api::GraphEdit::AddEdge { from, method, to } => boot_graph
+                .add_edge(&(&from).into(), (&method).into(), &(&to).into())
+                .map(|_| None),
+            api::GraphEdit::RemoveEdge { from, method } => boot_graph
+                .remove_edge(&(&from).into(), &(&method).into())
+                .map(|_| None),
+            api::GraphEdit::DisableEdge { from, method, disabled } => boot_graph
+                .set_disabled(&(&from).into(), &(&method).into(), disabled)
+                .map(|_| None),
+            api::GraphEdit::AddNode { display_name, from, method } => boot_graph
+                .add_node(display_name, &(&from).into(), (&method).into())
+                .map(Some),
+            api::GraphEdit::RemoveNode { id } => boot_graph.remove_node(id).map(|_| None),
+        }
+    }
+    /// an edge driven by a bmc or a smart plug need it configured
+    async fn check_controller(&self, method: &BootMethod) -> Result<(), EditError> {
+        let configured = match method {
+            BootMethod::Bmc(_) => self.bmc.lock().await.is_some(),
+            BootMethod::PowerCycle => self.plug.lock().await.is_some(),
+            _ => true,
+        };
+        match configured {
+            true => Ok(()),
+            false => Err(EditError::NoController(method.clone())),
+        }
+    }
+    /// also applied to the connected packet, or the next one
+    pub(super) async fn set_wol(&self, wol: WolConfig) {
+        if let Some(packet) = self.packet.lock().await.as_mut() {
+            packet.set_wol(wol.clone());
+        }
+        *self.wol.lock().await = wol;
+    }
+    pub(super) async fn edit_os(&self, os: protocal::ID, label: OsLabel) -> bool {
+        if self.boot_graph.read().await.find_os(os).is_none() {
+            return false;
+        }
+        let mut labels = self.labels.lock().await;
+        if label.default {
+            labels.values_mut().for_each(|label| label.default = false);
+        }
+        labels.insert(os, label);
+        true
+    }
+    /// also applied to the connected packet, or the next one
+    pub(super) async fn set_probe(&self, probe: Option<ProbeConfig>) {
+        if let Some(packet) = self.packet.lock().await.as_mut() {
+            packet.set_probe(probe.clone());
+        }
+        *self.probe.lock().await = probe;
+    }
+    /// also applied to the connected packet, or the next one
+    ///
+    /// edges driven by the bmc are removed with it
+    pub(super) async fn set_bmc(&self, bmc: Option<BmcConfig>) {
+        if bmc.is_none() {
+            self.boot_graph
+                .write()
+                .await
+                .remove_controlled(|method| matches!(method, BootMethod::Bmc(_)));
+        }
+        if let Some(packet) = self.packet.lock().await.as_mut() {
+            packet.set_bmc(bmc.clone());
+        }
+        *self.bmc.lock().await = bmc;
+    }
+    /// also applied to the connected packet, or the next one
+    ///
+    /// the power cycle edge is added or removed with it
+    pub(super) async fn set_plug(&self, plug: Option<PlugConfig>) {
+        let mut boot_graph = self.boot_graph.write().await;
+        match plug {
+            Some(_) => boot_graph.add_power_cycle(),
+            None => boot_graph.remove_controlled(|method| *method == BootMethod::PowerCycle),
+        }
+        drop(boot_graph);
+        if let Some(packet) = self.packet.lock().await.as_mut() {
+            packet.set_plug(plug.clone());
+        }
+        *self.plug.lock().await = plug;
+    }
+    /// also applied to the connected packet, or the next one
+    pub(super) async fn set_escalation(&self, escalation: Option<Escalation>) {
+        if let Some(packet) = self.packet.lock().await.as_mut() {
+            packet.set_escalation(escalation.clone());
+        }
+        *self.escalation.lock().await = escalation;
+    }
+    /// none if no probe is set or it can't be sent
+    pub(super) async fn awake(&self) -> Option<bool> {
+        let probe = self.probe.lock().await.clone()?.probe;
+        match probe.reachable(packet::PROBE_TIMEOUT).await {
+            Ok(reachable) => Some(reachable),
+            Err(err) => {
+                log::warn!("fail to probe {:?}: {:?}", probe, err);
+                None
+            }
+        }
+    }
+    /// send magic packets until the probe reach it, true if it woke or no probe is set
+    pub(super) async fn wake(&self, relay: Option<TcpRelay>) -> Result<bool, Error> {
+        let waker = TcpWaker::new(self.mac_address, self.wol.lock().await.clone(), relay);
+        let probe = match self.probe.lock().await.clone() {
+            Some(probe) => probe,
+            None => {
+                for _ in 0..BLIND_WAKE {
+                    waker.wake().await?;
+                    async_std::task::sleep(time::Duration::from_secs(1)).await;
+                }
+                return Ok(true);
+            }
+        };
+        let deadline = std::time::Instant::now() + probe.timeout;
+        while std::time::Instant::now() < deadline {
+            waker.wake().await?;
+            if probe.probe.reachable(packet::PROBE_TIMEOUT).await? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
     pub(super) async fn current_os(&self) -> Result<Option<protocal::ID>, Error> {
         let mut packet1 = self.packet.lock().await;
         let packet = &mut *packet1;
         Ok(match packet {
-            Some(packet) => match self.boot_graph.current_os(packet).await? {
+            Some(packet) => match BootGraph::current_os(packet).await? {
                 OsStatus::Down => None,
                 OsStatus::Up(os) => Some(os),
             },
@@ -260,27 +1091,61 @@ impl Machine {
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error("Io Error")]
-    PacketError(#[from] packet::Error),
+    Packet(#[from] packet::Error),
     #[error("Bugs occurs in either graph logic or Host")]
-    BootGraphError(bootgraph::Error),
+    BootGraph(bootgraph::Error),
     #[error("Host didn't follow protocal")]
     UndefinedClientBehavior,
     #[error("client already connected")]
     ClientConnected,
     #[error("Server Failure")]
-    IoError(#[from] io::Error),
+    Io(#[from] io::Error),
     #[error("Unable to save file")]
-    BincodeError(#[from] bincode::Error),
+    Bincode(#[from] bincode::Error),
     #[error("Client not connected")]
     ClientNotConnected,
+    #[error("Unable to load or save file")]
+    Save(#[from] SaveError),
 }
 
 impl From<bootgraph::Error> for Error {
     fn from(e: bootgraph::Error) -> Self {
         match e {
             bootgraph::Error::UndefinedClientBehavior => Self::UndefinedClientBehavior,
-            bootgraph::Error::BadGraph => Self::BootGraphError(e),
-            bootgraph::Error::PacketError(e) => Self::PacketError(e),
+            bootgraph::Error::BadGraph | bootgraph::Error::TooManyHop => Self::BootGraph(e),
+            bootgraph::Error::Packet(e) => Self::Packet(e),
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{LabelError, OsLabel, LABEL_LIST_LIMIT, LABEL_NAME_LIMIT, LABEL_NOTES_LIMIT};
+
+    #[test]
+    fn label() {
+        let label = OsLabel {
+            display_name: Some("a".repeat(LABEL_NAME_LIMIT)),
+            aliases: vec!["alias".to_owned(); LABEL_LIST_LIMIT],
+            notes: "a".repeat(LABEL_NOTES_LIMIT),
+            ..Default::default()
+        };
+        assert!(label.validate().is_ok());
+
+        let label = OsLabel {
+            icon: Some("a".repeat(LABEL_NAME_LIMIT + 1)),
+            ..Default::default()
+        };
+        assert!(matches!(label.validate(), Err(LabelError::Name("icon"))));
+        let label = OsLabel {
+            tags: vec!["tag".to_owned(); LABEL_LIST_LIMIT + 1],
+            ..Default::default()
+        };
+        assert!(matches!(label.validate(), Err(LabelError::List("tags"))));
+        let label = OsLabel {
+            notes: "a".repeat(LABEL_NOTES_LIMIT + 1),
+            ..Default::default()
+        };
+        assert!(matches!(label.validate(), Err(LabelError::Notes)));
+    }
+}
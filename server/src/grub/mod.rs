@@ -1,10 +1,12 @@
 pub mod adaptor;
-pub mod api;
+pub mod audit;
 pub mod bootgraph;
 pub mod machine;
 pub mod packet;
 pub mod serde;
 
+pub use proto::prelude::web as api;
+
 pub use machine::Error;
 pub mod prelude {
     pub use super::machine::Server;
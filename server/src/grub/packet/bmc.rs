@@ -0,0 +1,352 @@
+use std::{io, sync::Arc, time::Duration};
+
+use async_native_tls::TlsConnector;
+use async_std::process::Command;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use surf::http::{auth::BasicAuth, Method};
+
+// a controller answer in this long, powering on is done asynchronously by it
+const BMC_TIMEOUT: Duration = Duration::from_secs(20);
+
+#[derive(thiserror::Error, Debug)]
+pub enum BmcError {
+    #[error("{0} isn't an http(s) url for redfish, or a host for ipmi")]
+    Address(String),
+    #[error("password is required")]
+    Password,
+    #[error("redfish request fail: {0}")]
+    Http(String),
+    #[error("redfish answer status {0}")]
+    Status(u16),
+    #[error("redfish service has no computer system")]
+    NoSystem,
+    #[error("ipmitool fail: {0}")]
+    Ipmi(String),
+    #[error("fail to run ipmitool")]
+    Io(#[from] io::Error),
+    #[error("escalation of the machine use the bmc, change it first")]
+    Escalation,
+}
+
+impl From<surf::Error> for BmcError {
+    fn from(err: surf::Error) -> Self {
+        BmcError::Http(err.to_string())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub enum BmcProtocol {
+    Redfish,
+    Ipmi,
+}
+
+/// credentials of the baseboard management controller of a machine
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct BmcConfig {
+    pub protocol: BmcProtocol,
+    // base url of redfish, "host[:port]" of ipmi
+    pub address: String,
+    pub username: String,
+    pub password: String,
+    // accept the self-signed certificate most controllers ship with
+    pub insecure: bool,
+}
+
+#[derive(Hash, Eq, PartialEq, Clone, Copy, Deserialize, Serialize, Debug)]
+pub enum BmcAction {
+    PowerOn,
+    PowerOff,
+    HardReset,
+    OneTimeBoot(BootDevice),
+}
+
+#[derive(Hash, Eq, PartialEq, Clone, Copy, Deserialize, Serialize, Debug)]
+pub enum BootDevice {
+    Pxe,
+    Disk,
+    Cdrom,
+    BiosSetup,
+}
+
+impl BmcConfig {
+    pub fn validate(&self) -> Result<(), BmcError> {
+        let valid = match self.protocol {
+            BmcProtocol::Redfish => matches!(
+                surf::Url::parse(&self.address),
+                Ok(url) if (url.scheme() == "http" || url.scheme() == "https") && url.has_host()
+            ),
+            BmcProtocol::Ipmi => !self.address.is_empty() && !self.address.contains('/'),
+        };
+        match valid {
+            true => Ok(()),
+            false => Err(BmcError::Address(self.address.clone())),
+        }
+    }
+    /// `up` tells whether a one time boot has to reset the machine or to power it on
+    pub async fn execute(&self, action: BmcAction, up: bool) -> Result<(), BmcError> {
+        log::info!("{:?} by bmc at {}", action, self.address);
+        match self.protocol {
+            BmcProtocol::Redfish => self.redfish(action, up).await,
+            BmcProtocol::Ipmi => {
+                if let BmcAction::OneTimeBoot(device) = action {
+                    self.ipmitool(&["chassis", "bootdev", ipmi_device(device)])
+                        .await?;
+                }
+                self.ipmitool(&["chassis", "power", ipmi_power(action, up)])
+                    .await
+            }
+        }
+    }
+    async fn redfish(&self, action: BmcAction, up: bool) -> Result<(), BmcError> {
+        let client = self.client();
+        let systems = self
+            .request(&client, Method::Get, "/redfish/v1/Systems", None)
+            .await?;
+        // a server has a single system, blades and partitions aren't supported
+        let system = systems["Members"][0]["@odata.id"]
+            .as_str()
+            .ok_or(BmcError::NoSystem)?
+            .to_owned();
+        if let BmcAction::OneTimeBoot(device) = action {
+            let body = json!({
+                "Boot": {
+                    "BootSourceOverrideEnabled": "Once",
+                    "BootSourceOverrideTarget": redfish_device(device),
+                }
+            });
+            self.request(&client, Method::Patch, &system, Some(body))
+                .await?;
+        }
+        let body = json!({ "ResetType": redfish_reset(action, up) });
+        let reset = format!("{}/Actions/ComputerSystem.Reset", system);
+        self.request(&client, Method::Post, &reset, Some(body))
+            .await?;
+        Ok(())
+    }
+    fn client(&self) -> surf::Client {
+        let mut config = surf::Config::new().set_timeout(Some(BMC_TIMEOUT));
+        if self.insecure {
+            let tls = TlsConnector::new()
+                .danger_accept_invalid_certs(true)
+                .danger_accept_invalid_hostnames(true);
+            config = config.set_tls_config(Some(Arc::new(tls)));
+        }
+        // the h1 client is built without error
+        let Ok(client) = config.try_into();
+        client
+    }
+    async fn request(
+        &self,
+        client: &surf::Client,
+        method: Method,
+        path: &str,
+        body: Option<Value>,
+    ) -> Result<Value, BmcError> {
+        let url = format!("{}{}", self.address.trim_end_matches('/'), path);
+        let auth = BasicAuth::new(&self.username, &self.password);
+        let mut req =
+            surf::RequestBuilder::new(method, url.parse().map_err(|_| BmcError::Http(url))?)
+                .header(auth.name(), auth.value());
+        if let Some(body) = body {
+            req = req.body_json(&body)?;
+        }
+        let mut res = client.send(req).await?;
+        if !res.status().is_success() {
+            return Err(BmcError::Status(res.status().into()));
+        }
+        // actions may answer 204 without body
+        let body = res.body_bytes().await?;
+        Ok(serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+    async fn ipmitool(&self, command: &[&str]) -> Result<(), BmcError> {
+        let (host, port) = match self.address.rsplit_once(':') {
+            Some((host, port)) => (host, port),
+            None => (self.address.as_str(), "623"),
+        };
+        // password is passed by environment, not to be seen in the process list
+        let output = Command::new("ipmitool")
+            .args([
+                "-I",
+                "lanplus",
+                "-H",
+                host,
+                "-p",
+                port,
+                "-U",
+                &self.username,
+                "-E",
+            ])
+            .args(command)
+            .env("IPMI_PASSWORD", &self.password)
+            .output()
+            .await?;
+        match output.status.success() {
+            true => Ok(()),
+            false => Err(BmcError::Ipmi(
+                String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+            )),
+        }
+    }
+}
+
+fn redfish_reset(action: BmcAction, up: bool) -> &'static str {
+    match action {
+        BmcAction::PowerOn => "On",
+        BmcAction::PowerOff => "ForceOff",
+        BmcAction::HardReset => "ForceRestart",
+        BmcAction::OneTimeBoot(_) if up => "ForceRestart",
+        BmcAction::OneTimeBoot(_) => "On",
+    }
+}
+
+fn redfish_device(device: BootDevice) -> &'static str {
+    match device {
+        BootDevice::Pxe => "Pxe",
+        BootDevice::Disk => "Hdd",
+        BootDevice::Cdrom => "Cd",
+        BootDevice::BiosSetup => "BiosSetup",
+    }
+}
+
+fn ipmi_power(action: BmcAction, up: bool) -> &'static str {
+    match action {
+        BmcAction::PowerOn => "on",
+        BmcAction::PowerOff => "off",
+        BmcAction::HardReset => "reset",
+        BmcAction::OneTimeBoot(_) if up => "reset",
+        BmcAction::OneTimeBoot(_) => "on",
+    }
+}
+
+// without "options=persistent", ipmi override only the next boot
+fn ipmi_device(device: BootDevice) -> &'static str {
+    match device {
+        BootDevice::Pxe => "pxe",
+        BootDevice::Disk => "disk",
+        BootDevice::Cdrom => "cdrom",
+        BootDevice::BiosSetup => "bios",
+    }
+}
+
+#[cfg(test)]
+pub(super) mod test {
+    use std::sync::{Arc, Mutex};
+
+    use async_std::task::spawn;
+    use serde_json::{json, Value};
+    use surf::http::auth::BasicAuth;
+    use tide::listener::{Listener, ToListener};
+
+    use super::{BmcAction, BmcConfig, BmcError, BmcProtocol, BootDevice};
+
+    pub(in super::super) type Log = Arc<Mutex<Vec<(String, String, Value)>>>;
+
+    /// a controller with a single system, recording every request
+    pub(in super::super) async fn mock_redfish() -> (String, Log) {
+        let log: Log = Default::default();
+        let mut app = tide::with_state(log.clone());
+        app.at("/redfish/v1/Systems").get(systems);
+        app.at("/redfish/v1/Systems/1").patch(record);
+        app.at("/redfish/v1/Systems/1/Actions/ComputerSystem.Reset")
+            .post(record);
+        let mut listener = "127.0.0.1:0".to_listener().unwrap();
+        listener.bind(app).await.unwrap();
+        let url = listener.info()[0].connection().to_owned();
+        spawn(async move { listener.accept().await });
+        (url, log)
+    }
+
+    fn authorized(req: &tide::Request<Log>) -> bool {
+        matches!(
+            BasicAuth::from_headers(req),
+            Ok(Some(auth)) if auth.username() == "admin" && auth.password() == "secret"
+        )
+    }
+
+    async fn systems(req: tide::Request<Log>) -> tide::Result {
+        if !authorized(&req) {
+            return Ok(tide::Response::new(401));
+        }
+        Ok(json!({ "Members": [{ "@odata.id": "/redfish/v1/Systems/1" }] }).into())
+    }
+
+    async fn record(mut req: tide::Request<Log>) -> tide::Result {
+        if !authorized(&req) {
+            return Ok(tide::Response::new(401));
+        }
+        let body: Value = req.body_json().await?;
+        let entry = (req.method().to_string(), req.url().path().to_owned(), body);
+        req.state().lock().unwrap().push(entry);
+        Ok(tide::Response::new(204))
+    }
+
+    pub(in super::super) fn config(address: String, password: &str) -> BmcConfig {
+        BmcConfig {
+            protocol: BmcProtocol::Redfish,
+            address,
+            username: "admin".to_owned(),
+            password: password.to_owned(),
+            insecure: false,
+        }
+    }
+
+    #[async_std::test]
+    async fn redfish() {
+        let (url, log) = mock_redfish().await;
+        let bmc = config(url.clone(), "secret");
+        let reset = "/redfish/v1/Systems/1/Actions/ComputerSystem.Reset";
+
+        bmc.execute(BmcAction::PowerOn, false).await.unwrap();
+        bmc.execute(BmcAction::HardReset, true).await.unwrap();
+        assert_eq!(
+            log.lock().unwrap().drain(..).collect::<Vec<_>>(),
+            vec![
+                (
+                    "POST".to_owned(),
+                    reset.to_owned(),
+                    json!({ "ResetType": "On" })
+                ),
+                (
+                    "POST".to_owned(),
+                    reset.to_owned(),
+                    json!({ "ResetType": "ForceRestart" })
+                ),
+            ]
+        );
+
+        bmc.execute(BmcAction::OneTimeBoot(BootDevice::Pxe), true)
+            .await
+            .unwrap();
+        let log = log.lock().unwrap().drain(..).collect::<Vec<_>>();
+        assert_eq!(log[0].0, "PATCH");
+        assert_eq!(log[0].1, "/redfish/v1/Systems/1");
+        assert_eq!(
+            log[0].2,
+            json!({ "Boot": { "BootSourceOverrideEnabled": "Once", "BootSourceOverrideTarget": "Pxe" } })
+        );
+        assert_eq!(log[1].2, json!({ "ResetType": "ForceRestart" }));
+
+        let bmc = config(url, "wrong");
+        assert!(matches!(
+            bmc.execute(BmcAction::PowerOff, true).await,
+            Err(BmcError::Status(401))
+        ));
+    }
+
+    #[test]
+    fn validate() {
+        assert!(config("https://10.0.0.2".to_owned(), "").validate().is_ok());
+        assert!(config("10.0.0.2".to_owned(), "").validate().is_err());
+        let ipmi = BmcConfig {
+            protocol: BmcProtocol::Ipmi,
+            ..config("10.0.0.2:623".to_owned(), "")
+        };
+        assert!(ipmi.validate().is_ok());
+        let ipmi = BmcConfig {
+            address: "https://10.0.0.2".to_owned(),
+            ..ipmi
+        };
+        assert!(matches!(ipmi.validate(), Err(BmcError::Address(_))));
+    }
+}
@@ -0,0 +1,27 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// request the agent may leave unanswered when the os hangs
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub enum Graceful {
+    Shutdown,
+    Reboot,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub enum EscalationAction {
+    BmcHardReset,
+    BmcPowerOff,
+    // by the smart plug of the machine
+    PowerCycle,
+    // by the reset hook of the agent relaying magic packets for the machine
+    Relay,
+}
+
+/// out of band action taken when the agent doesn't answer a graceful request in timeout
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Escalation {
+    pub action: EscalationAction,
+    pub timeout: Duration,
+}
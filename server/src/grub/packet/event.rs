@@ -170,7 +170,7 @@ where
 
         registry.signals.push(signal, id);
 
-        Hook { ignitor: &self, id }
+        Hook { ignitor: self, id }
     }
 }
 
@@ -198,10 +198,7 @@ where
 
         registry.wakers.remove(&self.id).unwrap();
 
-        match registry.payloads.remove(&self.id) {
-            Some(payload) => Some(payload),
-            None => None,
-        }
+        registry.payloads.remove(&self.id)
     }
 }
 
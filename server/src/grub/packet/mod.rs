@@ -1,10 +1,31 @@
+mod bmc;
+mod escalation;
 mod event;
-pub(self) mod hashvec;
-pub(self) mod packet;
+mod hashvec;
+#[allow(clippy::module_inception)]
+mod packet;
+mod power;
+mod probe;
 mod wol;
 
+pub use bmc::BmcAction;
+pub use bmc::BmcConfig;
+pub use bmc::BmcError;
+pub use bmc::BmcProtocol;
+pub use bmc::BootDevice;
+pub use escalation::Escalation;
+pub use escalation::EscalationAction;
+pub use escalation::Graceful;
 pub use packet::Error;
-pub use packet::Packet;
-pub use packet::Packets;
 pub use packet::TcpPacket;
 pub use packet::TcpPackets;
+pub use packet::TcpRelay;
+pub use packet::TcpWaker;
+pub use power::PlugConfig;
+pub use power::PlugError;
+pub use power::PlugKind;
+pub use probe::Probe;
+pub use probe::ProbeConfig;
+pub use probe::PROBE_TIMEOUT;
+pub use wol::WolConfig;
+pub use wol::WolError;
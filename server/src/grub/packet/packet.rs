@@ -10,9 +10,26 @@ use paste::paste;
 use proto::prelude::{
     packets::host::Packet as HostP, packets::server::Packet as ServerP, ReadConn, WriteConn, ID,
 };
-use std::{pin::Pin, sync::Arc, time::Duration};
+use std::{
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use crate::grub::audit::{Audit, AuditEvent};
 
-use super::{event::EventHook, hashvec::HashVec, wol::MagicPacket};
+use super::{
+    bmc::{BmcAction, BmcConfig, BmcError},
+    escalation::{Escalation, EscalationAction, Graceful},
+    event::EventHook,
+    hashvec::HashVec,
+    power::{PlugConfig, PlugError},
+    probe::{ProbeConfig, PROBE_TIMEOUT},
+    wol::{MagicPacket, WolConfig, WolError},
+};
 
 pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
 
@@ -35,6 +52,10 @@ pub enum HostPTy {
     InitId,
     Shutdown,
     OsQuery,
+    WakeOnLan,
+    UefiQuery,
+    BootNext,
+    Reset,
 }
 
 impl HostPTy {
@@ -47,6 +68,10 @@ impl HostPTy {
             HostP::InitId => HostPTy::InitId,
             HostP::Shutdown => HostPTy::Shutdown,
             HostP::OsQuery(_) => HostPTy::OsQuery,
+            HostP::WakeOnLan(_) => HostPTy::WakeOnLan,
+            HostP::UefiQuery(_) => HostPTy::UefiQuery,
+            HostP::BootNext(_) => HostPTy::BootNext,
+            HostP::Reset(_) => HostPTy::Reset,
         }
     }
 }
@@ -118,14 +143,11 @@ where
     }
     async fn read(&self, ty: HostPTy) -> Result<HostP, Error> {
         loop {
-            match self.reader.try_lock() {
-                Some(mut reader) => {
-                    let res = reader.read().await?;
-                    let res_ty = HostPTy::from_packet(&res);
-                    let mut read_buffer = self.read_buffer.lock().await;
-                    read_buffer.push(res_ty, res);
-                }
-                None => {}
+            if let Some(mut reader) = self.reader.try_lock() {
+                let res = reader.read().await?;
+                let res_ty = HostPTy::from_packet(&res);
+                let mut read_buffer = self.read_buffer.lock().await;
+                read_buffer.push(res_ty, res);
             }
             self.reader.lock().await;
             let mut read_buffer = self.read_buffer.lock().await;
@@ -212,8 +234,23 @@ where
     raw: RwLock<Option<RawPacket<T>>>,
     event_hook: Arc<EventHook<[u8; 6], RawPacket<T>>>,
     mac_address: [u8; 6],
+    wol: WolConfig,
+    relay: Option<Relay<T>>,
+    probe: Option<ProbeConfig>,
+    bmc: Option<BmcConfig>,
+    plug: Option<PlugConfig>,
+    escalation: Option<Escalation>,
+    audit: Audit,
 }
 
+/// connection slot of another machine, which may be offline or busy booting
+pub type Relay<T> = Arc<Mutex<Option<Packet<T>>>>;
+
+// a relay answer quickly, or the magic packet is sent from this server
+const RELAY_TIMEOUT: Duration = Duration::from_secs(3);
+// the reset hook of a relay may have to reach a bmc or a plug first
+const RESET_TIMEOUT: Duration = Duration::from_secs(60);
+
 macro_rules! impl_write_packet {
     ($p:ident) => {
         paste! {
@@ -276,6 +313,10 @@ where
     impl_write_packet_signal! {GrubQuery}
     impl_write_packet_signal! {Ping}
     impl_write_packet_signal! {OsQuery}
+    impl_write_packet! {WakeOnLan}
+    impl_write_packet_signal! {UefiQuery}
+    impl_write_packet! {BootNext}
+    impl_write_packet! {Reset}
 
     impl_read_packet! {GrubQuery}
     impl_read_packet! {Ping}
@@ -283,14 +324,22 @@ where
     impl_read_packet_signal! {InitId}
     impl_read_packet_signal! {Shutdown}
     impl_read_packet! {OsQuery}
+    impl_read_packet! {WakeOnLan}
+    impl_read_packet! {UefiQuery}
+    impl_read_packet! {BootNext}
+    impl_read_packet! {Reset}
 
     pub async fn wait_reconnect(&self) -> Result<(), Error> {
-        let raw = self.raw.write().await.take().ok_or(Error::ClientOffline)?;
-        let new_raw = self.event_hook.wait(raw.handshake.mac_address).await;
+        self.disconnect().await;
+        let new_raw = self.event_hook.wait(self.mac_address).await;
         *self.raw.write().await = Some(new_raw);
         log::trace!("received distributed RawPacket");
         Ok(())
     }
+    /// drop the connection, the host is considered offline until it reconnect
+    pub async fn disconnect(&self) {
+        self.raw.write().await.take();
+    }
     pub fn get_mac_address(&self) -> &[u8; 6] {
         &self.mac_address
     }
@@ -308,18 +357,237 @@ where
             .uid = uid;
         Ok(())
     }
+    pub fn set_wol(&mut self, wol: WolConfig) {
+        self.wol = wol;
+    }
+    /// relay used by wol_reconnect if wol settings name one
+    pub fn set_relay(&mut self, relay: Option<Relay<T>>) {
+        self.relay = relay;
+    }
+    /// bound wol_reconnect and tell whether the host woke without its agent
+    pub fn set_probe(&mut self, probe: Option<ProbeConfig>) {
+        self.probe = probe;
+    }
+    pub fn set_bmc(&mut self, bmc: Option<BmcConfig>) {
+        self.bmc = bmc;
+    }
+    pub fn set_plug(&mut self, plug: Option<PlugConfig>) {
+        self.plug = plug;
+    }
+    /// without escalation, an unanswered graceful request is waited forever
+    pub fn set_escalation(&mut self, escalation: Option<Escalation>) {
+        self.escalation = escalation;
+    }
+    /// trail of the machine, where escalations are recorded
+    pub fn set_audit(&mut self, audit: Audit) {
+        self.audit = audit;
+    }
+    /// wait the answer of a graceful request, escalating out of band if it doesn't come in time
+    ///
+    /// once escalated, the host has been reset or powered off and `Error::Escalated` is returned
+    pub async fn graceful<R>(
+        &self,
+        request: Graceful,
+        answer: impl Future<Output = Result<R, Error>>,
+    ) -> Result<R, Error> {
+        let escalation = match &self.escalation {
+            Some(escalation) => escalation.clone(),
+            None => return answer.await,
+        };
+        if let Ok(res) = timeout(escalation.timeout, answer).await {
+            return res;
+        }
+        log::warn!(
+            "host {:x?} didn't answer {:?} in {:?}, escalating to {:?}",
+            self.mac_address,
+            request,
+            escalation.timeout,
+            escalation.action
+        );
+        let action = escalation.action;
+        let res = match action {
+            EscalationAction::BmcHardReset => self.bmc_reconnect(BmcAction::HardReset).await,
+            EscalationAction::BmcPowerOff => self.bmc_reconnect(BmcAction::PowerOff).await,
+            EscalationAction::PowerCycle => self.plug_reconnect().await,
+            EscalationAction::Relay => self.relay_reconnect().await,
+        };
+        match res {
+            Ok(()) => {
+                self.audit.record(AuditEvent::Escalated { request, action });
+                Err(Error::Escalated)
+            }
+            Err(err) => {
+                self.audit.record(AuditEvent::EscalationFailed {
+                    request,
+                    action,
+                    reason: err.to_string(),
+                });
+                Err(err)
+            }
+        }
+    }
+    /// ask the host to send a magic packet on its segment
+    pub async fn relay_wol(&self, req: proto::prelude::server::WakeOnLan) -> Result<(), Error> {
+        self.write_wake_on_lan(req).await?;
+        timeout(RELAY_TIMEOUT, self.read_wake_on_lan())
+            .await
+            .map_err(|_| Error::Timeout)??
+            .map_err(Error::Relay)
+    }
+    /// ask the host to run its reset hook on another machine of its segment
+    pub async fn relay_reset(&self, mac_address: [u8; 6]) -> Result<(), Error> {
+        self.write_reset(mac_address).await?;
+        timeout(RESET_TIMEOUT, self.read_reset())
+            .await
+            .map_err(|_| Error::Timeout)??
+            .map_err(Error::RelayReset)
+    }
     pub async fn wol_reconnect(&self) -> Result<(), Error> {
-        let magic_packet = MagicPacket::new(self.get_mac_address());
+        let waker = Waker::new(self.mac_address, self.wol.clone(), self.relay.clone());
+        // a misconfigured machine fails here instead of waiting for the timeout
+        waker.wake().await?;
+        self.wait_woken(Some(waker)).await
+    }
+    /// act on the host by its baseboard management controller, then wait for the agent if powered
+    pub async fn bmc_reconnect(&self, action: BmcAction) -> Result<(), Error> {
+        let bmc = self.bmc.as_ref().ok_or(Error::NoBmc)?;
+        let up = self.raw.read().await.is_some();
+        bmc.execute(action, up).await?;
+        match action {
+            BmcAction::PowerOff => {
+                self.disconnect().await;
+                Ok(())
+            }
+            _ => self.wait_woken(None).await,
+        }
+    }
+    /// power cycle the host by its smart plug, then wait for the agent
+    pub async fn plug_reconnect(&self) -> Result<(), Error> {
+        let plug = self.plug.as_ref().ok_or(Error::NoPlug)?;
+        plug.controller().power_cycle(plug.off_time).await?;
+        self.wait_woken(None).await
+    }
+    /// reset the host by the reset hook of its relay, then wait for the agent
+    pub async fn relay_reconnect(&self) -> Result<(), Error> {
+        // a relay left from previous settings is ignored
+        let relay = self
+            .relay
+            .as_ref()
+            .filter(|_| self.wol.relay.is_some())
+            .ok_or(Error::NoRelay)?;
+        match &*relay.lock().await {
+            Some(relay) => relay.relay_reset(self.mac_address).await?,
+            None => return Err(Error::RelayReset("relay offline".to_owned())),
+        }
+        self.wait_woken(None).await
+    }
+    /// without probe, wait for the agent forever, resending magic packets if there's a waker
+    async fn wait_woken(&self, waker: Option<Waker<T>>) -> Result<(), Error> {
+        let probe = match self.probe.clone() {
+            Some(probe) => probe,
+            None => {
+                let wol_handle = spawn(async move {
+                    loop {
+                        sleep(Duration::from_secs(1)).await;
+                        if let Some(waker) = &waker {
+                            waker.resend().await;
+                        }
+                    }
+                });
+                self.wait_reconnect().await?;
+                wol_handle.cancel().await;
+                return Ok(());
+            }
+        };
+
+        // magic packets are sent until the probe answer, the agent may follow later
+        let dur = probe.timeout;
+        let woke = Arc::new(AtomicBool::new(false));
+        let woke1 = woke.clone();
         let wol_handle = spawn(async move {
             loop {
-                magic_packet.send().await;
+                match probe.probe.reachable(PROBE_TIMEOUT).await {
+                    Ok(true) => {
+                        woke1.store(true, Ordering::Relaxed);
+                        break;
+                    }
+                    Ok(false) => {}
+                    Err(err) => log::warn!("fail to probe {:?}: {:?}", probe.probe, err),
+                }
                 sleep(Duration::from_secs(1)).await;
+                if let Some(waker) = &waker {
+                    waker.resend().await;
+                }
             }
         });
-        self.wait_reconnect().await?;
+        self.disconnect().await;
+        let raw = self.event_hook.timeout(self.mac_address, dur).await;
         wol_handle.cancel().await;
+        match raw {
+            Ok(raw) => {
+                *self.raw.write().await = Some(raw);
+                log::trace!("received distributed RawPacket");
+                Ok(())
+            }
+            Err(()) if woke.load(Ordering::Relaxed) => {
+                log::warn!("host {:x?} woke, but its agent is missing", self.mac_address);
+                Err(Error::AgentMissing)
+            }
+            Err(()) => {
+                log::warn!("host {:x?} didn't wake", self.mac_address);
+                Err(Error::NotWoken)
+            }
+        }
+    }
+}
+
+/// send magic packets of a machine, by its relay if any
+pub struct Waker<T>
+where
+    T: io::WriteExt + Unpin + io::ReadExt + Send + 'static,
+{
+    magic_packet: MagicPacket,
+    mac_address: [u8; 6],
+    wol: WolConfig,
+    relay: Option<Relay<T>>,
+}
+
+impl<T> Waker<T>
+where
+    T: io::WriteExt + Unpin + io::ReadExt + Send + 'static,
+{
+    pub fn new(mac_address: [u8; 6], wol: WolConfig, relay: Option<Relay<T>>) -> Self {
+        Self {
+            magic_packet: MagicPacket::new(&mac_address),
+            mac_address,
+            // a relay left from previous settings is ignored
+            relay: relay.filter(|_| wol.relay.is_some()),
+            wol,
+        }
+    }
+    /// through the relay if it is connected, falling back to send from this server
+    pub async fn wake(&self) -> Result<(), Error> {
+        if let Some(relay) = &self.relay {
+            match &*relay.lock().await {
+                Some(relay) => {
+                    let req = self.wol.relay_request(&self.mac_address);
+                    match relay.relay_wol(req).await {
+                        Ok(()) => return Ok(()),
+                        Err(err) => log::warn!("relay fail, send magic packet directly: {:?}", err),
+                    }
+                }
+                None => log::warn!("relay offline, send magic packet directly"),
+            }
+        }
+        self.magic_packet.send(&self.wol).await?;
         Ok(())
     }
+    /// while waiting for the host, a failure doesn't stop the wait
+    async fn resend(&self) {
+        if let Err(err) = self.wake().await {
+            log::warn!("fail to resend magic packet: {:?}", err);
+        }
+    }
 }
 
 pub struct Packets<T>
@@ -355,6 +623,13 @@ where
                 raw: RwLock::new(Some(raw)),
                 event_hook: self.event_hook.clone(),
                 mac_address,
+                wol: Default::default(),
+                relay: None,
+                probe: None,
+                bmc: None,
+                plug: None,
+                escalation: None,
+                audit: Default::default(),
             })),
             None => Ok(None),
         }
@@ -369,7 +644,302 @@ pub enum Error {
     Timeout,
     #[error("conn error")]
     Conn(#[from] proto::prelude::Error),
+    #[error("wake on lan error")]
+    Wol(#[from] WolError),
+    #[error("relay fail to send magic packet: {0}")]
+    Relay(String),
+    #[error("no relay configured")]
+    NoRelay,
+    #[error("relay fail to reset the host: {0}")]
+    RelayReset(String),
+    #[error("host fail to set BootNext: {0}")]
+    BootNext(String),
+    #[error("host answer the probe, but its agent didn't connect")]
+    AgentMissing,
+    #[error("host neither answer the probe nor connect")]
+    NotWoken,
+    #[error("no baseboard management controller configured")]
+    NoBmc,
+    #[error("baseboard management controller error")]
+    Bmc(#[from] BmcError),
+    #[error("no smart plug configured")]
+    NoPlug,
+    #[error("smart plug error")]
+    Plug(#[from] PlugError),
+    #[error("host didn't answer, escalated out of band")]
+    Escalated,
 }
 
 pub type TcpPacket = Packet<net::TcpStream>;
 pub type TcpPackets = Packets<net::TcpStream>;
+pub type TcpRelay = Relay<net::TcpStream>;
+pub type TcpWaker = Waker<net::TcpStream>;
+
+#[cfg(test)]
+mod test {
+    use std::{
+        marker::PhantomData,
+        net::Ipv4Addr,
+        sync::Arc,
+        time::{Duration, Instant},
+    };
+
+    use async_std::{net, sync::Mutex, task::spawn};
+    use proto::prelude::{
+        host, packets::host::Packet as HostP, packets::server::Packet as ServerP, ReadConn,
+        WriteConn, APIVERSION, PROTO_IDENT,
+    };
+
+    use async_std::sync::RwLock;
+    use futures_lite::future::pending;
+
+    use crate::grub::audit::{Audit, AuditEvent};
+
+    use super::{
+        super::bmc::test::{config, mock_redfish},
+        super::escalation::{Escalation, EscalationAction, Graceful},
+        super::probe::{Probe, ProbeConfig},
+        Error, MagicPacket, Packet, TcpPackets, Waker, WolConfig,
+    };
+
+    const MAC: [u8; 6] = [0x00, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e];
+    const RELAY: [u8; 6] = [0x00, 0x1a, 0x2b, 0x3c, 0x4d, 0x5f];
+
+    #[async_std::test]
+    async fn relay() {
+        let listener = net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let host = spawn(async move {
+            let stream = net::TcpStream::connect(addr).await.unwrap();
+            let mut reader = ReadConn::<_, ServerP> {
+                data_type: PhantomData,
+                stream: stream.clone(),
+            };
+            let mut writer = WriteConn::<_, HostP> {
+                data_type: PhantomData,
+                stream,
+            };
+            writer
+                .write(HostP::Handshake(host::Handshake {
+                    ident: PROTO_IDENT,
+                    mac_address: RELAY,
+                    uid: 1,
+                    version: APIVERSION,
+                }))
+                .await
+                .unwrap();
+            assert!(matches!(reader.read().await.unwrap(), ServerP::Handshake(_)));
+            let req = match reader.read().await.unwrap() {
+                ServerP::WakeOnLan(req) => req,
+                packet => panic!("unexpected {:?}", packet),
+            };
+            writer.write(HostP::WakeOnLan(Ok(()))).await.unwrap();
+            req
+        });
+
+        let (stream, _) = listener.accept().await.unwrap();
+        let relay = TcpPackets::default().connect(stream).await.unwrap().unwrap();
+        let waker = Waker {
+            magic_packet: MagicPacket::new(&MAC),
+            mac_address: MAC,
+            wol: WolConfig {
+                // unusable from this server, the relay must be taken
+                port: 8,
+                password: Some(vec![1, 2, 3, 4]),
+                relay: Some(RELAY),
+                ..Default::default()
+            },
+            relay: Some(Arc::new(Mutex::new(Some(relay)))),
+        };
+        waker.wake().await.unwrap();
+
+        let req = host.await;
+        assert_eq!(req.mac_address, MAC);
+        assert_eq!(req.port, 8);
+        assert_eq!(req.password, Some(vec![1, 2, 3, 4]));
+
+        // an offline relay fall back to this server, which refuse the port
+        let waker = Waker {
+            relay: Some(Default::default()),
+            ..waker
+        };
+        assert!(waker.wake().await.is_err());
+    }
+
+    #[async_std::test]
+    async fn relay_escalation() {
+        let listener = net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let host = spawn(async move {
+            let stream = net::TcpStream::connect(addr).await.unwrap();
+            let mut reader = ReadConn::<_, ServerP> {
+                data_type: PhantomData,
+                stream: stream.clone(),
+            };
+            let mut writer = WriteConn::<_, HostP> {
+                data_type: PhantomData,
+                stream,
+            };
+            writer
+                .write(HostP::Handshake(host::Handshake {
+                    ident: PROTO_IDENT,
+                    mac_address: RELAY,
+                    uid: 1,
+                    version: APIVERSION,
+                }))
+                .await
+                .unwrap();
+            assert!(matches!(reader.read().await.unwrap(), ServerP::Handshake(_)));
+            let mac_address = match reader.read().await.unwrap() {
+                ServerP::Reset(mac_address) => mac_address,
+                packet => panic!("unexpected {:?}", packet),
+            };
+            let res = Err("no reset hook configured".to_owned());
+            writer.write(HostP::Reset(res)).await.unwrap();
+            mac_address
+        });
+
+        let (stream, _) = listener.accept().await.unwrap();
+        let relay = TcpPackets::default().connect(stream).await.unwrap().unwrap();
+        let audit = Audit::default();
+        let packet: Packet<net::TcpStream> = Packet {
+            raw: RwLock::new(None),
+            event_hook: Default::default(),
+            mac_address: MAC,
+            wol: WolConfig {
+                relay: Some(RELAY),
+                ..Default::default()
+            },
+            relay: Some(Arc::new(Mutex::new(Some(relay)))),
+            probe: None,
+            bmc: None,
+            plug: None,
+            escalation: Some(Escalation {
+                action: EscalationAction::Relay,
+                timeout: Duration::from_millis(100),
+            }),
+            audit: audit.clone(),
+        };
+
+        let answer = pending::<Result<(), Error>>();
+        assert!(matches!(
+            packet.graceful(Graceful::Shutdown, answer).await,
+            Err(Error::RelayReset(_))
+        ));
+        assert_eq!(host.await, MAC);
+        assert!(matches!(
+            audit.entries()[0].event,
+            AuditEvent::EscalationFailed { action: EscalationAction::Relay, .. }
+        ));
+
+        // the relay must be named by wake on lan settings
+        let packet = Packet {
+            wol: Default::default(),
+            ..packet
+        };
+        let answer = pending::<Result<(), Error>>();
+        assert!(matches!(
+            packet.graceful(Graceful::Shutdown, answer).await,
+            Err(Error::NoRelay)
+        ));
+    }
+
+    #[async_std::test]
+    async fn probe() {
+        let listener = net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let host = spawn(async move {
+            let stream = net::TcpStream::connect(addr).await.unwrap();
+            let mut writer = WriteConn::<_, HostP> {
+                data_type: PhantomData,
+                stream,
+            };
+            writer
+                .write(HostP::Handshake(host::Handshake {
+                    ident: PROTO_IDENT,
+                    mac_address: MAC,
+                    uid: 1,
+                    version: APIVERSION,
+                }))
+                .await
+                .unwrap();
+            writer
+        });
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut packet = TcpPackets::default().connect(stream).await.unwrap().unwrap();
+        let _host = host.await;
+        packet.set_wol(WolConfig {
+            target: Some(Ipv4Addr::LOCALHOST),
+            ..Default::default()
+        });
+
+        // the os is up, answering on a port, but the agent never reconnect
+        let service = net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = service.local_addr().unwrap().port();
+        let config = ProbeConfig {
+            probe: Probe::Tcp {
+                address: Ipv4Addr::LOCALHOST,
+                port,
+            },
+            timeout: Duration::from_secs(2),
+        };
+        packet.set_probe(Some(config.clone()));
+        let start = Instant::now();
+        assert!(matches!(packet.wol_reconnect().await, Err(Error::AgentMissing)));
+        assert!(start.elapsed() >= config.timeout);
+
+        drop(service);
+        assert!(matches!(packet.wol_reconnect().await, Err(Error::NotWoken)));
+    }
+
+    #[async_std::test]
+    async fn escalation() {
+        let (url, log) = mock_redfish().await;
+        let audit = Audit::default();
+        let mut packet: Packet<net::TcpStream> = Packet {
+            raw: RwLock::new(None),
+            event_hook: Default::default(),
+            mac_address: MAC,
+            wol: Default::default(),
+            relay: None,
+            probe: None,
+            bmc: Some(config(url.clone(), "secret")),
+            plug: None,
+            escalation: Some(Escalation {
+                action: EscalationAction::BmcPowerOff,
+                timeout: Duration::from_millis(100),
+            }),
+            audit: audit.clone(),
+        };
+
+        // a hung agent never answer
+        let answer = pending::<Result<(), Error>>();
+        assert!(matches!(
+            packet.graceful(Graceful::Shutdown, answer).await,
+            Err(Error::Escalated)
+        ));
+        assert_eq!(log.lock().unwrap()[0].2["ResetType"], "ForceOff");
+        assert_eq!(
+            audit.entries()[0].event,
+            AuditEvent::Escalated {
+                request: Graceful::Shutdown,
+                action: EscalationAction::BmcPowerOff,
+            }
+        );
+
+        packet.set_bmc(Some(config(url, "wrong")));
+        let answer = pending::<Result<(), Error>>();
+        assert!(matches!(
+            packet.graceful(Graceful::Reboot, answer).await,
+            Err(Error::Bmc(_))
+        ));
+        assert!(matches!(
+            audit.entries()[1].event,
+            AuditEvent::EscalationFailed { request: Graceful::Reboot, .. }
+        ));
+
+        // answered in time, nothing is escalated
+        assert_eq!(packet.graceful(Graceful::Reboot, async { Ok(1) }).await.unwrap(), 1);
+        assert_eq!(audit.entries().len(), 2);
+    }
+}
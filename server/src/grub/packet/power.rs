@@ -0,0 +1,253 @@
+use std::time::Duration;
+
+use async_std::task::sleep;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use surf::{http::auth::BasicAuth, Url};
+
+// plugs are on the local network and answer at once
+const PLUG_TIMEOUT: Duration = Duration::from_secs(5);
+// local apis of both firmwares only know this user
+const PLUG_USERNAME: &str = "admin";
+
+#[derive(thiserror::Error, Debug)]
+pub enum PlugError {
+    #[error("{0} isn't an http url of a plug")]
+    Address(String),
+    #[error("plug request fail: {0}")]
+    Http(String),
+    #[error("plug answer status {0}")]
+    Status(u16),
+    #[error("plug didn't switch {0}")]
+    Switch(&'static str),
+    #[error("escalation of the machine use the plug, change it first")]
+    Escalation,
+}
+
+impl From<surf::Error> for PlugError {
+    fn from(err: surf::Error) -> Self {
+        PlugError::Http(err.to_string())
+    }
+}
+
+/// switch the power of a machine from outside, where a magic packet can't wake it
+#[async_trait]
+pub trait PowerController: Send + Sync {
+    async fn switch(&self, on: bool) -> Result<(), PlugError>;
+    /// cut the power for `off_time`, then restore it
+    async fn power_cycle(&self, off_time: Duration) -> Result<(), PlugError> {
+        self.switch(false).await?;
+        sleep(off_time).await;
+        self.switch(true).await
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub enum PlugKind {
+    Tasmota,
+    Shelly,
+}
+
+/// network smart plug feeding a machine, which must power on when the power is restored
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct PlugConfig {
+    pub kind: PlugKind,
+    // base url of the local http api
+    pub address: String,
+    // relay of a multi-outlet plug, starting at 0
+    pub channel: u8,
+    // web admin password, if the plug is protected
+    pub password: Option<String>,
+    // time without power, long enough for the power supply to drain
+    pub off_time: Duration,
+}
+
+impl PlugConfig {
+    pub fn validate(&self) -> Result<(), PlugError> {
+        self.url("").map(|_| ())
+    }
+    pub fn controller(&self) -> Box<dyn PowerController> {
+        match self.kind {
+            PlugKind::Tasmota => Box::new(Tasmota(self.clone())),
+            PlugKind::Shelly => Box::new(Shelly(self.clone())),
+        }
+    }
+    fn url(&self, path: &str) -> Result<Url, PlugError> {
+        let url = format!("{}{}", self.address.trim_end_matches('/'), path);
+        match Url::parse(&url) {
+            Ok(url) if (url.scheme() == "http" || url.scheme() == "https") && url.has_host() => {
+                Ok(url)
+            }
+            _ => Err(PlugError::Address(self.address.clone())),
+        }
+    }
+    async fn get(&self, url: Url, auth: Option<BasicAuth>) -> Result<Value, PlugError> {
+        let config = surf::Config::new().set_timeout(Some(PLUG_TIMEOUT));
+        // the h1 client is built without error
+        let Ok(client): Result<surf::Client, _> = config.try_into();
+        let mut req = surf::get(url);
+        if let Some(auth) = auth {
+            req = req.header(auth.name(), auth.value());
+        }
+        let mut res = client.send(req).await?;
+        if !res.status().is_success() {
+            return Err(PlugError::Status(res.status().into()));
+        }
+        Ok(res.body_json().await?)
+    }
+}
+
+fn state(on: bool) -> &'static str {
+    match on {
+        true => "on",
+        false => "off",
+    }
+}
+
+/// tasmota firmware, commands are sent by `/cm?cmnd=`
+struct Tasmota(PlugConfig);
+
+#[async_trait]
+impl PowerController for Tasmota {
+    async fn switch(&self, on: bool) -> Result<(), PlugError> {
+        let mut url = self.0.url("/cm")?;
+        let relay = self.0.channel as u16 + 1;
+        url.query_pairs_mut()
+            .append_pair("cmnd", &format!("Power{} {}", relay, state(on)));
+        // tasmota doesn't take basic auth, only credentials in the query
+        if let Some(password) = &self.0.password {
+            url.query_pairs_mut()
+                .append_pair("user", PLUG_USERNAME)
+                .append_pair("password", password);
+        }
+        let res = self.0.get(url, None).await?;
+        // a single relay device answer "POWER" instead of "POWER1"
+        let power = res[format!("POWER{}", relay)]
+            .as_str()
+            .or_else(|| res["POWER"].as_str().filter(|_| relay == 1));
+        match power {
+            Some(power) if power.eq_ignore_ascii_case(state(on)) => Ok(()),
+            _ => Err(PlugError::Switch(state(on))),
+        }
+    }
+}
+
+/// shelly firmware by its gen1 api, `/relay/<channel>?turn=`, also served by gen2 devices
+struct Shelly(PlugConfig);
+
+#[async_trait]
+impl PowerController for Shelly {
+    async fn switch(&self, on: bool) -> Result<(), PlugError> {
+        let mut url = self.0.url(&format!("/relay/{}", self.0.channel))?;
+        url.query_pairs_mut().append_pair("turn", state(on));
+        let auth = self
+            .0
+            .password
+            .as_ref()
+            .map(|password| BasicAuth::new(PLUG_USERNAME, password));
+        let res = self.0.get(url, auth).await?;
+        match res["ison"].as_bool() {
+            Some(ison) if ison == on => Ok(()),
+            _ => Err(PlugError::Switch(state(on))),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(super) mod test {
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
+    };
+
+    use async_std::task::spawn;
+    use serde_json::json;
+    use surf::http::auth::BasicAuth;
+    use tide::listener::{Listener, ToListener};
+
+    use super::{PlugConfig, PlugError, PlugKind};
+
+    /// every switch the plug made, `true` for on
+    pub(in super::super) type Log = Arc<Mutex<Vec<bool>>>;
+
+    /// a plug answering both tasmota and shelly apis, protected by password "secret"
+    pub(in super::super) async fn mock_plug() -> (String, Log) {
+        let log: Log = Default::default();
+        let mut app = tide::with_state(log.clone());
+        app.at("/cm").get(tasmota);
+        app.at("/relay/:channel").get(shelly);
+        let mut listener = "127.0.0.1:0".to_listener().unwrap();
+        listener.bind(app).await.unwrap();
+        let url = listener.info()[0].connection().to_owned();
+        spawn(async move { listener.accept().await });
+        (url, log)
+    }
+
+    async fn tasmota(req: tide::Request<Log>) -> tide::Result {
+        let query: HashMap<String, String> = req.query()?;
+        if query.get("user").map(String::as_str) != Some("admin")
+            || query.get("password").map(String::as_str) != Some("secret")
+        {
+            return Ok(json!({ "WARNING": "Need user=<username>&password=<password>" }).into());
+        }
+        let (power, state) = query["cmnd"].split_once(' ').unwrap();
+        let on = state == "on";
+        req.state().lock().unwrap().push(on);
+        Ok(json!({ power.to_uppercase(): state.to_uppercase() }).into())
+    }
+
+    async fn shelly(req: tide::Request<Log>) -> tide::Result {
+        if !matches!(
+            BasicAuth::from_headers(&req),
+            Ok(Some(auth)) if auth.username() == "admin" && auth.password() == "secret"
+        ) {
+            return Ok(tide::Response::new(401));
+        }
+        let query: HashMap<String, String> = req.query()?;
+        let on = query["turn"] == "on";
+        req.state().lock().unwrap().push(on);
+        Ok(json!({ "ison": on, "has_timer": false, "source": "http" }).into())
+    }
+
+    pub(in super::super) fn config(kind: PlugKind, address: String, password: &str) -> PlugConfig {
+        PlugConfig {
+            kind,
+            address,
+            channel: 0,
+            password: Some(password.to_owned()),
+            off_time: Duration::from_millis(100),
+        }
+    }
+
+    #[async_std::test]
+    async fn plug() {
+        let (url, log) = mock_plug().await;
+        for kind in [PlugKind::Tasmota, PlugKind::Shelly] {
+            let plug = config(kind, url.clone(), "secret");
+            let start = Instant::now();
+            plug.controller().power_cycle(plug.off_time).await.unwrap();
+            assert!(start.elapsed() >= plug.off_time);
+            assert_eq!(
+                log.lock().unwrap().drain(..).collect::<Vec<_>>(),
+                vec![false, true]
+            );
+
+            let plug = config(kind, url.clone(), "wrong");
+            assert!(matches!(
+                plug.controller().switch(true).await,
+                Err(PlugError::Switch("on") | PlugError::Status(401))
+            ));
+            assert!(log.lock().unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn validate() {
+        let plug = config(PlugKind::Shelly, "http://10.0.0.3/".to_owned(), "");
+        assert!(plug.validate().is_ok());
+        let plug = config(PlugKind::Tasmota, "10.0.0.3".to_owned(), "");
+        assert!(matches!(plug.validate(), Err(PlugError::Address(_))));
+    }
+}
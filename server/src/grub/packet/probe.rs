@@ -0,0 +1,147 @@
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, SocketAddrV4, UdpSocket},
+    time::{Duration, Instant},
+};
+
+use async_std::{future::timeout, net::TcpStream, task::spawn_blocking};
+use serde::{Deserialize, Serialize};
+use socket2::{Domain, Protocol, Socket, Type};
+
+const ECHO_REQUEST: u8 = 8;
+const ECHO_REPLY: u8 = 0;
+// a probe answer in this long, or the host is considered down
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// check whether a host is up without its agent
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum Probe {
+    // up once a service listening on port accept the connection
+    Tcp { address: Ipv4Addr, port: u16 },
+    Icmp { address: Ipv4Addr },
+}
+
+/// a probe and how long a woken machine may take to answer it
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct ProbeConfig {
+    pub probe: Probe,
+    pub timeout: Duration,
+}
+
+impl Probe {
+    /// whether the host answer in `dur`, error only if the probe can't be sent
+    pub async fn reachable(&self, dur: Duration) -> io::Result<bool> {
+        match *self {
+            Probe::Tcp { address, port } => {
+                let connect = timeout(dur, TcpStream::connect((address, port))).await;
+                Ok(matches!(connect, Ok(Ok(_))))
+            }
+            Probe::Icmp { address } => spawn_blocking(move || ping(address, dur)).await,
+        }
+    }
+}
+
+/// an unprivileged ping socket if allowed by `net.ipv4.ping_group_range`, or a raw socket
+fn ping(address: Ipv4Addr, dur: Duration) -> io::Result<bool> {
+    let (socket, raw) = match Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::ICMPV4)) {
+        Ok(socket) => (socket, false),
+        Err(_) => (
+            Socket::new(Domain::IPV4, Type::RAW, Some(Protocol::ICMPV4))?,
+            true,
+        ),
+    };
+    let ident: u16 = rand::random();
+    let mut request = [ECHO_REQUEST, 0, 0, 0, 0, 0, 0, 1];
+    request[4..6].copy_from_slice(&ident.to_be_bytes());
+    let sum = checksum(&request);
+    request[2..4].copy_from_slice(&sum.to_be_bytes());
+    socket.send_to(&request, &SocketAddrV4::new(address, 0).into())?;
+
+    let socket: UdpSocket = socket.into();
+    let deadline = Instant::now() + dur;
+    let mut buf = [0; 1500];
+    loop {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Ok(false);
+        }
+        socket.set_read_timeout(Some(left))?;
+        let (len, from) = match socket.recv_from(&mut buf) {
+            Ok(res) => res,
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                return Ok(false)
+            }
+            Err(err) => return Err(err),
+        };
+        // raw socket receive the ip header, whose length is in the low nibble
+        let reply = match raw {
+            true => buf[..len].get(((buf[0] & 0xf) as usize * 4)..).unwrap_or(&[]),
+            false => &buf[..len],
+        };
+        // the kernel rewrite ident of ping socket, so only raw socket can check it
+        if from.ip() == IpAddr::V4(address)
+            && reply.first() == Some(&ECHO_REPLY)
+            && (!raw || reply.get(4..6) == Some(&ident.to_be_bytes()[..]))
+        {
+            return Ok(true);
+        }
+    }
+}
+
+fn checksum(data: &[u8]) -> u16 {
+    let mut sum: u32 = data
+        .chunks(2)
+        .map(|x| u16::from_be_bytes([x[0], *x.get(1).unwrap_or(&0)]) as u32)
+        .sum();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+#[cfg(test)]
+mod test {
+    use std::{io, net::Ipv4Addr, time::Duration};
+
+    use async_std::net::TcpListener;
+
+    use super::{checksum, Probe};
+
+    #[test]
+    fn icmp_checksum() {
+        let mut request = [8, 0, 0, 0, 0x12, 0x34, 0, 1];
+        let sum = checksum(&request);
+        request[2..4].copy_from_slice(&sum.to_be_bytes());
+        assert_eq!(checksum(&request), 0);
+    }
+
+    #[async_std::test]
+    async fn tcp() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let probe = Probe::Tcp {
+            address: Ipv4Addr::LOCALHOST,
+            port,
+        };
+        assert!(probe.reachable(Duration::from_secs(1)).await.unwrap());
+        drop(listener);
+        assert!(!probe.reachable(Duration::from_secs(1)).await.unwrap());
+    }
+
+    #[async_std::test]
+    async fn icmp() {
+        let probe = Probe::Icmp {
+            address: Ipv4Addr::LOCALHOST,
+        };
+        match probe.reachable(Duration::from_secs(1)).await {
+            Ok(reachable) => assert!(reachable),
+            // neither ping socket nor raw socket allowed for this user
+            Err(err) => assert_eq!(err.kind(), io::ErrorKind::PermissionDenied),
+        }
+    }
+}
@@ -1,9 +1,103 @@
-use std::net::Ipv4Addr;
+use std::{
+    io,
+    net::{Ipv4Addr, SocketAddrV4},
+};
 
 use async_std::net::UdpSocket;
+use proto::prelude::server;
+use serde::{Deserialize, Serialize};
 
 const SIX_FF: [u8; 6] = [0xFF; 6];
 
+#[derive(thiserror::Error, Debug)]
+pub enum WolError {
+    #[error("no interface named {0} with an ipv4 address")]
+    Interface(String),
+    #[error("port {0} isn't 7 or 9")]
+    Port(u16),
+    #[error("SecureOn password must be 4 or 6 bytes, got {0}")]
+    Password(usize),
+    #[error("relay {0:x?} isn't another enrolled machine")]
+    Relay([u8; 6]),
+    #[error("escalation of the machine use the relay, change it first")]
+    Escalation,
+    #[error("fail to send magic packet")]
+    Io(#[from] io::Error),
+}
+
+/// how magic packets of a machine are sent
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct WolConfig {
+    // outgoing interface, its address is bound and its subnet broadcast targeted
+    pub interface: Option<String>,
+    // source address to bind, overriding the one of interface
+    pub source: Option<Ipv4Addr>,
+    // directed subnet broadcast or unicast, overriding the one of interface
+    pub target: Option<Ipv4Addr>,
+    pub port: u16,
+    // appended to the payload for NICs requiring SecureOn
+    pub password: Option<Vec<u8>>,
+    // enrolled machine on the segment of this one, sending magic packets for it
+    pub relay: Option<[u8; 6]>,
+}
+
+impl Default for WolConfig {
+    fn default() -> Self {
+        Self {
+            interface: None,
+            source: None,
+            target: None,
+            port: 9,
+            password: None,
+            relay: None,
+        }
+    }
+}
+
+impl WolConfig {
+    pub fn validate(&self) -> Result<(), WolError> {
+        if self.port != 7 && self.port != 9 {
+            return Err(WolError::Port(self.port));
+        }
+        match &self.password {
+            Some(password) if password.len() != 4 && password.len() != 6 => {
+                Err(WolError::Password(password.len()))
+            }
+            _ => Ok(()),
+        }
+    }
+    /// interface and source are of this server, a relay only get target
+    pub fn relay_request(&self, mac_address: &[u8; 6]) -> server::WakeOnLan {
+        server::WakeOnLan {
+            mac_address: *mac_address,
+            target: self.target,
+            port: self.port,
+            password: self.password.clone(),
+        }
+    }
+    /// address to bind and to send to, the interface is looked up every time as dhcp may change it
+    fn resolve(&self) -> Result<(SocketAddrV4, SocketAddrV4), WolError> {
+        self.validate()?;
+        let (mut source, mut target) = (Ipv4Addr::UNSPECIFIED, Ipv4Addr::BROADCAST);
+        if let Some(name) = &self.interface {
+            let addr = if_addrs::get_if_addrs()?
+                .into_iter()
+                .find_map(|interface| match interface.addr {
+                    if_addrs::IfAddr::V4(addr) if interface.name == *name => Some(addr),
+                    _ => None,
+                })
+                .ok_or_else(|| WolError::Interface(name.clone()))?;
+            source = addr.ip;
+            // loopback and point-to-point links have no broadcast
+            target = addr.broadcast.unwrap_or(target);
+        }
+        Ok((
+            SocketAddrV4::new(self.source.unwrap_or(source), 0),
+            SocketAddrV4::new(self.target.unwrap_or(target), self.port),
+        ))
+    }
+}
+
 pub struct MagicPacket {
     packet: Vec<u8>,
 }
@@ -13,7 +107,7 @@ impl MagicPacket {
         log::debug!("construct MagicPacket of {:x?}", mac_address);
         MagicPacket {
             packet: {
-                let mac_address: &[u8; 6] = &mac_address;
+                let mac_address: &[u8; 6] = mac_address;
                 let dst: &mut Vec<u8> = &mut vec![0_u8; 0];
 
                 dst.extend_from_slice(&SIX_FF);
@@ -26,12 +120,99 @@ impl MagicPacket {
             },
         }
     }
-    pub async fn send(&self) {
-        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await.unwrap();
-        socket.set_broadcast(true).unwrap();
-        socket
-            .send_to(&self.packet, (Ipv4Addr::BROADCAST, 9))
+    pub async fn send(&self, config: &WolConfig) -> Result<(), WolError> {
+        let (source, target) = config.resolve()?;
+        let packet = match &config.password {
+            Some(password) => [self.packet.as_slice(), password].concat(),
+            None => self.packet.clone(),
+        };
+        send_to(&packet, source, target).await
+    }
+}
+
+async fn send_to(
+    packet: &[u8],
+    source: SocketAddrV4,
+    target: SocketAddrV4,
+) -> Result<(), WolError> {
+    let socket = UdpSocket::bind(source).await?;
+    socket.set_broadcast(true)?;
+    socket.send_to(packet, target).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::{Ipv4Addr, SocketAddrV4};
+
+    use async_std::net::UdpSocket;
+
+    use super::{send_to, MagicPacket, WolConfig, WolError};
+
+    const MAC: [u8; 6] = [0x00, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e];
+
+    #[test]
+    fn resolve() {
+        let (source, target) = WolConfig::default().resolve().unwrap();
+        assert_eq!(source, SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0));
+        assert_eq!(target, SocketAddrV4::new(Ipv4Addr::BROADCAST, 9));
+
+        let config = WolConfig {
+            interface: Some("lo".to_owned()),
+            port: 7,
+            ..Default::default()
+        };
+        let (source, target) = config.resolve().unwrap();
+        assert_eq!(*source.ip(), Ipv4Addr::LOCALHOST);
+        assert_eq!(target, SocketAddrV4::new(Ipv4Addr::BROADCAST, 7));
+
+        let config = WolConfig {
+            interface: Some("lo".to_owned()),
+            source: Some(Ipv4Addr::new(127, 0, 0, 2)),
+            target: Some(Ipv4Addr::new(192, 168, 1, 255)),
+            ..Default::default()
+        };
+        let (source, target) = config.resolve().unwrap();
+        assert_eq!(*source.ip(), Ipv4Addr::new(127, 0, 0, 2));
+        assert_eq!(
+            target,
+            SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 255), 9)
+        );
+
+        let config = WolConfig {
+            interface: Some("nonexistent0".to_owned()),
+            ..Default::default()
+        };
+        assert!(matches!(config.resolve(), Err(WolError::Interface(_))));
+        let config = WolConfig {
+            port: 8,
+            ..Default::default()
+        };
+        assert!(matches!(config.resolve(), Err(WolError::Port(8))));
+        let config = WolConfig {
+            password: Some(vec![1, 2, 3]),
+            ..Default::default()
+        };
+        assert!(matches!(config.resolve(), Err(WolError::Password(3))));
+    }
+
+    #[async_std::test]
+    async fn send() {
+        let receiver = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let target = match receiver.local_addr().unwrap() {
+            std::net::SocketAddr::V4(addr) => addr,
+            _ => unreachable!(),
+        };
+        let packet = [MagicPacket::new(&MAC).packet, vec![1, 2, 3, 4, 5, 6]].concat();
+        send_to(&packet, SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0), target)
             .await
             .unwrap();
+
+        let mut buf = [0; 128];
+        let (len, _) = receiver.recv_from(&mut buf).await.unwrap();
+        assert_eq!(len, 6 + 16 * 6 + 6);
+        assert_eq!(buf[..6], [0xff; 6]);
+        assert!(buf[6..102].chunks(6).all(|x| x == MAC));
+        assert_eq!(buf[102..108], [1, 2, 3, 4, 5, 6]);
     }
 }
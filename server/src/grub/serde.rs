@@ -5,18 +5,34 @@ use std::{
 };
 
 use super::{
-    bootgraph::BootGraph,
-    machine::{Machine, Server},
+    audit::{Audit, AuditEntry},
+    bootgraph::{BootGraph, BootGraphV0},
+    machine::{Machine, OsLabel, Server},
+    packet::{BmcConfig, Escalation, PlugConfig, ProbeConfig, WolConfig},
 };
 use ::serde::{Deserialize, Serialize};
-use async_std::{
-    fs::File,
-    io::{ReadExt, WriteExt},
-    sync::Mutex,
-};
+use async_std::sync::{Mutex, RwLock};
 use async_trait::async_trait;
+use bincode::Options;
 use indexmap::IndexMap;
-use proto::prelude::SERVER_PORT;
+use proto::prelude::{ID, SERVER_PORT};
+
+// start of every save file, followed by the version of its format
+const SAVE_MAGIC: [u8; 8] = *b"grubwol\0";
+/// bumped whenever a saved type change, files of another version are refused instead of misread
+pub const SAVE_VERSION: u32 = 1;
+
+#[derive(thiserror::Error, Debug)]
+pub enum SaveError {
+    #[error("fail to access save file")]
+    Io(#[from] std::io::Error),
+    #[error("broken save file")]
+    Bincode(#[from] bincode::Error),
+    #[error("save file without version, neither of this server nor of the first release, move it away to start over")]
+    Unversioned,
+    #[error("save file of version {0}, expected version {SAVE_VERSION}")]
+    Version(u32),
+}
 
 #[async_trait]
 pub trait AsyncState<O>
@@ -26,26 +42,41 @@ where
 {
     async fn serde(machine: &O) -> Self;
     fn deserde(self) -> O;
-    async fn load(path: &Path) -> O {
-        let save = if path.exists() && path.is_file() {
-            let mut file = File::open(path).await.unwrap();
-
-            let buf = &mut Vec::new();
-            file.read_to_end(buf).await.unwrap();
-
-            bincode::deserialize::<Self>(buf).unwrap()
-        } else {
-            Default::default()
+    /// read a save file written before the header was introduced
+    fn migrate(_buf: &[u8]) -> Option<Self> {
+        None
+    }
+    /// default if there is no save file yet
+    async fn load(path: &Path) -> Result<O, SaveError> {
+        if !path.is_file() {
+            return Ok(Self::default().deserde());
+        }
+        let buf = async_std::fs::read(path).await?;
+        let header: Option<([u8; 8], u32)> = bincode::deserialize(&buf).ok();
+        let version = match header {
+            Some((magic, version)) if magic == SAVE_MAGIC => version,
+            _ => {
+                let save = Self::migrate(&buf).ok_or(SaveError::Unversioned)?;
+                log::warn!("migrated unversioned save file, it's rewritten on next save");
+                return Ok(save.deserde());
+            }
         };
-        save.deserde()
+        if version != SAVE_VERSION {
+            return Err(SaveError::Version(version));
+        }
+        let (_, _, save): ([u8; 8], u32, Self) = bincode::deserialize(&buf)?;
+        Ok(save.deserde())
     }
-    async fn save(src: &O, path: &Path) {
-        let buf = bincode::serialize(&Self::serde(src).await).unwrap();
+    async fn save(src: &O, path: &Path) -> Result<(), SaveError> {
+        let buf = bincode::serialize(&(SAVE_MAGIC, SAVE_VERSION, Self::serde(src).await))?;
 
         log::trace!("Serialized save file");
-        let mut file = File::open(path).await.unwrap();
-        file.write_all(&buf).await.unwrap();
+        // a crash while writing must not corrupt the last save
+        let tmp = path.with_extension("tmp");
+        async_std::fs::write(&tmp, buf).await?;
+        async_std::fs::rename(&tmp, path).await?;
         log::info!("Saving Done");
+        Ok(())
     }
 }
 
@@ -54,23 +85,47 @@ pub struct MachineSave {
     display_name: String,
     mac_address: [u8; 6],
     boot_graph: BootGraph,
+    labels: IndexMap<ID, OsLabel>,
+    wol: WolConfig,
+    standalone: bool,
+    probe: Option<ProbeConfig>,
+    bmc: Option<BmcConfig>,
+    plug: Option<PlugConfig>,
+    escalation: Option<Escalation>,
+    audit: Vec<AuditEntry>,
 }
 
 #[async_trait]
 impl AsyncState<Machine> for MachineSave {
     async fn serde(machine: &Machine) -> MachineSave {
         MachineSave {
-            display_name: (&*machine.display_name.lock().await).clone(),
-            mac_address: machine.mac_address.clone(),
-            boot_graph: machine.boot_graph.clone(),
+            display_name: machine.display_name.lock().await.clone(),
+            mac_address: machine.mac_address,
+            boot_graph: machine.boot_graph.read().await.clone(),
+            labels: machine.labels.lock().await.clone(),
+            wol: machine.wol.lock().await.clone(),
+            standalone: machine.standalone,
+            probe: machine.probe.lock().await.clone(),
+            bmc: machine.bmc.lock().await.clone(),
+            plug: machine.plug.lock().await.clone(),
+            escalation: machine.escalation.lock().await.clone(),
+            audit: machine.audit.entries(),
         }
     }
     fn deserde(self) -> Machine {
         Machine {
             display_name: Mutex::new(self.display_name),
             mac_address: self.mac_address,
-            boot_graph: self.boot_graph,
+            boot_graph: RwLock::new(self.boot_graph),
+            labels: Mutex::new(self.labels),
             packet: Default::default(),
+            wol: Mutex::new(self.wol),
+            standalone: self.standalone,
+            probe: Mutex::new(self.probe),
+            bmc: Mutex::new(self.bmc),
+            plug: Mutex::new(self.plug),
+            escalation: Mutex::new(self.escalation),
+            audit: Audit::new(self.audit),
         }
     }
 }
@@ -85,11 +140,23 @@ impl AsyncState<Server> for ServerSave {
     async fn serde(server: &Server) -> ServerSave {
         let mut machines = IndexMap::new();
         for (mac, machine) in &*(server.machines.lock().await) {
-            machines.insert(mac.clone(), MachineSave::serde(&**machine).await);
+            machines.insert(*mac, MachineSave::serde(&**machine).await);
         }
-        let machines = machines.into();
         ServerSave { machines }
     }
+    fn migrate(buf: &[u8]) -> Option<ServerSave> {
+        // written by bincode::serialize, trailing bytes mean it's something else
+        let old: ServerSaveV0 = bincode::DefaultOptions::new()
+            .with_fixint_encoding()
+            .deserialize(buf)
+            .ok()?;
+        let machines = old
+            .machines
+            .into_iter()
+            .map(|(mac, machine)| (mac, machine.into()))
+            .collect();
+        Some(ServerSave { machines })
+    }
     fn deserde(self) -> Server {
         let bind_host = IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0));
         let socket = std::net::SocketAddr::new(bind_host, SERVER_PORT);
@@ -107,3 +174,115 @@ impl AsyncState<Server> for ServerSave {
         }
     }
 }
+
+// layout of the first release, saved without header
+#[derive(Deserialize)]
+struct MachineSaveV0 {
+    display_name: String,
+    mac_address: [u8; 6],
+    boot_graph: BootGraphV0,
+}
+
+impl From<MachineSaveV0> for MachineSave {
+    fn from(old: MachineSaveV0) -> Self {
+        MachineSave {
+            display_name: old.display_name,
+            mac_address: old.mac_address,
+            boot_graph: old.boot_graph.into(),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ServerSaveV0 {
+    // a map laid out as a vec, see BootGraphV0
+    machines: Vec<([u8; 6], MachineSaveV0)>,
+}
+
+#[cfg(test)]
+mod test {
+    use indexmap::IndexMap;
+    use serde::Serialize;
+
+    use super::{AsyncState, SaveError, ServerSave, SAVE_VERSION};
+    use crate::grub::{
+        bootgraph::{BootMethod, OsStatus},
+        machine::Server,
+    };
+
+    const MAC: [u8; 6] = [0x00, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e];
+
+    #[async_std::test]
+    async fn version() {
+        let dir = std::env::temp_dir().join(format!("grub-wol-save-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("grub-wol.sav");
+
+        // no save file yet
+        let server: Server = ServerSave::load(&path).await.unwrap();
+        ServerSave::save(&server, &path).await.unwrap();
+        assert!(ServerSave::load(&path).await.is_ok());
+
+        let mut buf = std::fs::read(&path).unwrap();
+        buf[8..12].copy_from_slice(&(SAVE_VERSION + 1).to_le_bytes());
+        std::fs::write(&path, &buf).unwrap();
+        assert!(matches!(
+            ServerSave::load(&path).await,
+            Err(SaveError::Version(version)) if version == SAVE_VERSION + 1
+        ));
+
+        // neither versioned nor of the first release
+        std::fs::write(&path, b"not a save file").unwrap();
+        assert!(matches!(
+            ServerSave::load(&path).await,
+            Err(SaveError::Unversioned)
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // mirror of BootMethod in the first release, whose variants were reordered since
+    #[derive(Serialize)]
+    enum BootMethodV0 {
+        Wol,
+        Grub(u64),
+        Shutdown,
+    }
+
+    #[async_std::test]
+    async fn migrate() {
+        let dir = std::env::temp_dir().join(format!("grub-wol-migrate-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("grub-wol.sav");
+
+        // laid out as ServerSave of the first release, written by bincode::serialize
+        let values: IndexMap<OsStatus, usize> = [(OsStatus::Down, 0), (OsStatus::Up(1), 1)]
+            .into_iter()
+            .collect();
+        let edges = vec![
+            vec![(1_usize, BootMethodV0::Wol)],
+            vec![(0, BootMethodV0::Shutdown), (1, BootMethodV0::Grub(2))],
+        ];
+        let os: IndexMap<u64, (String,)> = [(1, ("linux".to_owned(),))].into_iter().collect();
+        let machine = ("desk".to_owned(), MAC, ((edges, values), os, 2_u64));
+        let machines: IndexMap<[u8; 6], _> = [(MAC, machine)].into_iter().collect();
+        std::fs::write(&path, bincode::serialize(&(machines,)).unwrap()).unwrap();
+
+        let server: Server = ServerSave::load(&path).await.unwrap();
+        let machines = server.machines.lock().await;
+        let machine = &machines[&MAC];
+        assert_eq!(*machine.display_name.lock().await, "desk");
+        let graph = machine.boot_graph.read().await;
+        assert_eq!(graph.find_os(1).unwrap().display_name, "linux");
+        let edges: Vec<_> = graph.list_edge().collect();
+        assert_eq!(
+            edges,
+            [
+                (&OsStatus::Down, &BootMethod::Wol, &OsStatus::Up(1)),
+                (&OsStatus::Up(1), &BootMethod::Shutdown, &OsStatus::Down),
+                (&OsStatus::Up(1), &BootMethod::Grub(2), &OsStatus::Up(1)),
+            ]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
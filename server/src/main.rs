@@ -8,9 +8,8 @@ pub mod grub;
 mod test;
 mod web;
 use rand::Rng;
-use web::prelude::*;
 
-use crate::web::route;
+use crate::web::{openapi, route, state::AppState};
 
 #[async_std::main]
 async fn main() {
@@ -19,7 +18,13 @@ async fn main() {
     #[cfg(not(debug_assertions))]
     env_logger::builder().filter_level(log::LevelFilter::Info).try_init().unwrap();
 
-    let app_state = AppState::new().await;
+    let app_state = match AppState::new().await {
+        Ok(app_state) => app_state,
+        Err(err) => {
+            log::error!("fail to load {}: {}", grub::machine::SAVE_PATH.display(), err);
+            std::process::exit(1);
+        }
+    };
     app_state.start_grub();
 
     let mut app = tide::new();
@@ -33,14 +38,11 @@ async fn main() {
     ));
 
     app.at("/login").post(route::login);
+    app.at(openapi::ROUTE).get(route::openapi);
     app.at("/api").nest({
         let mut api = tide::with_state(app_state);
         api.with(route::AuthMiddleware);
-        api.at("/op/boot").post(route::boot);
-        api.at("/get/machines").post(route::list_machine);
-        api.at("/get/machine").post(route::info_machine);
-        api.at("/get/oss").post(route::list_os);
-        api.at("/op/new").post(route::new_machine);
+        route::register(&mut api);
         api.at("/auth")
             .get(|_| async { Ok("User is authenticated") });
         api
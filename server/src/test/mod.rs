@@ -43,9 +43,30 @@ async fn test_main() {
                 state.connect().await;
                 continue;
             }
+            server::Packet::BootNext(x) => {
+                let res = state.boot_next(x);
+                let reboot = matches!(res, host::Packet::BootNext(Ok(())));
+                state.conn().send(res).await.unwrap();
+                if reboot {
+                    state.conn().flush().await.unwrap();
+                    state.close().await;
+                    sleep(Duration::from_secs(1)).await;
+                    state.connect().await;
+                }
+                continue;
+            }
             server::Packet::GrubQuery => state.os().respond_grub(),
+            server::Packet::UefiQuery => state.respond_uefi(),
             server::Packet::OsQuery => state.os().respond_os(),
             server::Packet::Ping => todo!(),
+            server::Packet::WakeOnLan(x) => {
+                log::info!("relay magic packet to {:x?}", x.mac_address);
+                host::Packet::WakeOnLan(state::relay_wol(&x).await.map_err(|e| e.to_string()))
+            }
+            server::Packet::Reset(x) => {
+                log::info!("no reset hook for {:x?}", x);
+                host::Packet::Reset(Err("no reset hook configured".to_owned()))
+            }
         };
         state.conn().send(res).await.unwrap();
     }
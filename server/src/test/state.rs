@@ -1,10 +1,14 @@
+use std::time::Duration;
+
 use async_std::net;
-use proto::prelude::{host, server, GrubId, APIVERSION, ID, PROTO_IDENT, SERVER_PORT};
+use proto::discovery;
+use proto::prelude::{host, server, GrubId, UefiId, APIVERSION, ID, PROTO_IDENT, SERVER_PORT};
 use rand::Rng;
 
 use super::transfer::TcpConn;
 
 const OS_VARIETY: usize = 3;
+const DISCOVER: Duration = Duration::from_secs(10);
 
 type Conn = TcpConn<host::Packet, server::Packet>;
 
@@ -13,6 +17,11 @@ pub struct MachineInfo {
     pub packet: Option<Conn>,
     mac_address: [u8; 6],
     oss: Vec<OsInfo>,
+    // firmware boot entries and the os they load, shared by every os
+    uefi: Vec<(UefiId, usize)>,
+    // "host:port" of GRUBWOL_SERVER_ADDR or the local server, discovered by mdns on first connect
+    // like the agent if GRUBWOL_DISCOVER is set
+    server: Option<String>,
 }
 
 impl MachineInfo {
@@ -50,6 +59,14 @@ impl MachineInfo {
             packet: None,
             oss,
             mac_address: rand::thread_rng().gen(),
+            uefi: vec![(0x0000, 0), (0x0001, 2)],
+            server: match std::env::var("GRUBWOL_SERVER_ADDR") {
+                Ok(server) if server.contains(':') => Some(server),
+                Ok(server) => Some(format!("{}:{}", server, SERVER_PORT)),
+                // multicast may not reach the server, like in a container
+                Err(_) if std::env::var_os("GRUBWOL_DISCOVER").is_some() => None,
+                Err(_) => Some(format!("127.0.0.1:{}", SERVER_PORT)),
+            },
         }
     }
     pub fn os(&self) -> &OsInfo {
@@ -73,6 +90,26 @@ impl MachineInfo {
         }
         host::Packet::Reboot
     }
+    pub fn respond_uefi(&self) -> host::Packet {
+        host::Packet::UefiQuery(
+            self.uefi
+                .iter()
+                .map(|&(boot_num, os)| host::UefiInfo {
+                    boot_num,
+                    description: self.oss[os].display_name.clone(),
+                })
+                .collect(),
+        )
+    }
+    pub fn boot_next(&mut self, boot_num: UefiId) -> host::Packet {
+        match self.uefi.iter().find(|(x, _)| *x == boot_num) {
+            Some(&(_, os)) => {
+                self.current_os = os;
+                host::Packet::BootNext(Ok(()))
+            }
+            None => host::Packet::BootNext(Err(format!("no entry Boot{:04X}", boot_num))),
+        }
+    }
     pub async fn close(&mut self) {
         if self.packet.is_none() {
             log::error!("Packet already closed");
@@ -92,12 +129,15 @@ impl MachineInfo {
             version: APIVERSION,
         };
 
-        let conn = Some(
-            net::TcpStream::connect(format!("127.0.0.1:{}", SERVER_PORT))
-                .await
-                .unwrap(),
-        );
-        self.packet = Some(Conn::from_tcp(conn.unwrap()));
+        if self.server.is_none() {
+            let server = discovery::discover(DISCOVER).await.unwrap();
+            log::info!("discovered server at {}", server);
+            self.server = Some(server.to_string());
+        }
+        let conn = net::TcpStream::connect(self.server.as_ref().unwrap())
+            .await
+            .unwrap();
+        self.packet = Some(Conn::from_tcp(conn));
         self.packet
             .as_mut()
             .unwrap()
@@ -129,6 +169,9 @@ impl OsInfo {
     pub fn respond_os(&self) -> host::Packet {
         host::Packet::OsQuery(host::OsQuery {
             display_name: self.display_name.clone(),
+            kernel_version: "6.1.0".to_owned(),
+            distro_id: self.display_name.to_lowercase(),
+            hostname: "dummy".to_owned(),
         })
     }
     pub fn change_uid(&mut self, uid: ID) -> host::Packet {
@@ -136,8 +179,20 @@ impl OsInfo {
         host::Packet::InitId
     }
     pub fn name(&self) -> host::Packet {
-        host::Packet::OsQuery(host::OsQuery {
-            display_name: self.display_name.clone(),
-        })
+        self.respond_os()
+    }
+}
+
+/// magic packet sent on local segment, as a relay of server
+pub async fn relay_wol(req: &server::WakeOnLan) -> std::io::Result<()> {
+    let mut packet = vec![0xff; 6];
+    (0..16).for_each(|_| packet.extend_from_slice(&req.mac_address));
+    if let Some(password) = &req.password {
+        packet.extend_from_slice(password);
     }
+    let target = req.target.unwrap_or(std::net::Ipv4Addr::BROADCAST);
+    let socket = net::UdpSocket::bind((std::net::Ipv4Addr::UNSPECIFIED, 0)).await?;
+    socket.set_broadcast(true)?;
+    socket.send_to(&packet, (target, req.port)).await?;
+    Ok(())
 }
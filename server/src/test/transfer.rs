@@ -18,9 +18,9 @@ lazy_static! {
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("bincode")]
-    BincodeError(#[from] bincode::Error),
+    Bincode(#[from] bincode::Error),
     #[error("Error from smol")]
-    SmolIOError(#[from] async_std::io::Error),
+    Io(#[from] async_std::io::Error),
     #[error("too large entity")]
     TooLargeEntity,
 }
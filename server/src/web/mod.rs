@@ -1,7 +1,3 @@
+pub mod openapi;
 pub mod route;
 pub mod state;
-
-pub mod prelude {
-    use super::*;
-    pub use state::AppState;
-}
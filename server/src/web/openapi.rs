@@ -0,0 +1,167 @@
+/// OpenAPI 3 document of the http api, generated from `grub::api`
+use super::route;
+use crate::grub::api;
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde_json::{json, Map, Value};
+
+pub const ROUTE: &str = "/openapi.json";
+// bodies of requests and responses, as served by route::BinaryResponder
+pub const MEDIA_TYPE: &str = "application/json";
+
+pub(super) fn schema_for<T: JsonSchema>(gen: &mut SchemaGenerator) -> Value {
+    serde_json::to_value(gen.subschema_for::<T>()).unwrap()
+}
+
+pub(super) fn post(summary: &str, req: Option<Value>, res: Value) -> Value {
+    let mut operation = json!({
+        "summary": summary,
+        "responses": {
+            "200": {
+                "description": "OK",
+                "content": { MEDIA_TYPE: { "schema": res } }
+            }
+        }
+    });
+    if let Some(req) = req {
+        operation["requestBody"] = json!({
+            "required": true,
+            "content": { MEDIA_TYPE: { "schema": req } }
+        });
+    }
+    json!({ "post": operation })
+}
+
+pub fn document() -> Value {
+    let mut gen = SchemaSettings::openapi3().into_generator();
+
+    let mut paths = Map::new();
+    paths.insert(
+        "/login".to_owned(),
+        post(
+            "login",
+            Some(schema_for::<api::LoginReq>(&mut gen)),
+            schema_for::<api::LoginRes>(&mut gen),
+        ),
+    );
+    paths.extend(route::documents(&mut gen));
+
+    let schemas: Map<String, Value> = gen
+        .take_definitions()
+        .into_iter()
+        .map(|(name, schema): (String, Schema)| (name, serde_json::to_value(schema).unwrap()))
+        .collect();
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "grub-wol",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": paths,
+        "components": { "schemas": schemas },
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::borrow::Cow;
+
+    use monostate::MustBe;
+    use serde_json::Value;
+
+    use schemars::gen::SchemaSettings;
+
+    use super::{document, route};
+    use crate::grub::api;
+
+    const SNAPSHOT: &str = include_str!("../../openapi.json");
+
+    // regenerate with `UPDATE_OPENAPI=1 cargo test`
+    #[test]
+    fn snapshot() {
+        let generated = serde_json::to_string_pretty(&document()).unwrap() + "\n";
+        if std::env::var_os("UPDATE_OPENAPI").is_some() {
+            std::fs::write(
+                concat!(env!("CARGO_MANIFEST_DIR"), "/openapi.json"),
+                &generated,
+            )
+            .unwrap();
+            return;
+        }
+        assert_eq!(
+            serde_json::from_str::<Value>(SNAPSHOT).unwrap(),
+            serde_json::from_str::<Value>(&generated).unwrap(),
+            "openapi.json is out of date, rerun the test with UPDATE_OPENAPI=1"
+        );
+    }
+
+    // a path listed twice in the route table would be registered twice but documented once
+    #[test]
+    fn paths() {
+        let doc = document();
+        let mut gen = SchemaSettings::openapi3().into_generator();
+        let routes = route::documents(&mut gen);
+        assert_eq!(doc["paths"].as_object().unwrap().len(), routes.len() + 1);
+        assert!(routes.iter().all(|(path, _)| path.starts_with("/api/")));
+    }
+
+    fn kinds(doc: &Value, schema: &str) -> Vec<String> {
+        doc["components"]["schemas"][schema]["anyOf"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|variant| {
+                variant["properties"]["kind"]["enum"][0]
+                    .as_str()
+                    .unwrap()
+                    .to_owned()
+            })
+            .collect()
+    }
+
+    fn kind_of<T: serde::Serialize>(value: &T) -> String {
+        serde_json::to_value(value).unwrap()["kind"]
+            .as_str()
+            .unwrap()
+            .to_owned()
+    }
+
+    #[test]
+    fn kind_tag() {
+        let doc = document();
+
+        let machine_states = [
+            api::MachineState::Down { kind: MustBe!("Down") },
+            api::MachineState::Uninited { kind: MustBe!("Uninited") },
+            api::MachineState::Up { kind: MustBe!("Up"), id: 1 },
+            api::MachineState::Standalone { kind: MustBe!("Standalone"), awake: None },
+        ];
+        assert_eq!(
+            kinds(&doc, "MachineState"),
+            machine_states.iter().map(kind_of).collect::<Vec<_>>()
+        );
+
+        let os_status = [
+            api::OsStatus::Down { kind: MustBe!("Down") },
+            api::OsStatus::Up { kind: MustBe!("Up"), id: 1 },
+        ];
+        assert_eq!(
+            kinds(&doc, "OsStatus"),
+            os_status.iter().map(kind_of).collect::<Vec<_>>()
+        );
+
+        let req = api::BootReq {
+            mac_address: Cow::Owned([0; 6]),
+            os: api::OsStatus::Down { kind: MustBe!("Down") },
+        };
+        let req = serde_json::to_value(req).unwrap();
+        let required = doc["components"]["schemas"]["BootReq"]["required"]
+            .as_array()
+            .unwrap();
+        assert!(required
+            .iter()
+            .all(|field| req.get(field.as_str().unwrap()).is_some()));
+    }
+}
@@ -1,103 +1,235 @@
 use crate::grub::adaptor::Convert;
 
+use super::openapi;
 use super::state::AppState;
 use crate::grub::{self, api};
 use async_trait::async_trait;
 use bincode::config::{Bounded, WithOtherLimit};
 use bincode::{DefaultOptions, Options};
 use futures_lite::Future;
+use schemars::gen::SchemaGenerator;
 use serde::Deserialize;
+use serde_json::Value;
 use tide::{Middleware, Next, Request, Response};
 
+// bytes of a request body
+const PAYLOAD_LIMIT: usize = 1024;
+// labels of an os carry notes, longer ones are refused as invalid by the label itself
+const LABEL_PAYLOAD_LIMIT: usize = 16384;
+
 lazy_static! {
     static ref BINCODE: WithOtherLimit<DefaultOptions, Bounded> = bincode::DefaultOptions::new().with_limit(4096);
     // TODO: replace PASSWORD with env after test
     static ref PASSWORD:&'static str="abc";
+    // bearer token for non-browser clients like grubwolctl, disabled if unset
+    static ref TOKEN: Option<String> = std::env::var("GRUBWOL_TOKEN").ok().filter(|token| !token.is_empty());
 }
 
-pub async fn boot(mut req: Request<AppState>) -> Result<Response, tide::Error> {
-    BinaryResponder::parse(async move {
-        let payload = req.body_bytes().await.map_err(|e| Error::Tide(e))?;
-        let payload: api::BootReq = check_payload(payload)?;
-        let state = req.state();
+/// every route nested under /api, with what it takes and answers
+///
+/// handlers, their registration in main and their openapi paths are all made
+/// from this table, so none of them can drift from the others
+macro_rules! api_routes {
+    (@req $gen:ident) => {
+        None
+    };
+    (@req $gen:ident $req:ty) => {
+        Some(openapi::schema_for::<$req>($gen))
+    };
+    (@limit) => {
+        PAYLOAD_LIMIT
+    };
+    (@limit $limit:expr) => {
+        $limit
+    };
+    ($(
+        #[doc = $summary:literal]
+        $path:literal => fn $handler:ident(
+            $state:ident $(, $payload:ident: $req:ty $(, limit = $limit:expr)?)?
+        ) -> $res:ty $body:block
+    )*) => {
+        $(
+            #[doc = $summary]
+            pub async fn $handler(req: Request<AppState>) -> Result<Response, tide::Error> {
+                BinaryResponder::parse(async move {
+                    #[allow(unused_mut)]
+                    let mut req = req;
+                    $(
+                        let payload = req.body_bytes().await.map_err(Error::Tide)?;
+                        let $payload: $req =
+                            check_payload(payload, api_routes!(@limit $($limit)?))?;
+                    )?
+                    let $state = req.state();
+                    Convert::<$res>::convert($body)
+                        .await
+                        .map_err(Error::Internal)
+                })
+                .await
+            }
+        )*
+
+        pub fn register(api: &mut tide::Server<AppState>) {
+            $(api.at($path).post($handler);)*
+        }
+
+        /// openapi path items of the table, keyed by their full path
+        pub fn documents(gen: &mut SchemaGenerator) -> Vec<(String, Value)> {
+            vec![$((
+                format!("/api{}", $path),
+                openapi::post(
+                    $summary.trim(),
+                    api_routes!(@req gen $($req)?),
+                    openapi::schema_for::<$res>(gen),
+                ),
+            )),*]
+        }
+    };
+}
+
+api_routes! {
+    /// boot into a os
+    "/op/boot" => fn boot(state, payload: api::BootReq) -> api::BootRes {
+        state.grub.boot(payload.os, &payload.mac_address).await
+    }
+    /// get a list of machine
+    "/get/machines" => fn list_machine(state) -> api::MachineList {
+        state.grub.list_machine()
+    }
+    /// get detailed info of a machine
+    "/get/machine" => fn info_machine(state, payload: api::MachineInfoReq) -> api::MachineInfo {
+        state.grub.info_machine(&payload.mac_address).await
+    }
+    /// get a list of os
+    "/get/oss" => fn list_os(state, payload: api::OsListReq) -> api::OsList {
+        state.grub.list_os(&payload.mac_address).await
+    }
+    /// get boot timing of every edge of a machine
+    "/get/edges" => fn list_edge(state, payload: api::EdgeListReq) -> api::EdgeList {
+        state.grub.list_edge(&payload.mac_address).await
+    }
+    /// export boot graph of a machine, for drawing
+    "/get/graph" => fn export_graph(state, payload: api::GraphReq) -> api::GraphExport {
+        state.grub.export_graph(&payload.mac_address).await
+    }
+    /// plan a boot from current os without running it
+    "/get/plan" => fn plan(state, payload: api::PlanReq) -> api::PlanRes {
+        state.grub.plan(payload.os, &payload.mac_address).await
+    }
+    /// get how magic packets of a machine are sent, without its password
+    "/get/wol" => fn wol(state, payload: api::WolReq) -> api::WolRes {
+        state.grub.wol(&payload.mac_address).await
+    }
+    /// get how a machine is checked to be up without its agent
+    "/get/probe" => fn probe(state, payload: api::ProbeReq) -> api::ProbeRes {
+        state.grub.probe(&payload.mac_address).await
+    }
+    /// get the baseboard management controller of a machine, without its password
+    "/get/bmc" => fn bmc(state, payload: api::BmcReq) -> api::BmcRes {
+        state.grub.bmc(&payload.mac_address).await
+    }
+    /// get the smart plug feeding a machine, without its password
+    "/get/plug" => fn plug(state, payload: api::PlugReq) -> api::PlugRes {
+        state.grub.plug(&payload.mac_address).await
+    }
+    /// get what is done when the agent of a machine doesn't answer
+    "/get/escalation" => fn escalation(state, payload: api::EscalationReq) -> api::EscalationRes {
+        state.grub.escalation(&payload.mac_address).await
+    }
+    /// get recent out of band actions taken on a machine
+    "/get/audit" => fn audit(state, payload: api::AuditReq) -> api::AuditRes {
+        state.grub.audit(&payload.mac_address).await
+    }
+    /// init new machine
+    "/op/new" => fn new_machine(state, payload: api::NewMachineReq) -> api::NewMachineRes {
         state
             .grub
-            .boot(payload.os, &payload.mac_address)
+            .init_machine(*payload.mac_address, payload.display_name.to_string())
             .await
-            .convert()
+    }
+    /// rename a machine
+    "/op/rename" => fn rename_machine(state, payload: api::RenameMachineReq) -> api::RenameMachineRes {
+        state
+            .grub
+            .rename(*payload.mac_address, payload.display_name.to_string())
             .await
-            .map_err(|err| Error::Internal(err))
-    })
-    .await
-}
-
-pub async fn list_machine(req: Request<AppState>) -> Result<Response, tide::Error> {
-    BinaryResponder::parse(async move {
-        let state = req.state();
+    }
+    /// forget a machine
+    "/op/forget" => fn forget_machine(state, payload: api::ForgetMachineReq) -> api::ForgetMachineRes {
+        state.grub.forget(*payload.mac_address).await
+    }
+    /// discover os of an inited machine again
+    "/op/renew" => fn renew_machine(state, payload: api::RenewMachineReq) -> api::RenewMachineRes {
+        state.grub.renew(*payload.mac_address).await
+    }
+    /// edit labels of an os
+    "/op/os" => fn edit_os(state, payload: api::EditOsReq, limit = LABEL_PAYLOAD_LIMIT) -> api::EditOsRes {
         state
             .grub
-            .list_machine()
-            .convert()
+            .label_os(*payload.mac_address, payload.id, payload.label.into())
             .await
-            .map_err(|err| Error::Internal(err))
-    })
-    .await
-}
-
-pub async fn info_machine(mut req: Request<AppState>) -> Result<Response, tide::Error> {
-    BinaryResponder::parse(async move {
-        let payload = req.body_bytes().await.map_err(|e| Error::Tide(e))?;
-        let payload: api::MachineInfoReq = check_payload(payload)?;
-        let state = req.state();
+    }
+    /// edit boot graph of a machine by hand
+    "/op/graph" => fn edit_graph(state, payload: api::EditGraphReq) -> api::EditGraphRes {
         state
             .grub
-            .info_machine(&payload.mac_address)
+            .edit_boot_graph(*payload.mac_address, payload.edit)
             .await
-            .convert()
+    }
+    /// set how magic packets of a machine are sent
+    "/op/wol" => fn edit_wol(state, payload: api::EditWolReq) -> api::EditWolRes {
+        state
+            .grub
+            .edit_wol_settings(*payload.mac_address, payload.settings)
             .await
-            .map_err(|err| Error::Internal(err))
-    })
-    .await
-}
-
-pub async fn list_os(mut req: Request<AppState>) -> Result<Response, tide::Error> {
-    BinaryResponder::parse(async move {
-        let payload = req.body_bytes().await.map_err(|e| Error::Tide(e))?;
-        let payload: api::OsListReq = check_payload(payload)?;
-        let state = req.state();
+    }
+    /// register a machine without agent, which can only be woken
+    "/op/standalone" => fn new_standalone(state, payload: api::NewStandaloneReq) -> api::NewStandaloneRes {
         state
             .grub
-            .list_os(&payload.mac_address)
+            .new_standalone_machine(
+                *payload.mac_address,
+                payload.display_name.to_string(),
+                payload.wol,
+                payload.probe,
+            )
             .await
-            .convert()
+    }
+    /// set how a machine is checked to be up without its agent
+    "/op/probe" => fn edit_probe(state, payload: api::EditProbeReq) -> api::EditProbeRes {
+        state
+            .grub
+            .edit_probe_settings(*payload.mac_address, payload.probe)
             .await
-            .map_err(|err| Error::Internal(err))
-    })
-    .await
-}
-
-pub async fn new_machine(mut req: Request<AppState>) -> Result<Response, tide::Error> {
-    BinaryResponder::parse(async move {
-        let payload = req.body_bytes().await.map_err(|e| Error::Tide(e))?;
-        let payload: api::NewMachineReq = check_payload(payload)?;
-        let state = req.state();
+    }
+    /// set the baseboard management controller of a machine
+    "/op/bmc" => fn edit_bmc(state, payload: api::EditBmcReq) -> api::EditBmcRes {
         state
             .grub
-            .init_machine(*payload.mac_address, payload.display_name.to_string())
+            .edit_bmc_settings(*payload.mac_address, payload.settings)
             .await
-            .convert()
+    }
+    /// set the smart plug feeding a machine
+    "/op/plug" => fn edit_plug(state, payload: api::EditPlugReq) -> api::EditPlugRes {
+        state
+            .grub
+            .edit_plug_settings(*payload.mac_address, payload.settings)
             .await
-            .map_err(|err| Error::Internal(err))
-    })
-    .await
+    }
+    /// set what is done when the agent of a machine doesn't answer
+    "/op/escalation" => fn edit_escalation(state, payload: api::EditEscalationReq) -> api::EditEscalationRes {
+        state
+            .grub
+            .edit_escalation_settings(*payload.mac_address, payload.escalation)
+            .await
+    }
 }
 
 pub async fn login(mut req: Request<()>) -> Result<Response, tide::Error> {
     BinaryResponder::parse(async move {
-        let payload = req.body_bytes().await.map_err(|e| Error::Tide(e))?;
-        let payload: api::LoginReq = check_payload(payload)?;
+        let payload = req.body_bytes().await.map_err(Error::Tide)?;
+        let payload: api::LoginReq = check_payload(payload, PAYLOAD_LIMIT)?;
 
-        Ok(bincode::serialize(&if payload.password == *PASSWORD {
+        Ok(serde_json::to_vec(&if payload.password == *PASSWORD {
             req.session_mut()
                 .insert("authed", true)
                 .map_err(|_| Error::Tide(tide::Error::from_str(500, "Error inserting session")))?;
@@ -110,13 +242,26 @@ pub async fn login(mut req: Request<()>) -> Result<Response, tide::Error> {
     .await
 }
 
+pub async fn openapi(_: Request<()>) -> Result<Response, tide::Error> {
+    Ok(Response::builder(200)
+        .body(tide::Body::from_json(&openapi::document())?)
+        .build())
+}
+
 pub struct AuthMiddleware;
 
 #[async_trait]
 impl<State: Clone + Send + Sync + 'static> Middleware<State> for AuthMiddleware {
     async fn handle(&self, req: Request<State>, next: Next<'_, State>) -> tide::Result {
         let authed = req.session().get("authed").unwrap_or(false);
-        if authed {
+        let token = req
+            .header(tide::http::headers::AUTHORIZATION)
+            .and_then(|header| header.as_str().strip_prefix("Bearer "));
+        let token_authed = match (&*TOKEN, token) {
+            (Some(expect), Some(token)) => token_eq(expect.as_bytes(), token.as_bytes()),
+            _ => false,
+        };
+        if authed || token_authed {
             Ok(next.run(req).await)
         } else {
             Err(tide::Error::from_str(403, "Forbidden"))
@@ -124,14 +269,24 @@ impl<State: Clone + Send + Sync + 'static> Middleware<State> for AuthMiddleware
     }
 }
 
-fn check_payload<T>(payload: Vec<u8>) -> Result<T, Error>
+// compare in time independent of where the tokens differ
+fn token_eq(expect: &[u8], token: &[u8]) -> bool {
+    expect.len() == token.len()
+        && expect
+            .iter()
+            .zip(token)
+            .fold(0, |diff, (x, y)| diff | (x ^ y))
+            == 0
+}
+
+fn check_payload<T>(payload: Vec<u8>, limit: usize) -> Result<T, Error>
 where
     T: for<'a> Deserialize<'a>,
 {
-    if payload.len() > 1024 {
+    if payload.len() > limit {
         Err(Error::EntityTooLarge)
     } else {
-        serde_json::from_slice(&payload).map_err(|err| Error::Deserialize(err))
+        serde_json::from_slice(&payload).map_err(Error::Deserialize)
     }
 }
 
@@ -163,7 +318,7 @@ impl BinaryResponder {
         match self {
             BinaryResponder::Ok(x) => Response::builder(200)
                 .body(x)
-                .content_type("application/octet-stream")
+                .content_type(openapi::MEDIA_TYPE)
                 .build(),
             BinaryResponder::Err(err) => match err {
                 Error::Deserialize(err) => {
@@ -213,3 +368,16 @@ impl From<Result<Vec<u8>, Error>> for BinaryResponder {
 // struct RequestCounterMiddleware {
 //     requests_counted: Arc<AtomicUsize>,
 // }
+
+#[cfg(test)]
+mod test {
+    use super::token_eq;
+
+    #[test]
+    fn token() {
+        assert!(token_eq(b"secret", b"secret"));
+        assert!(!token_eq(b"secret", b"secreT"));
+        assert!(!token_eq(b"secret", b"secret2"));
+        assert!(!token_eq(b"secret", b""));
+    }
+}
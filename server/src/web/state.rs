@@ -1,11 +1,10 @@
-use std::{env, path::Path, sync::Arc};
+use std::{env, sync::Arc};
 
 use async_std::task::spawn;
 
 use crate::grub::{machine, prelude as grub};
 
 lazy_static! {
-    static ref SAVE_PATH: &'static Path = Path::new("./");
     static ref PASSWORD: String = env::var("password").unwrap();
 }
 
@@ -15,12 +14,12 @@ pub struct AppState {
 }
 
 impl AppState {
-    pub async fn new() -> AppState {
-        let grub_server = grub::Server::load(&SAVE_PATH).await.unwrap();
+    pub async fn new() -> Result<AppState, machine::Error> {
+        let grub_server = grub::Server::load(&machine::SAVE_PATH).await?;
 
-        AppState {
+        Ok(AppState {
             grub: Arc::new(grub_server),
-        }
+        })
     }
     pub fn start_grub(&self) {
         spawn(machine::Server::start(self.grub.clone()));
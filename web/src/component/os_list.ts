@@ -96,6 +96,14 @@ export class OsList extends LitElement {
     }
     return handler
   }
+  async rename(){
+    let display_name=this.info.display_name
+    let mac_address=this.info.mac_address
+    let res=await axios.post("/api/op/rename", { mac_address ,display_name}, { withCredentials: true })
+    if (res.data.kind!="Success"){
+      alert("rename fail")
+    }
+  }
   change_name(e:Event){
     const input = e.target as HTMLInputElement;
     this.info.display_name=input.value;
@@ -125,7 +133,7 @@ export class OsList extends LitElement {
             ${this.info.state.kind=="Uninited"? html`
             <button class="button is-warning" @click=${this.init}>New Machine</button>
             ` : html`
-            <button class="button is-primary">Save</button>
+            <button class="button is-primary" @click=${this.rename}>Save</button>
             `}
           </div>
         </div>